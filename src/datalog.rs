@@ -0,0 +1,189 @@
+//! In-memory log of recent readings for export over the serial console
+//!
+//! Every reading is kept with its uptime in a ring buffer of `CAPACITY` entries, the oldest are dropped first.
+//! The log is not persisted, flash would not survive the write rate, so it starts empty after every reboot.
+//!
+//! Exports can be pre-averaged into 5 minute or hourly buckets. The aggregates are computed on the fly from the
+//! raw readings, in batches of `EXPORT_BATCH_SIZE` rows so the log is never locked while the rows are written out.
+
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+use embassy_time::Instant;
+use heapless::{Deque, Vec};
+
+/// Number of readings kept, about 85 hours at the 300 second read interval of the normal profile and 17 hours at the
+/// 60 seconds of the performance profile
+const CAPACITY: usize = 1024;
+
+/// Maximum number of rows returned by one `export_batch` call
+pub const EXPORT_BATCH_SIZE: usize = 16;
+
+/// Recent readings, oldest first
+static DATALOG: Mutex<CriticalSectionRawMutex, Deque<Record, CAPACITY>> = Mutex::new(Deque::new());
+
+/// A logged reading
+#[derive(Clone, Copy)]
+struct Record {
+    /// When the reading arrived
+    at: Instant,
+    /// CO2 level in ppm
+    co2: u16,
+    /// Temperature in degrees Celsius (display value with offset)
+    temperature: f32,
+    /// Humidity in percentage (calibrated)
+    humidity: f32,
+}
+
+/// Time resolution of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    /// Every reading as it was logged
+    Raw,
+    /// Readings averaged per 5 minutes
+    FiveMinutes,
+    /// Readings averaged per hour
+    Hour,
+}
+
+impl Granularity {
+    /// Length of the buckets readings are averaged over, in seconds
+    const fn bucket_secs(self) -> u64 {
+        match self {
+            // Readings are seconds apart, so one second buckets keep them separate
+            Self::Raw => 1,
+            Self::FiveMinutes => 5 * 60,
+            Self::Hour => 60 * 60,
+        }
+    }
+
+    /// Name of the granularity as used on the serial console
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Raw => "raw",
+            Self::FiveMinutes => "5m",
+            Self::Hour => "1h",
+        }
+    }
+
+    /// Parses a granularity name as used on the serial console
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Raw, Self::FiveMinutes, Self::Hour]
+            .into_iter()
+            .find(|granularity| granularity.name() == name)
+    }
+}
+
+/// An exported row, a single reading or the average of the readings in a bucket
+#[derive(Clone, Copy)]
+pub struct ExportRow {
+    /// Uptime in seconds at the start of the bucket
+    pub start_secs: u64,
+    /// Average CO2 level in ppm
+    pub co2: u16,
+    /// Average temperature in degrees Celsius
+    pub temperature: f32,
+    /// Average humidity in percentage
+    pub humidity: f32,
+    /// Number of readings averaged
+    pub samples: u16,
+}
+
+/// Running sums of the readings in a bucket
+struct Bucket {
+    /// Uptime in seconds at the start of the bucket
+    start_secs: u64,
+    /// Sum of the CO2 levels
+    co2_sum: u32,
+    /// Sum of the temperatures
+    temperature_sum: f32,
+    /// Sum of the humidities
+    humidity_sum: f32,
+    /// Number of readings summed up
+    samples: u16,
+}
+
+impl Bucket {
+    /// Starts a bucket with its first reading
+    fn new(start_secs: u64, record: &Record) -> Self {
+        Self {
+            start_secs,
+            co2_sum: u32::from(record.co2),
+            temperature_sum: record.temperature,
+            humidity_sum: record.humidity,
+            samples: 1,
+        }
+    }
+
+    /// Adds a reading to the bucket
+    fn add(&mut self, record: &Record) {
+        self.co2_sum += u32::from(record.co2);
+        self.temperature_sum += record.temperature;
+        self.humidity_sum += record.humidity;
+        self.samples += 1;
+    }
+
+    /// Averages the readings of the bucket
+    #[allow(clippy::cast_possible_truncation)]
+    fn row(&self) -> ExportRow {
+        let samples = f32::from(self.samples);
+        ExportRow {
+            start_secs: self.start_secs,
+            co2: (self.co2_sum / u32::from(self.samples)) as u16,
+            temperature: self.temperature_sum / samples,
+            humidity: self.humidity_sum / samples,
+            samples: self.samples,
+        }
+    }
+}
+
+/// Logs a reading, dropping the oldest one when the log is full
+pub async fn record(co2: u16, temperature: f32, humidity: f32) {
+    let mut log = DATALOG.lock().await;
+    if log.is_full() {
+        log.pop_front();
+    }
+    let _ = log.push_back(Record {
+        at: Instant::now(),
+        co2,
+        temperature,
+        humidity,
+    });
+}
+
+/// Drops all logged readings
+pub async fn clear() {
+    DATALOG.lock().await.clear();
+}
+
+/// Gets the next rows of an export, starting after the bucket that started at `after_secs`
+/// An empty batch means the export is complete. The last row may be a bucket that is still filling up.
+pub async fn export_batch(granularity: Granularity, after_secs: Option<u64>) -> Vec<ExportRow, EXPORT_BATCH_SIZE> {
+    let bucket_secs = granularity.bucket_secs();
+    let log = DATALOG.lock().await;
+    let mut rows = Vec::new();
+    let mut bucket: Option<Bucket> = None;
+
+    for record in log.iter() {
+        let start_secs = record.at.as_secs() / bucket_secs * bucket_secs;
+        if after_secs.is_some_and(|after_secs| start_secs <= after_secs) {
+            continue;
+        }
+        match bucket {
+            Some(ref mut bucket) if bucket.start_secs == start_secs => bucket.add(record),
+            _ => {
+                if let Some(finished) = bucket.take() {
+                    // A full batch ends here, the next one picks up from its last row
+                    if rows.push(finished.row()).is_err() {
+                        return rows;
+                    }
+                }
+                bucket = Some(Bucket::new(start_secs, record));
+            }
+        }
+    }
+    drop(log);
+
+    if let Some(finished) = bucket {
+        let _ = rows.push(finished.row());
+    }
+    rows
+}
@@ -0,0 +1,118 @@
+//! Demo playback of a recorded trace of readings, replacing the sensor task with the `demo` feature
+//!
+//! The trace walks through a day in an office: fresh air in the morning, the CO2 level climbing above the alarm
+//! threshold while the room fills up, a window opened to air it out (dropping temperature and humidity) and a
+//! quiet afternoon. It is replayed in a loop at `DEMO_INTERVAL`, much faster than real readings, so every display
+//! mode and threshold shows up within minutes and a screenshot at a given point of the trace is reproducible.
+//!
+//! The readings pass through the orchestrator like real ones, with the temperature offset and the VOC kind of the
+//! current settings applied.
+
+use defmt::info;
+use embassy_time::{Duration, Timer};
+use ens160_aq::data::AirQualityIndex;
+
+use crate::{
+    event::{Event, send_event},
+    sensor::dew_point,
+    storage_mode,
+    system_state::SYSTEM_STATE,
+    watchdog::{TaskId, report_task_success},
+};
+
+/// Time between two replayed readings
+const DEMO_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A recorded reading
+struct DemoReading {
+    /// CO2 level in ppm
+    co2: u16,
+    /// Temperature in degrees Celsius, as displayed
+    temperature: f32,
+    /// Humidity in percentage
+    humidity: f32,
+    /// Ethanol or TVOC level in ppb
+    voc: u16,
+    /// Air quality index
+    air_quality: AirQualityIndex,
+}
+
+/// Shorthand for the entries of `DEMO_TRACE`
+const fn reading(co2: u16, temperature: f32, humidity: f32, voc: u16, air_quality: AirQualityIndex) -> DemoReading {
+    DemoReading {
+        co2,
+        temperature,
+        humidity,
+        voc,
+        air_quality,
+    }
+}
+
+/// The replayed trace, in order
+const DEMO_TRACE: [DemoReading; 24] = [
+    // Morning, fresh air
+    reading(430, 20.5, 42.0, 40, AirQualityIndex::Excellent),
+    reading(455, 20.8, 42.5, 45, AirQualityIndex::Excellent),
+    reading(520, 21.2, 43.0, 60, AirQualityIndex::Good),
+    // The room fills up
+    reading(680, 21.8, 45.0, 110, AirQualityIndex::Good),
+    reading(850, 22.3, 47.0, 180, AirQualityIndex::Moderate),
+    reading(1020, 22.8, 49.0, 260, AirQualityIndex::Moderate),
+    reading(1240, 23.2, 51.0, 380, AirQualityIndex::Poor),
+    reading(1460, 23.6, 53.0, 520, AirQualityIndex::Poor),
+    reading(1620, 23.9, 54.5, 700, AirQualityIndex::Unhealthy),
+    reading(1780, 24.1, 55.0, 950, AirQualityIndex::Unhealthy),
+    // Window opened
+    reading(1310, 22.4, 50.0, 640, AirQualityIndex::Poor),
+    reading(880, 20.1, 46.0, 320, AirQualityIndex::Moderate),
+    reading(610, 18.6, 43.0, 150, AirQualityIndex::Good),
+    reading(470, 17.9, 41.0, 70, AirQualityIndex::Excellent),
+    // Window closed, the room warms up again
+    reading(480, 18.8, 41.5, 65, AirQualityIndex::Excellent),
+    reading(530, 19.9, 42.5, 80, AirQualityIndex::Good),
+    reading(590, 20.8, 43.5, 95, AirQualityIndex::Good),
+    // Quiet afternoon, one more person drops by
+    reading(640, 21.3, 44.0, 110, AirQualityIndex::Good),
+    reading(760, 21.6, 45.5, 150, AirQualityIndex::Good),
+    reading(930, 21.9, 47.0, 210, AirQualityIndex::Moderate),
+    reading(1060, 22.1, 48.0, 240, AirQualityIndex::Moderate),
+    // Evening, the room empties
+    reading(870, 21.7, 46.5, 170, AirQualityIndex::Moderate),
+    reading(650, 21.2, 44.5, 100, AirQualityIndex::Good),
+    reading(500, 20.7, 43.0, 60, AirQualityIndex::Good),
+];
+
+/// Replays `DEMO_TRACE` in a loop, standing in for the sensor task towards the watchdog
+#[embassy_executor::task]
+pub async fn demo_task() {
+    info!(
+        "Demo playback of {} readings every {}s",
+        DEMO_TRACE.len(),
+        DEMO_INTERVAL.as_secs()
+    );
+
+    loop {
+        for demo_reading in &DEMO_TRACE {
+            storage_mode::park_if_active("Demo").await;
+
+            let settings = SYSTEM_STATE.lock().await.get_settings();
+            send_event(Event::SensorData {
+                temperature: demo_reading.temperature,
+                raw_temperature: demo_reading.temperature - settings.temperature_offset,
+                humidity: demo_reading.humidity,
+                raw_humidity: demo_reading.humidity,
+                dew_point: dew_point(demo_reading.temperature, demo_reading.humidity),
+                co2: demo_reading.co2,
+                co2_suspect: false,
+                voc: demo_reading.voc,
+                voc_kind: settings.voc_kind,
+                air_quality: demo_reading.air_quality,
+                settling: false,
+            })
+            .await;
+            report_task_success(TaskId::Sensor).await;
+
+            Timer::after(DEMO_INTERVAL).await;
+        }
+    }
+}
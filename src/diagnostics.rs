@@ -0,0 +1,231 @@
+//! Diagnostics counters shared between tasks, used to judge hardware reliability in the field
+
+use defmt::{Format, info};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+use embassy_time::{Duration, Instant};
+
+use crate::i2c_speed;
+#[cfg(feature = "debug-serial")]
+use crate::watchdog::TaskId;
+
+/// Global diagnostics state
+pub static DIAGNOSTICS: Mutex<CriticalSectionRawMutex, Diagnostics> = Mutex::new(Diagnostics::new());
+
+/// Devices on the shared I2C bus
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Format)]
+pub enum BusDevice {
+    /// AHT21 temperature and humidity sensor
+    Aht21,
+    /// ENS160 air quality sensor
+    Ens160,
+    /// SSD1306 display
+    Display,
+    /// DS3231 real time clock
+    #[cfg(feature = "rtc")]
+    Rtc,
+}
+
+/// Number of devices on the shared I2C bus
+const BUS_DEVICE_COUNT: usize = if cfg!(feature = "rtc") { 4 } else { 3 };
+
+/// Temperature and humidity compensation last written to the ENS160
+#[derive(Debug, Clone, Copy)]
+pub struct Ens160Compensation {
+    /// Temperature in degrees Celsius as written, the raw AHT21 value without the display offset
+    pub temperature: f32,
+    /// Humidity in percent before rounding, calibrated and with the user offset
+    pub humidity: f32,
+    /// Humidity in whole percent as written, the ENS160 driver takes no fraction
+    pub written_humidity: u16,
+    /// How `humidity` was rounded to `written_humidity`, e.g. "truncated"
+    pub rounding: &'static str,
+}
+
+/// Diagnostics collected since boot
+#[derive(Debug, Clone, Copy)]
+pub struct Diagnostics {
+    /// Cumulative I2C error count per device, indexed by `BusDevice`
+    i2c_errors: [u32; BUS_DEVICE_COUNT],
+    /// Since when the ENS160 has continuously reported valid data (normal operation), `None` if it currently does not
+    ens160_valid_since: Option<Instant>,
+    /// Number of display flushes (e-paper refreshes)
+    redraws: u32,
+    /// Number of redraws merged into another flush by the coalescing window
+    coalesced_redraws: u32,
+    /// Compensation last written to the ENS160, `None` before the first write
+    ens160_compensation: Option<Ens160Compensation>,
+}
+
+impl Diagnostics {
+    /// Creates diagnostics with all counters at zero
+    const fn new() -> Self {
+        Self {
+            i2c_errors: [0; BUS_DEVICE_COUNT],
+            ens160_valid_since: None,
+            redraws: 0,
+            coalesced_redraws: 0,
+            ens160_compensation: None,
+        }
+    }
+
+    /// Gets the cumulative I2C error count of a device
+    pub const fn i2c_errors(&self, device: BusDevice) -> u32 {
+        self.i2c_errors[device as usize]
+    }
+
+    /// Counts a failed I2C transaction with a device, returns its cumulative error count
+    const fn count_i2c_error(&mut self, device: BusDevice) -> u32 {
+        let count = &mut self.i2c_errors[device as usize];
+        *count = count.saturating_add(1);
+        *count
+    }
+
+    /// Gets how long the ENS160 has continuously reported valid data, `None` if it currently does not
+    pub fn ens160_valid_for(&self) -> Option<Duration> {
+        self.ens160_valid_since.map(|since| since.elapsed())
+    }
+
+    /// Gets the number of display flushes (e-paper refreshes)
+    pub const fn redraws(&self) -> u32 {
+        self.redraws
+    }
+
+    /// Gets the number of redraws merged into another flush by the coalescing window
+    pub const fn coalesced_redraws(&self) -> u32 {
+        self.coalesced_redraws
+    }
+
+    /// Gets the compensation last written to the ENS160, `None` before the first write
+    pub const fn ens160_compensation(&self) -> Option<Ens160Compensation> {
+        self.ens160_compensation
+    }
+
+    /// Logs all counters as a labeled block
+    pub fn log(&self) {
+        info!(
+            "Diagnostics - I2C errors: AHT21: {}, ENS160: {}, Display: {}",
+            self.i2c_errors(BusDevice::Aht21),
+            self.i2c_errors(BusDevice::Ens160),
+            self.i2c_errors(BusDevice::Display)
+        );
+        #[cfg(feature = "rtc")]
+        info!("Diagnostics - I2C errors: RTC: {}", self.i2c_errors(BusDevice::Rtc));
+        if let Some(valid_for) = self.ens160_valid_for() {
+            info!("Diagnostics - ENS160 valid for {} s", valid_for.as_secs());
+        } else {
+            info!("Diagnostics - ENS160 not valid");
+        }
+        info!(
+            "Diagnostics - Display: {} redraws, {} coalesced",
+            self.redraws, self.coalesced_redraws
+        );
+        if let Some(compensation) = self.ens160_compensation {
+            info!(
+                "Diagnostics - ENS160 compensation: {}°C, {}% ({} from {}%)",
+                compensation.temperature, compensation.written_humidity, compensation.rounding, compensation.humidity
+            );
+        }
+    }
+}
+
+/// Records a failed I2C transaction with a device, repeated errors lower the bus clock
+pub async fn record_i2c_error(device: BusDevice) {
+    let count = DIAGNOSTICS.lock().await.count_i2c_error(device);
+    info!("I2C error on {}: {} total", device, count);
+    i2c_speed::record_bus_error().await;
+}
+
+/// Records the ENS160 validity of the latest reading, starting the valid period on a transition to valid and
+/// ending it on any invalid reading
+pub async fn record_ens160_validity(valid: bool) {
+    let mut diagnostics = DIAGNOSTICS.lock().await;
+    match (valid, diagnostics.ens160_valid_since) {
+        (true, None) => {
+            info!("ENS160 reports valid data");
+            diagnostics.ens160_valid_since = Some(Instant::now());
+        }
+        (false, Some(_)) => {
+            info!("ENS160 data no longer valid");
+            diagnostics.ens160_valid_since = None;
+        }
+        _ => {}
+    }
+}
+
+/// Records the compensation written to the ENS160, to compare it with the displayed values
+pub async fn record_ens160_compensation(compensation: Ens160Compensation) {
+    DIAGNOSTICS.lock().await.ens160_compensation = Some(compensation);
+}
+
+/// Records a display flush, together with the number of redraws that were merged into it
+pub async fn record_redraw(coalesced: u32) {
+    let mut diagnostics = DIAGNOSTICS.lock().await;
+    diagnostics.redraws = diagnostics.redraws.saturating_add(1);
+    diagnostics.coalesced_redraws = diagnostics.coalesced_redraws.saturating_add(coalesced);
+}
+
+/// Gets a copy of the current diagnostics
+pub async fn get_diagnostics() -> Diagnostics {
+    *DIAGNOSTICS.lock().await
+}
+
+/// Loop iteration timing per task, indexed by `TaskId`
+#[cfg(feature = "debug-serial")]
+static LOOP_TIMINGS: Mutex<CriticalSectionRawMutex, [LoopTiming; 5]> = Mutex::new([LoopTiming::new(); 5]);
+
+/// Duration statistics of the work done in one iteration of a task loop, waiting for the next iteration excluded
+#[cfg(feature = "debug-serial")]
+#[derive(Debug, Clone, Copy)]
+pub struct LoopTiming {
+    /// Number of timed iterations
+    pub count: u32,
+    /// Shortest iteration
+    pub min: Duration,
+    /// Longest iteration
+    pub max: Duration,
+    /// Sum of all iterations, for the average
+    total: Duration,
+}
+
+#[cfg(feature = "debug-serial")]
+impl LoopTiming {
+    /// Creates timing statistics without any iterations
+    const fn new() -> Self {
+        Self {
+            count: 0,
+            min: Duration::MAX,
+            max: Duration::from_ticks(0),
+            total: Duration::from_ticks(0),
+        }
+    }
+
+    /// Gets the average iteration, zero without iterations
+    pub fn average(&self) -> Duration {
+        if self.count == 0 {
+            Duration::from_ticks(0)
+        } else {
+            self.total / self.count
+        }
+    }
+
+    /// Adds the duration of one iteration
+    fn record(&mut self, duration: Duration) {
+        self.count = self.count.saturating_add(1);
+        self.min = self.min.min(duration);
+        self.max = self.max.max(duration);
+        self.total += duration;
+    }
+}
+
+/// Records the duration of one loop iteration of a task, measured from `start`
+#[cfg(feature = "debug-serial")]
+pub async fn record_loop_time(task_id: TaskId, start: Instant) {
+    let duration = start.elapsed();
+    LOOP_TIMINGS.lock().await[task_id as usize].record(duration);
+}
+
+/// Gets the loop timing of a task
+#[cfg(feature = "debug-serial")]
+pub async fn get_loop_timing(task_id: TaskId) -> LoopTiming {
+    LOOP_TIMINGS.lock().await[task_id as usize]
+}
@@ -3,46 +3,334 @@
 use core::fmt::Write;
 
 use defmt::{Debug2Format, error, info};
-use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice;
-use embassy_rp::{
-    i2c::{Async, I2c},
-    peripherals::I2C0,
-};
-use embassy_sync::{
-    blocking_mutex::raw::{CriticalSectionRawMutex, NoopRawMutex},
-    channel::Channel,
-};
-use embassy_time::{Duration, Timer};
+use embassy_futures::select::{Either, select};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use embassy_time::{Duration, Instant, Timer};
 use embedded_graphics::{
     image::Image,
     mono_font::{
         MonoTextStyle, MonoTextStyleBuilder,
-        ascii::{FONT_5X8, FONT_6X13, FONT_8X13_BOLD},
+        ascii::{FONT_5X8, FONT_6X13, FONT_6X13_BOLD, FONT_8X13_BOLD, FONT_10X20},
     },
     pixelcolor::{BinaryColor, Gray8},
     prelude::*,
-    primitives::{Line, PrimitiveStyle, Rectangle},
-    text::{Baseline, Text},
+    primitives::{Line, PrimitiveStyle, Rectangle, Triangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
 };
 use ens160_aq::data::AirQualityIndex;
 use heapless::String;
+#[cfg(target_os = "none")]
 use panic_probe as _;
+#[cfg(all(not(feature = "epaper"), target_os = "none"))]
 use ssd1306_async::{I2CDisplayInterface, Ssd1306, prelude::*};
 use tinybmp::Bmp;
 
+#[cfg(feature = "debug-serial")]
+use crate::diagnostics::record_loop_time;
+#[cfg(feature = "rtc")]
+use crate::rtc;
 use crate::{
     FIRMWARE_VERSION,
+    diagnostics::{BusDevice, Diagnostics, get_diagnostics},
     event::{Event, send_event},
-    system_state::{BatteryLevel, DisplayMode, SYSTEM_STATE, SensorData},
-    watchdog::{TaskId, report_task_failure, report_task_success},
+    settings_store, storage_mode,
+    system_state::{
+        BatteryLevel, CO2_ALARM_THRESHOLD, CO2_EXCEEDANCE_THRESHOLD, Co2Trend, DisplayMode, ExtremesScope, FocusMetric,
+        ReadingQuality, SYSTEM_STATE, SensorData, SensorFault, SystemState, TemperatureUnit, VocKind,
+    },
+    watchdog::{ResetReason, TaskId, report_task_failure, report_task_success, reset_reason},
+};
+#[cfg(all(not(feature = "epaper"), target_os = "none"))]
+use crate::{
+    I2cBusDevice,
+    diagnostics::{record_i2c_error, record_redraw},
 };
 
+#[cfg(all(feature = "epaper", target_os = "none"))]
+mod epaper;
+
+#[cfg(all(feature = "epaper", target_os = "none"))]
+pub use epaper::{EpaperHardware, SPI_FREQUENCY as EPAPER_SPI_FREQUENCY, display_task};
+
+/// Hardware driven by the display task, the SSD1306 on the shared I2C bus
+#[cfg(all(not(feature = "epaper"), target_os = "none"))]
+pub type DisplayHardware = I2cBusDevice;
+
+/// Hardware driven by the display task, the e-paper panel on its own SPI bus
+#[cfg(all(feature = "epaper", target_os = "none"))]
+pub type DisplayHardware = EpaperHardware;
+
 /// Channel for triggering state updates  
 pub static DISPLAY_CHANNEL: Channel<CriticalSectionRawMutex, DisplayCommand, 3> = Channel::new();
 
 /// Duration for toggling display modes
 static TOGGLE_MODE: Duration = Duration::from_secs(10);
 
+/// How long the display stays frozen on the current screen unless it is unfrozen before
+/// Checked every `TOGGLE_MODE`, so the freeze may last up to that much longer
+pub const DISPLAY_FREEZE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Whether the battery icon is still updated while the display is frozen
+const REDRAW_BATTERY_WHILE_FROZEN: bool = true;
+
+/// How the ENS160 air quality index is shown on the raw data screen
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[allow(dead_code)]
+enum AqiDisplay {
+    /// Only the label, e.g. "Good"
+    Label,
+    /// The 1-5 index together with the label, e.g. "AQI 2 (Good)"
+    LabelAndIndex,
+    /// Only a face icon, happy for excellent and good, neutral for moderate, sad for poor and unhealthy air
+    Icon,
+    /// The face icon followed by the label
+    IconAndLabel,
+}
+
+/// Selected style for the air quality index
+const AQI_DISPLAY: AqiDisplay = AqiDisplay::Label;
+
+/// Width of the air quality face icons
+const AQI_ICON_WIDTH: i32 = 13;
+
+/// Metric that can be emphasized on the raw data screen
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[allow(dead_code)]
+enum EmphasizedMetric {
+    /// The CO2 line
+    Co2,
+    /// The ethanol or TVOC line
+    Voc,
+    /// The temperature line
+    Temperature,
+    /// The humidity line
+    Humidity,
+}
+
+/// Metric drawn inverted (dark text on a lit background) on the raw data screen to make it stand out, `None` draws
+/// all metrics alike. E.g. `Some(EmphasizedMetric::Co2)`. Drawn in the accent color instead with `ColorScheme::Accent`
+/// on a target that has one
+const EMPHASIZED_METRIC: Option<EmphasizedMetric> = None;
+
+/// Colors used for emphasis and alarm states
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[allow(dead_code)]
+enum ColorScheme {
+    /// Emphasis only by inverting the emphasized metric, everything else in the foreground color
+    Mono,
+    /// The CO2 value at or above the alarm threshold, its bars in the CO2 history and the emphasized metric are drawn
+    /// in the accent color of the target (e.g. red on a black/white/red panel). Targets without an accent color, like
+    /// the SSD1306, invert the text and fill the bars solid instead
+    Accent,
+}
+
+/// Selected color scheme
+const COLOR_SCHEME: ColorScheme = ColorScheme::Mono;
+
+/// Display brightness in normal operation, as SSD1306 contrast from 0 (dimmest) to 255 (brightest)
+#[cfg(not(feature = "epaper"))]
+const DISPLAY_BRIGHTNESS: u8 = 0;
+
+/// Display brightness in night mode, where drawing is additionally dithered to light fewer pixels
+#[cfg(not(feature = "epaper"))]
+const NIGHT_MODE_BRIGHTNESS: u8 = 0;
+
+/// Lowest brightness any brightness setting may result in, e.g. raised for an enclosure that diffuses the display
+#[cfg(not(feature = "epaper"))]
+const MIN_BRIGHTNESS: u8 = 0;
+
+/// Highest brightness any brightness setting may result in, e.g. lowered to keep a bedroom display dim
+#[cfg(not(feature = "epaper"))]
+const MAX_BRIGHTNESS: u8 = 255;
+
+// The defaults are the bounds of the whole range
+#[cfg(not(feature = "epaper"))]
+#[allow(clippy::absurd_extreme_comparisons)]
+const _: () = assert!(
+    MIN_BRIGHTNESS <= MAX_BRIGHTNESS,
+    "MIN_BRIGHTNESS must not exceed MAX_BRIGHTNESS"
+);
+
+/// Whether to show the age of the last reading below the battery icon on the raw data screen
+const SHOW_READING_AGE: bool = true;
+
+/// How long a redraw waits for further commands before the display is flushed, commands arriving in the meantime are
+/// drawn into the same flush. The OLED flushes within milliseconds, a short window is enough to merge bursts
+#[cfg(not(feature = "epaper"))]
+const REDRAW_COALESCE_WINDOW: Duration = Duration::from_millis(50);
+
+/// How often the reading age is refreshed when no other command redraws the screen
+const READING_AGE_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Minimal screen replacing the data screens on battery once nothing happened for `IDLE_SCREEN_TIMEOUT`, against
+/// burn-in of the static content between infrequent readings
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[allow(dead_code)]
+enum IdleScreen {
+    /// The data screens stay until the next reading
+    Off,
+    /// The time in large digits, needs the `rtc` feature and a set clock, the logo is shown otherwise
+    Clock,
+    /// The name of the device and the firmware version
+    Logo,
+}
+
+/// Selected idle screen, the OLED only as the e-paper panel does not burn in
+const IDLE_SCREEN: IdleScreen = IdleScreen::Off;
+
+/// How long after the last reading or redraw the idle screen is shown, it composes with the display timeout of the
+/// power profile, a longer timeout switches the display off first
+#[cfg(not(feature = "epaper"))]
+const IDLE_SCREEN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often the idle screen is redrawn, advancing the clock and moving the content a little
+#[cfg(not(feature = "epaper"))]
+const IDLE_SCREEN_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Number of decimals of the humidity on the raw data screen, 0 hides the jitter in the tenths
+const HUMIDITY_DECIMALS: usize = 1;
+
+const _: () = assert!(HUMIDITY_DECIMALS <= 1, "HUMIDITY_DECIMALS must be 0 or 1");
+
+/// Whether to show the battery percentage as text below the battery icon
+pub const SHOW_BATTERY_PERCENTAGE: bool = false;
+
+/// Height of the battery area below its top edge: the icon, plus the percentage text if shown
+const BATTERY_AREA_HEIGHT: i32 = if SHOW_BATTERY_PERCENTAGE { 21 } else { 11 };
+
+/// Luma from which a pixel of the grayscale icons is lit
+/// The bundled icons are pure black and white, a lower value keeps gray or anti-aliased icons from rendering faintly
+const ICON_LUMA_THRESHOLD: u8 = 128;
+
+/// Whether to draw a trend arrow behind the CO2 value
+const SHOW_CO2_TREND: bool = true;
+
+/// Whether to show a Low/Mod/High band behind the ethanol or TVOC value on the raw data screen
+const SHOW_VOC_BAND: bool = true;
+
+/// Whether the humidity line of the raw data screen shows the dew point next to the calibrated humidity, rather than
+/// the raw and calibrated humidity
+const SHOW_DEW_POINT: bool = true;
+
+/// Ethanol level in ppb from which the band is moderate
+const ETOH_MODERATE_PPB: u16 = 200;
+
+/// Ethanol level in ppb from which the band is high
+const ETOH_HIGH_PPB: u16 = 1000;
+
+/// TVOC level in ppb from which the band is moderate (the "moderate" level of the usual TVOC guidelines)
+const TVOC_MODERATE_PPB: u16 = 220;
+
+/// TVOC level in ppb from which the band is high (the "poor" level of the usual TVOC guidelines)
+const TVOC_HIGH_PPB: u16 = 660;
+
+/// Whether to mark the CO2 value as approximate ("~") while the ENS160 settles after a compensation change
+const SHOW_SETTLING_INDICATOR: bool = true;
+
+/// Whether to label the time axis of the history charts with the age of the oldest bar and "now"
+/// The age is derived from the read interval of the current power profile
+const SHOW_CHART_TIME_LABELS: bool = true;
+
+/// Height of the time axis labels below the history charts
+const CHART_TIME_LABEL_HEIGHT: i32 = 8;
+
+/// Height of the seven-segment digits of the large CO2 screen
+const CO2_LARGE_DIGIT_HEIGHT: i32 = 36;
+
+/// Widest seven-segment digit of the large CO2 screen, five digit levels get narrower digits to fit
+const CO2_LARGE_DIGIT_WIDTH: i32 = 20;
+
+/// Thickness of the segments of the large CO2 screen
+const CO2_LARGE_SEGMENT: i32 = 4;
+
+/// Gap between the digits of the large CO2 screen
+const CO2_LARGE_DIGIT_GAP: i32 = 4;
+
+/// Segments lit for the digits 0 to 9, bit 0 to 6 for the segments a (top) to g (middle) clockwise
+const SEVEN_SEGMENT_DIGITS: [u8; 10] = [0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F];
+
+/// How the bars of the CO2 history chart are scaled
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[allow(dead_code)]
+enum ChartScale {
+    /// Bars proportional to the level between the lowest and the highest level of the history
+    Linear,
+    /// Bars proportional to the logarithm of the level from `CO2_CHART_LOG_FLOOR`, spreading out the differences at
+    /// normal indoor levels next to a peak of several thousand ppm
+    Logarithmic,
+}
+
+/// Scaling of the CO2 history chart
+const CO2_CHART_SCALE: ChartScale = ChartScale::Linear;
+
+/// Level in ppm at the bottom of the logarithmic CO2 history chart, unless the history goes lower
+const CO2_CHART_LOG_FLOOR: u16 = 400;
+
+/// Humidity at the bottom of the humidity history chart, lower values are drawn as empty bars
+const HUMIDITY_CHART_MIN: u8 = 20;
+
+/// Humidity at the top of the humidity history chart, higher values are drawn as full bars
+const HUMIDITY_CHART_MAX: u8 = 80;
+
+const _: () = assert!(
+    HUMIDITY_CHART_MIN < HUMIDITY_CHART_MAX,
+    "HUMIDITY_CHART_MIN must be below HUMIDITY_CHART_MAX"
+);
+
+/// Which minimum and maximum readings the stats screen shows, those of the current session or those of all time
+const STATS_EXTREMES: ExtremesScope = ExtremesScope::Session;
+
+/// Top of the low battery warning banner, it covers the bottom line of the main area down to the display edge
+const LOW_BATTERY_BANNER_TOP: i32 = 50;
+
+/// Whether a "!" below the reading age marks that a recent reading was of poor quality (warmup or suspect)
+const SHOW_QUALITY_INDICATOR: bool = true;
+
+/// Flash pattern inverting the whole display when an alarm is raised, `None` disables it
+/// E.g. `Some(AlarmFlash { count: 3, interval: Duration::from_millis(500) })`
+#[cfg(not(feature = "epaper"))]
+const ALARM_FLASH: Option<AlarmFlash> = None;
+
+/// Flash pattern of the display on a raised alarm
+#[cfg(not(feature = "epaper"))]
+#[derive(Debug, Clone, Copy)]
+#[allow(dead_code)]
+struct AlarmFlash {
+    /// Number of flashes, the display ends up in normal (not inverted) mode after the last one
+    count: u8,
+    /// How long the display stays inverted, and then normal, in each flash
+    interval: Duration,
+}
+
+/// How much the boot screen tells about why the system was reset, a normal power-on always goes straight to the
+/// startup screen
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[allow(dead_code)]
+enum BootReasonDetail {
+    /// No boot screen
+    Off,
+    /// Only the reason, e.g. "Watchdog reset"
+    Reason,
+    /// The reason and, for a watchdog reset, the tasks that had gone stale
+    Culprits,
+}
+
+/// Selected detail of the boot screen
+const BOOT_REASON_DETAIL: BootReasonDetail = BootReasonDetail::Culprits;
+
+/// How long the boot screen stays on before the startup screen
+const BOOT_REASON_TIME: Duration = Duration::from_secs(2);
+
+/// Maximum length of the stale task list on the boot screen
+const BOOT_REASON_CAPACITY: usize = 64;
+
+/// Characters per line of the stale task list in the small font
+const BOOT_REASON_LINE_CHARS: usize = 25;
+
+/// How long the storage mode notice stays on screen before the display is switched off
+#[cfg(not(feature = "epaper"))]
+const STORAGE_MODE_NOTICE_TIME: Duration = Duration::from_secs(10);
+
 /// Commands for controlling the display
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum DisplayCommand {
@@ -56,12 +344,22 @@ pub enum DisplayCommand {
         humidity: f32,
         /// Raw humidity in percentage (uncalibrated)
         raw_humidity: f32,
+        /// Dew point in degrees Celsius, from the display temperature and the calibrated humidity
+        dew_point: f32,
         /// CO2 level in ppm
         co2: u16,
-        /// Ethanol level in ppb
-        etoh: u16,
+        /// Whether the raw CO2 level was implausibly low (below the atmospheric baseline)
+        co2_suspect: bool,
+        /// Ethanol or TVOC level in ppb, depending on `voc_kind`
+        voc: u16,
+        /// Which figure `voc` holds
+        voc_kind: VocKind,
         /// Air quality index
         air_quality: AirQualityIndex,
+        /// Whether the ENS160 is still settling after a large compensation change
+        settling: bool,
+        /// How trustworthy the reading is
+        quality: ReadingQuality,
     },
     /// Update the battery charging state
     UpdateBatteryCharging,
@@ -69,6 +367,33 @@ pub enum DisplayCommand {
     UpdateBatteryPercentage(u8),
     /// Toggle display mode (triggered by mode switching task)
     ToggleMode,
+    /// Night mode was switched on or off, brightness and rendering need updating
+    NightModeChanged,
+    /// The sensors entered or left the error standby
+    SensorErrorChanged,
+    /// The low battery warning was raised or cleared
+    BatteryWarningChanged,
+    /// Seconds left of the ENS160 warmup at startup, shown until the first reading
+    WarmupProgress(u16),
+    /// The display freeze ended, redraw what was held back while frozen
+    Unfrozen,
+    /// The CO2 history was cleared, the chart starts over
+    HistoryCleared,
+    /// Clear the whole display and repaint the current screen from the system state
+    ForceRedraw,
+    /// Redraw the age of the last reading (issued by the display task itself)
+    RefreshReadingAge,
+    /// Switch the display off until the next reading, when the power profile has a display timeout
+    /// (issued by the display task itself)
+    Sleep,
+    /// Show the storage mode notice, then switch the display off for good
+    StorageMode,
+    /// Flash the display to draw attention to a raised alarm, if `ALARM_FLASH` is configured
+    AlarmFlash,
+    /// Flush the redraws of the closed coalescing window to the display (issued by the display task itself)
+    Flush,
+    /// Show or refresh the idle screen, if `IDLE_SCREEN` is configured (issued by the display task itself)
+    Idle,
 }
 
 /// Triggers a display update with the provided command
@@ -76,14 +401,107 @@ pub async fn send_display_command(command: DisplayCommand) {
     DISPLAY_CHANNEL.send(command).await;
 }
 
+/// Receiver of the display commands of the orchestrator
+// Embassy tasks are not Send, so neither are the futures of the sink
+#[allow(async_fn_in_trait)]
+pub trait DisplaySink {
+    /// Passes a command on to the display
+    async fn send(&mut self, command: DisplayCommand);
+}
+
+/// The display task, reached through its command channel
+pub struct DisplayChannel;
+
+impl DisplaySink for DisplayChannel {
+    async fn send(&mut self, command: DisplayCommand) {
+        send_display_command(command).await;
+    }
+}
+
+/// Whether a command is still drawn while the display is frozen on the current screen
+/// Sensor data, mode toggles and the reading age are held back, state changes the user must see are not
+const fn shown_while_frozen(command: DisplayCommand) -> bool {
+    match command {
+        DisplayCommand::UpdateBatteryCharging | DisplayCommand::UpdateBatteryPercentage(_) => {
+            REDRAW_BATTERY_WHILE_FROZEN
+        }
+        DisplayCommand::SensorErrorChanged
+        | DisplayCommand::BatteryWarningChanged
+        | DisplayCommand::Unfrozen
+        | DisplayCommand::StorageMode
+        | DisplayCommand::AlarmFlash
+        | DisplayCommand::Flush => true,
+        DisplayCommand::SensorData { .. }
+        | DisplayCommand::ToggleMode
+        | DisplayCommand::NightModeChanged
+        | DisplayCommand::WarmupProgress(_)
+        | DisplayCommand::HistoryCleared
+        | DisplayCommand::ForceRedraw
+        | DisplayCommand::RefreshReadingAge
+        | DisplayCommand::Sleep
+        | DisplayCommand::Idle => false,
+    }
+}
+
+/// Whether a command repaints the whole main area, replacing the idle screen
+#[cfg(not(feature = "epaper"))]
+const fn redraws_main_area(command: DisplayCommand) -> bool {
+    matches!(
+        command,
+        DisplayCommand::SensorData { .. }
+            | DisplayCommand::ToggleMode
+            | DisplayCommand::NightModeChanged
+            | DisplayCommand::SensorErrorChanged
+            | DisplayCommand::BatteryWarningChanged
+            | DisplayCommand::Unfrozen
+            | DisplayCommand::HistoryCleared
+            | DisplayCommand::ForceRedraw
+    )
+}
+
 /// Waits for next indicator state change signal
 async fn wait_for_display_command() -> DisplayCommand {
     DISPLAY_CHANNEL.receive().await
 }
 
+/// Waits for the next display command, or issues `Flush`, `RefreshReadingAge`, `Idle` and `Sleep` when they are due
+async fn next_display_command(
+    sleep_at: Option<Instant>,
+    idle_at: Option<Instant>,
+    flush_at: Option<Instant>,
+) -> DisplayCommand {
+    // A pending flush is due within the coalescing window, ahead of anything else
+    if let Some(flush_at) = flush_at {
+        return match select(wait_for_display_command(), Timer::at(flush_at)).await {
+            Either::First(command) => command,
+            Either::Second(()) => DisplayCommand::Flush,
+        };
+    }
+
+    // Keep the reading age counting up between readings
+    let refresh_at = SHOW_READING_AGE.then(|| Instant::now() + READING_AGE_REFRESH_INTERVAL);
+    // The earliest is due, on a tie the later entry wins, so the display sleeps rather than redrawing first
+    let Some((deadline, due_command)) = [
+        (refresh_at, DisplayCommand::RefreshReadingAge),
+        (idle_at, DisplayCommand::Idle),
+        (sleep_at, DisplayCommand::Sleep),
+    ]
+    .into_iter()
+    .filter_map(|(at, command)| at.map(|at| (at, command)))
+    .min_by_key(|&(at, _)| at) else {
+        return wait_for_display_command().await;
+    };
+
+    match select(wait_for_display_command(), Timer::at(deadline)).await {
+        Either::First(command) => command,
+        Either::Second(()) => due_command,
+    }
+}
+
+#[cfg(all(not(feature = "epaper"), target_os = "none"))]
 #[embassy_executor::task]
 #[allow(clippy::too_many_lines)]
-pub async fn display_task(i2c_device: I2cDevice<'static, NoopRawMutex, I2c<'static, I2C0, Async>>) {
+pub async fn display_task(i2c_device: I2cBusDevice) {
     // Initialize the display
     let interface = I2CDisplayInterface::new(i2c_device);
     let mut display =
@@ -95,7 +513,7 @@ pub async fn display_task(i2c_device: I2cDevice<'static, NoopRawMutex, I2c<'stat
         return;
     }
 
-    if let Err(e) = display.set_brightness(Brightness::DIMMEST).await {
+    if let Err(e) = display.set_brightness(clamp_brightness(DISPLAY_BRIGHTNESS)).await {
         error!("Failed to set display brightness: {}t", Debug2Format(&e));
         return;
     }
@@ -112,6 +530,17 @@ pub async fn display_task(i2c_device: I2cDevice<'static, NoopRawMutex, I2c<'stat
         return;
     };
 
+    // Tell briefly why the system was reset before the startup screen
+    if let Some(reason) = boot_reason_to_show() {
+        settings.draw_boot_reason(&mut display, reason);
+        if let Err(e) = display.flush().await {
+            error!("Failed to flush boot screen: {}", Debug2Format(&e));
+            return;
+        }
+        Timer::after(BOOT_REASON_TIME).await;
+        display.clear();
+    }
+
     // Show initial startup screen
     show_initial_screen(&mut display, &settings).await;
     if let Err(e) = display.flush().await {
@@ -122,26 +551,172 @@ pub async fn display_task(i2c_device: I2cDevice<'static, NoopRawMutex, I2c<'stat
     let task_id = TaskId::Display;
     report_task_success(task_id).await;
 
+    // When the display is switched off to save power, `None` while it stays on
+    let mut sleep_at: Option<Instant> = None;
+    let mut display_on = true;
+    // When the drawn redraws are flushed, `None` while nothing is pending
+    let mut flush_at: Option<Instant> = None;
+    let mut coalesced = 0;
+    // When the idle screen is shown or refreshed next, `None` while it is not due
+    let mut idle_at: Option<Instant> = None;
+    let mut idle = false;
+
     // Main display loop - all errors here are considered transient
     loop {
-        let command = wait_for_display_command().await;
+        let command = next_display_command(sleep_at, idle_at, flush_at).await;
 
-        // Handle the display command
-        handle_display_command(command, &mut display, &settings).await;
+        if command == DisplayCommand::Flush {
+            flush_at = None;
+            #[cfg(feature = "debug-serial")]
+            let iteration_start = Instant::now();
 
-        // Flush display - if this fails, it's transient, so we continue
-        if let Err(e) = display.flush().await {
-            error!("Failed to flush display (continuing): {}", Debug2Format(&e));
-            // Report task failure for watchdog health monitoring (flush failed)
-            report_task_failure(task_id).await;
-        } else {
-            // Report task success for watchdog health monitoring (flush succeeded)
+            // Flush display - if this fails, it's transient, so we continue
+            if let Err(e) = display.flush().await {
+                error!("Failed to flush display (continuing): {}", Debug2Format(&e));
+                record_i2c_error(BusDevice::Display).await;
+                // Report task failure for watchdog health monitoring (flush failed)
+                report_task_failure(task_id).await;
+            } else {
+                // Report task success for watchdog health monitoring (flush succeeded)
+                report_task_success(task_id).await;
+            }
+            record_redraw(coalesced).await;
+            coalesced = 0;
+            #[cfg(feature = "debug-serial")]
+            record_loop_time(task_id, iteration_start).await;
+            continue;
+        }
+
+        if command == DisplayCommand::StorageMode {
+            // Show the notice long enough to be read, even if the display was switched off
+            if let Err(e) = display.set_display_on(true).await {
+                error!("Failed to switch display on (continuing): {}", Debug2Format(&e));
+            }
+            display.clear();
+            settings.draw_storage_mode_message(&mut display);
+            if let Err(e) = display.flush().await {
+                error!("Failed to flush storage mode notice (continuing): {}", Debug2Format(&e));
+            }
+
+            Timer::after(STORAGE_MODE_NOTICE_TIME).await;
+            if let Err(e) = display.set_display_on(false).await {
+                error!("Failed to switch display off for storage mode: {}", Debug2Format(&e));
+            }
+            storage_mode::park("Display").await;
+        }
+
+        if command == DisplayCommand::AlarmFlash {
+            // Inverting is a display setting, the drawn content stays untouched
+            if let Some(flash) = ALARM_FLASH.filter(|_| display_on) {
+                for _ in 0..flash.count {
+                    for invert in [true, false] {
+                        if let Err(e) = display.set_invert(invert).await {
+                            error!("Failed to flash display (continuing): {}", Debug2Format(&e));
+                            record_i2c_error(BusDevice::Display).await;
+                        }
+                        Timer::after(flash.interval).await;
+                    }
+                }
+            }
+            continue;
+        }
+
+        let frozen = SYSTEM_STATE.lock().await.is_display_frozen();
+
+        if command == DisplayCommand::Sleep {
+            sleep_at = None;
+            if frozen {
+                // Stay on while frozen, the next reading after unfreezing restarts the timeout
+                continue;
+            }
+            if let Err(e) = display.set_display_on(false).await {
+                error!("Failed to switch display off (continuing): {}", Debug2Format(&e));
+                record_i2c_error(BusDevice::Display).await;
+            } else {
+                display_on = false;
+            }
+            continue;
+        }
+
+        if command == DisplayCommand::Idle {
+            // Refreshed while shown, the display being off or frozen ends it until the next redraw
+            idle_at = (display_on && !frozen).then(|| Instant::now() + IDLE_SCREEN_REFRESH_INTERVAL);
+            idle = idle_at.is_some();
+        } else if redraws_main_area(command) {
+            // New data or an interaction replaces the idle screen right away, it is due again after the timeout on
+            // battery
+            idle = false;
+            let on_battery = SYSTEM_STATE.lock().await.get_charging_since().is_none();
+            idle_at = (IDLE_SCREEN != IdleScreen::Off && on_battery).then(|| Instant::now() + IDLE_SCREEN_TIMEOUT);
+        } else if idle && command == DisplayCommand::RefreshReadingAge {
+            // The reading age belongs to the data screen
+            continue;
+        }
+
+        if matches!(command, DisplayCommand::SensorData { .. }) {
+            let display_timeout = SYSTEM_STATE
+                .lock()
+                .await
+                .get_settings()
+                .power_profile
+                .params()
+                .display_timeout;
+            sleep_at = display_timeout.map(|timeout| Instant::now() + timeout);
+
+            if !display_on {
+                if let Err(e) = display.set_display_on(true).await {
+                    error!("Failed to switch display on (continuing): {}", Debug2Format(&e));
+                    record_i2c_error(BusDevice::Display).await;
+                } else {
+                    display_on = true;
+                }
+            }
+        }
+
+        let night_mode = SYSTEM_STATE.lock().await.is_night_mode();
+
+        // A forced redraw sends the brightness again too, in case the controller lost it in a glitch
+        if matches!(command, DisplayCommand::NightModeChanged | DisplayCommand::ForceRedraw) {
+            let brightness = if night_mode {
+                NIGHT_MODE_BRIGHTNESS
+            } else {
+                DISPLAY_BRIGHTNESS
+            };
+            if let Err(e) = display.set_brightness(clamp_brightness(brightness)).await {
+                error!("Failed to set display brightness (continuing): {}", Debug2Format(&e));
+                record_i2c_error(BusDevice::Display).await;
+            }
+        }
+
+        if !display_on || (frozen && !shown_while_frozen(command)) {
+            // Nothing to see while the display is off, the next reading redraws everything
+            // While frozen the screen is kept as it is, unfreezing redraws everything
             report_task_success(task_id).await;
+            continue;
+        }
+
+        // Handle the display command, dithered in night mode so fewer pixels are lit
+        #[cfg(feature = "debug-serial")]
+        let iteration_start = Instant::now();
+        if night_mode {
+            handle_display_command(command, &mut Dithered::new(&mut display), &settings).await;
+        } else {
+            handle_display_command(command, &mut display, &settings).await;
+        }
+
+        // Flushed when the coalescing window closes, commands until then are drawn into the same flush
+        if flush_at.is_some() {
+            coalesced += 1;
+        } else {
+            flush_at = Some(Instant::now() + REDRAW_COALESCE_WINDOW);
         }
+        #[cfg(feature = "debug-serial")]
+        record_loop_time(task_id, iteration_start).await;
     }
 }
 
 /// Handles a display command and updates the display accordingly
+#[allow(clippy::too_many_lines)]
 async fn handle_display_command<D>(command: DisplayCommand, display: &mut D, settings: &Settings<'_>)
 where
     D: embedded_graphics::prelude::DrawTarget<Color = BinaryColor>,
@@ -152,9 +727,14 @@ where
             raw_temperature,
             humidity,
             raw_humidity,
+            dew_point,
             co2,
-            etoh,
+            co2_suspect,
+            voc,
+            voc_kind,
             air_quality,
+            settling,
+            quality,
         } => {
             // Create the sensor data structure
             let sensor_data = SensorData {
@@ -162,70 +742,175 @@ where
                 raw_temperature,
                 humidity,
                 raw_humidity,
+                dew_point,
                 co2,
-                etoh,
+                co2_suspect,
+                voc,
+                voc_kind,
                 air_quality,
+                settling,
+                quality,
             };
 
             // Clear main content area (preserves battery icon)
-            settings.clear_main_area(&mut display.color_converted());
+            settings.clear_main_area(display);
 
             // Draw based on current display mode
+            let diagnostics = get_diagnostics().await;
             {
                 let state = SYSTEM_STATE.lock().await;
-                match state.get_display_mode() {
-                    DisplayMode::RawData => {
-                        settings.draw_sensor_data(&mut display.color_converted(), &sensor_data);
-                    }
-                    DisplayMode::Co2History => {
-                        settings.draw_co2_history(&mut display.color_converted(), state.get_co2_history());
-                    }
-                }
+                draw_mode_content(display, settings, &state, &sensor_data, &diagnostics);
 
                 // Draw battery icon
-                settings.draw_battery_icon(&mut display.color_converted(), &state.get_battery_level());
+                settings.draw_battery(display, &state);
             }
         }
         DisplayCommand::UpdateBatteryCharging | DisplayCommand::UpdateBatteryPercentage(_) => {
             // Only clear and redraw battery icon area
-            settings.clear_battery_area(&mut display.color_converted());
+            settings.clear_battery_area(display);
             {
                 let state = SYSTEM_STATE.lock().await;
-                settings.draw_battery_icon(&mut display.color_converted(), &state.get_battery_level());
+                settings.draw_battery(display, &state);
+            }
+        }
+        DisplayCommand::Sleep | DisplayCommand::StorageMode | DisplayCommand::AlarmFlash | DisplayCommand::Flush => {}
+        DisplayCommand::WarmupProgress(remaining) => {
+            // Readings that arrived in the meantime, or a sensor fault, take precedence
+            let state = SYSTEM_STATE.lock().await;
+            if state.last_sensor_data.is_none() && state.get_sensor_error().is_none() {
+                settings.clear_main_area(display);
+                settings.draw_warmup_countdown(display, remaining);
+            }
+        }
+        DisplayCommand::Idle => {
+            let time = if IDLE_SCREEN == IdleScreen::Clock {
+                clock_time().await
+            } else {
+                None
+            };
+            settings.clear_main_area(display);
+            settings.draw_idle_screen(display, time);
+        }
+        DisplayCommand::RefreshReadingAge => {
+            let state = SYSTEM_STATE.lock().await;
+            if state.get_display_mode() == DisplayMode::RawData && state.last_sensor_data.is_some() {
+                settings.draw_reading_age(display, state.get_last_sensor_data_age());
             }
         }
-        DisplayCommand::ToggleMode => {
-            // State has already been toggled by orchestrator, just redraw
+        DisplayCommand::ToggleMode
+        | DisplayCommand::NightModeChanged
+        | DisplayCommand::SensorErrorChanged
+        | DisplayCommand::BatteryWarningChanged
+        | DisplayCommand::Unfrozen
+        | DisplayCommand::HistoryCleared
+        | DisplayCommand::ForceRedraw => {
+            // State has already been updated by orchestrator, just redraw
             let sensor_data_option = {
                 let state = SYSTEM_STATE.lock().await;
                 state.last_sensor_data.clone()
             };
 
-            settings.clear_main_area(&mut display.color_converted());
+            if command == DisplayCommand::ForceRedraw {
+                // Every pixel is repainted, not just the main area, so nothing is left of a glitch
+                display.clear(BinaryColor::Off).unwrap_or_default();
+            } else {
+                settings.clear_main_area(display);
+            }
             if let Some(sensor_data) = sensor_data_option {
                 // Redraw with the current mode
+                let diagnostics = get_diagnostics().await;
                 {
                     let state = SYSTEM_STATE.lock().await;
-                    match state.get_display_mode() {
-                        DisplayMode::RawData => {
-                            settings.draw_sensor_data(&mut display.color_converted(), &sensor_data);
-                        }
-                        DisplayMode::Co2History => {
-                            settings.draw_co2_history(&mut display.color_converted(), state.get_co2_history());
-                        }
-                    }
+                    draw_mode_content(display, settings, &state, &sensor_data, &diagnostics);
                 }
             } else {
-                // No sensor data yet, clear main area and show initialization message
-                settings.draw_initialization_message(&mut display.color_converted());
+                // No sensor data yet, clear main area and show initialization message or the warmup countdown
+                let (sensor_error, warmup_remaining) = {
+                    let state = SYSTEM_STATE.lock().await;
+                    (state.get_sensor_error(), state.get_warmup_remaining())
+                };
+                match (sensor_error, warmup_remaining) {
+                    (None, Some(remaining)) => settings.draw_warmup_countdown(display, remaining),
+                    _ => settings.draw_initialization_message(display, sensor_error),
+                }
             }
 
             // Draw battery icon
             {
                 let state = SYSTEM_STATE.lock().await;
-                settings.draw_battery_icon(&mut display.color_converted(), &state.get_battery_level());
+                settings.draw_battery(display, &state);
+            }
+        }
+    }
+}
+
+/// Draws the main content area for the current display mode
+fn draw_mode_content<D>(
+    display: &mut D,
+    settings: &Settings<'_>,
+    state: &SystemState,
+    sensor_data: &SensorData,
+    diagnostics: &Diagnostics,
+) where
+    D: embedded_graphics::prelude::DrawTarget<Color = BinaryColor>,
+{
+    match state.get_display_mode() {
+        DisplayMode::RawData => {
+            settings.draw_sensor_data(
+                display,
+                sensor_data,
+                state.get_co2_trend(),
+                state.get_settings().temperature_unit,
+            );
+            if SHOW_READING_AGE {
+                settings.draw_reading_age(display, state.get_last_sensor_data_age());
             }
+            if SHOW_QUALITY_INDICATOR && state.get_worst_recent_quality() != ReadingQuality::Good {
+                settings.draw_quality_indicator(display);
+            }
+        }
+        DisplayMode::Co2History => {
+            settings.draw_co2_history(
+                display,
+                state.get_co2_history(),
+                state.get_read_interval(),
+                CO2_CHART_SCALE,
+            );
         }
+        DisplayMode::HumidityHistory => {
+            settings.draw_humidity_history(display, state.get_humidity_history(), state.get_read_interval());
+        }
+        DisplayMode::Co2Large => {
+            settings.draw_co2_large(display, sensor_data);
+        }
+        DisplayMode::Diagnostics => {
+            settings.draw_diagnostics(display, diagnostics);
+        }
+        DisplayMode::Compact => {
+            settings.draw_compact(
+                display,
+                sensor_data,
+                state.get_co2_history(),
+                state.get_settings().temperature_unit,
+            );
+        }
+        DisplayMode::Stats => {
+            settings.draw_stats(display, state);
+        }
+        DisplayMode::Focus => {
+            settings.draw_focus(
+                display,
+                sensor_data,
+                state.get_focus_metric(),
+                state.get_settings().temperature_unit,
+            );
+        }
+    }
+    if state.is_reduced_sensing() {
+        settings.draw_reduced_sensing_notice(display);
+    }
+    if state.is_low_battery_warning() {
+        settings.draw_low_battery_warning(display);
     }
 }
 
@@ -250,11 +935,21 @@ async fn show_initial_screen<D>(display: &mut D, settings: &Settings<'_>)
 where
     D: embedded_graphics::prelude::DrawTarget<Color = BinaryColor>,
 {
-    // Show initial startup screen
-    settings.draw_initialization_message(&mut display.color_converted());
+    // Show initial startup screen, telling once that the settings were lost
+    settings.draw_initialization_message(display, None);
+    if settings_store::were_settings_reset() {
+        Text::with_baseline(
+            "Settings reset",
+            settings.sensor_init_position + Point::new(0, 14),
+            settings.sensor_init_text_style,
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap_or_default();
+    }
     {
         let state = SYSTEM_STATE.lock().await;
-        settings.draw_battery_icon(&mut display.color_converted(), &state.get_battery_level());
+        settings.draw_battery(display, &state);
     }
 
     // Draw firmware version
@@ -264,123 +959,395 @@ where
         settings.firmware_version_text_style,
         Baseline::Top,
     )
-    .draw(&mut display.color_converted())
+    .draw(display)
     .unwrap_or_default();
 }
 
-/// Loads and holds BMP images and Points for the display
-/// Holds some settings for composing the display
-struct Settings<'a> {
-    /// BMP images of the battery status icons
-    bat: [Bmp<'static, Gray8>; 6],
-    /// BMP image of the battery mains icon
-    bat_mains: Bmp<'static, Gray8>,
-    /// BMP image of the settings icon
-    init_icon: Bmp<'static, Gray8>,
-    /// Position of the battery status images, hight is 11
-    bat_position: Point,
-    /// Position of the air quality text
-    air_quality_position: Point,
-    /// Style of the air quality text
-    air_quality_text_style: MonoTextStyle<'a, BinaryColor>,
-    /// Position of the CO2 text
-    co2_position: Point,
-    /// Style of the CO2 text
-    co2_text_style: MonoTextStyle<'a, BinaryColor>,
-    /// Position of the etoh text
-    etoh_position: Point,
-    /// Style of the etoh text
-    etoh_text_style: MonoTextStyle<'a, BinaryColor>,
-    /// Position of the temperature text
-    temperature_position: Point,
-    /// Style of the temperature text
-    temperature_text_style: MonoTextStyle<'a, BinaryColor>,
-    /// Position of the humidity text
-    humidity_position: Point,
-    /// Style of the humidity text
-    humidity_text_style: MonoTextStyle<'a, BinaryColor>,
-    /// Position of the sensor initialization message
-    sensor_init_position: Point,
-    /// Style of the sensor initialization message
-    sensor_init_text_style: MonoTextStyle<'a, BinaryColor>,
-    /// Position for min text in CO2 history
-    minmax_min_position: Point,
-    /// Position for max text in CO2 history
-    minmax_max_position: Point,
-    /// Style for min/max labels in CO2 history chart
-    minmax_text_style: MonoTextStyle<'a, BinaryColor>,
-    /// Position for firmware version text
-    firmware_version_position: Point,
-    /// Style for firmware version text
-    firmware_version_text_style: MonoTextStyle<'a, BinaryColor>,
-    /// Bar chart starting Y position
-    chart_start_y: i32,
-    /// Bar chart height
-    chart_height: i32,
-    /// Bar chart width
-    chart_width: i32,
+/// Limits a brightness to `MIN_BRIGHTNESS` and `MAX_BRIGHTNESS`, every brightness sent to the SSD1306 goes through here
+#[cfg(all(not(feature = "epaper"), target_os = "none"))]
+fn clamp_brightness(level: u8) -> Brightness {
+    let contrast = level.clamp(MIN_BRIGHTNESS, MAX_BRIGHTNESS);
+    // Like the predefined levels, only the very dimmest uses the shortest precharge period
+    let precharge = if contrast == 0 { 1 } else { 2 };
+    Brightness::custom(precharge, contrast)
 }
 
-impl Settings<'_> {
-    /// Creates a new `Settings` instance by loading BMP images and setting up text styles
-    fn new() -> Result<Self, &'static str> {
-        let bat_000 = Bmp::from_slice(include_bytes!("media/bat_000.bmp")).map_err(|_| "Failed to load bat_000.bmp")?;
-        let bat_020 = Bmp::from_slice(include_bytes!("media/bat_020.bmp")).map_err(|_| "Failed to load bat_020.bmp")?;
-        let bat_040 = Bmp::from_slice(include_bytes!("media/bat_040.bmp")).map_err(|_| "Failed to load bat_040.bmp")?;
-        let bat_060 = Bmp::from_slice(include_bytes!("media/bat_060.bmp")).map_err(|_| "Failed to load bat_060.bmp")?;
-        let bat_080 = Bmp::from_slice(include_bytes!("media/bat_080.bmp")).map_err(|_| "Failed to load bat_080.bmp")?;
-        let bat_100 = Bmp::from_slice(include_bytes!("media/bat_100.bmp")).map_err(|_| "Failed to load bat_100.bmp")?;
-        let bat_mains =
-            Bmp::from_slice(include_bytes!("media/bat_mains.bmp")).map_err(|_| "Failed to load bat_mains.bmp")?;
-        let settings_icon =
-            Bmp::from_slice(include_bytes!("media/settings.bmp")).map_err(|_| "Failed to load settings.bmp")?;
+/// Current time as `(hour, minute)` for the idle screen clock, `None` while the clock is not set
+#[cfg(feature = "rtc")]
+async fn clock_time() -> Option<(u8, u8)> {
+    rtc::now().await.map(|now| (now.hour, now.minute))
+}
 
-        Ok(Self {
-            bat: [bat_000, bat_020, bat_040, bat_060, bat_080, bat_100],
-            bat_mains,
-            init_icon: settings_icon,
-            bat_position: Point::new(108, 1),
-            air_quality_text_style: MonoTextStyleBuilder::new()
-                .font(&FONT_8X13_BOLD)
-                .text_color(BinaryColor::On)
-                .build(),
-            air_quality_position: Point::new(0, 0),
+/// Current time for the idle screen clock, always `None` without the `rtc` feature
+#[cfg(not(feature = "rtc"))]
+#[allow(clippy::unused_async)]
+async fn clock_time() -> Option<(u8, u8)> {
+    None
+}
+
+/// Reason to show on the boot screen, `None` for a normal power-on or with the boot screen switched off
+fn boot_reason_to_show() -> Option<ResetReason> {
+    let reason = reset_reason();
+    (BOOT_REASON_DETAIL != BootReasonDetail::Off && reason != ResetReason::PowerOn).then_some(reason)
+}
+
+/// Draw target wrapper that only lights every other pixel in a checkerboard pattern
+/// Halves the number of lit pixels, lowering the perceived brightness below the hardware minimum
+#[cfg(not(feature = "epaper"))]
+struct Dithered<'a, D> {
+    /// The wrapped draw target
+    target: &'a mut D,
+}
+
+#[cfg(not(feature = "epaper"))]
+impl<'a, D> Dithered<'a, D> {
+    /// Wraps a draw target
+    const fn new(target: &'a mut D) -> Self {
+        Self { target }
+    }
+}
+
+#[cfg(not(feature = "epaper"))]
+impl<D> Dimensions for Dithered<'_, D>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    fn bounding_box(&self) -> Rectangle {
+        self.target.bounding_box()
+    }
+}
+
+#[cfg(not(feature = "epaper"))]
+impl<D> DrawTarget for Dithered<'_, D>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    type Color = BinaryColor;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.target.draw_iter(pixels.into_iter().map(|Pixel(point, color)| {
+            let lit = color.is_on() && (point.x + point.y) % 2 == 0;
+            Pixel(point, BinaryColor::from(lit))
+        }))
+    }
+}
+
+/// Colors of a draw target the screens can be drawn on
+trait Palette: PixelColor {
+    /// Color of unlit pixels, the background
+    const BACKGROUND: Self;
+    /// Color of text and graphics
+    const FOREGROUND: Self;
+    /// Second color for emphasis and alarm states, `None` if the target only shows one color
+    const ACCENT: Option<Self>;
+}
+
+impl Palette for BinaryColor {
+    const BACKGROUND: Self = Self::Off;
+    const FOREGROUND: Self = Self::On;
+    const ACCENT: Option<Self> = None;
+}
+
+/// Draw target wrapper converting grayscale images to the foreground and background colors at `ICON_LUMA_THRESHOLD`
+/// Used instead of `color_converted`, whose fixed conversion cannot be tuned to the icons
+struct Thresholded<'a, D> {
+    /// The wrapped draw target
+    target: &'a mut D,
+}
+
+impl<'a, D> Thresholded<'a, D> {
+    /// Wraps a draw target
+    const fn new(target: &'a mut D) -> Self {
+        Self { target }
+    }
+}
+
+impl<D, C> Dimensions for Thresholded<'_, D>
+where
+    D: DrawTarget<Color = C>,
+    C: Palette,
+{
+    fn bounding_box(&self) -> Rectangle {
+        self.target.bounding_box()
+    }
+}
+
+impl<D, C> DrawTarget for Thresholded<'_, D>
+where
+    D: DrawTarget<Color = C>,
+    C: Palette,
+{
+    type Color = Gray8;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.target.draw_iter(pixels.into_iter().map(|Pixel(point, color)| {
+            let lit = color.luma() >= ICON_LUMA_THRESHOLD;
+            Pixel(point, if lit { C::FOREGROUND } else { C::BACKGROUND })
+        }))
+    }
+}
+
+/// Size of a screenshot in bytes, in the SSD1306 memory layout of 8 pages of 128 columns with one bit per pixel
+#[cfg(any(test, feature = "debug-serial"))]
+pub const SCREENSHOT_SIZE: usize = 128 * 64 / 8;
+
+/// Draw target recording pixels in the SSD1306 memory layout, used to re-render the screen for a screenshot
+#[cfg(any(test, feature = "debug-serial"))]
+struct Framebuffer {
+    /// One bit per pixel, each byte is a column of 8 pixels with the top pixel in bit 0
+    buffer: [u8; SCREENSHOT_SIZE],
+}
+
+#[cfg(any(test, feature = "debug-serial"))]
+impl OriginDimensions for Framebuffer {
+    fn size(&self) -> Size {
+        Size::new(128, 64)
+    }
+}
+
+#[cfg(any(test, feature = "debug-serial"))]
+impl DrawTarget for Framebuffer {
+    type Color = BinaryColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            let (Ok(x), Ok(y)) = (usize::try_from(point.x), usize::try_from(point.y)) else {
+                continue;
+            };
+            if x >= 128 {
+                continue;
+            }
+            let Some(byte) = self.buffer.get_mut(y / 8 * 128 + x) else {
+                continue;
+            };
+            let bit = 1 << (y % 8);
+            if color.is_on() {
+                *byte |= bit;
+            } else {
+                *byte &= !bit;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Re-renders the current screen into a buffer in the SSD1306 memory layout, for the screenshot serial command
+/// The screen is drawn from the system state, so it matches the display except while the display is switched off
+#[cfg(feature = "debug-serial")]
+pub async fn render_screenshot() -> Result<[u8; SCREENSHOT_SIZE], &'static str> {
+    let settings = Settings::new()?;
+    let mut framebuffer = Framebuffer {
+        buffer: [0; SCREENSHOT_SIZE],
+    };
+
+    // The e-paper panel has no night mode
+    #[cfg(not(feature = "epaper"))]
+    if SYSTEM_STATE.lock().await.is_night_mode() {
+        handle_display_command(
+            DisplayCommand::ToggleMode,
+            &mut Dithered::new(&mut framebuffer),
+            &settings,
+        )
+        .await;
+        return Ok(framebuffer.buffer);
+    }
+    handle_display_command(DisplayCommand::ToggleMode, &mut framebuffer, &settings).await;
+    Ok(framebuffer.buffer)
+}
+
+/// Loads and holds BMP images and Points for the display
+/// Holds some settings for composing the display
+/// All drawing methods take a target in the `Palette` colors directly, `BinaryColor` for both displays of this firmware.
+/// The grayscale icons are converted once by `Thresholded`
+struct Settings<'a, C: Palette = BinaryColor> {
+    /// BMP images of the battery status icons
+    bat: [Bmp<'static, Gray8>; 6],
+    /// BMP image of the battery mains icon
+    bat_mains: Bmp<'static, Gray8>,
+    /// BMP image of the settings icon
+    init_icon: Bmp<'static, Gray8>,
+    /// BMP images of the air quality faces (good, moderate, poor), height is 13
+    aqi_icons: [Bmp<'static, Gray8>; 3],
+    /// Position of the battery status images, hight is 11
+    bat_position: Point,
+    /// Position of the air quality text
+    air_quality_position: Point,
+    /// Style of the air quality text
+    air_quality_text_style: MonoTextStyle<'a, C>,
+    /// Narrower style of the air quality text, used when the index is shown next to the label
+    air_quality_index_text_style: MonoTextStyle<'a, C>,
+    /// Position of the CO2 text
+    co2_position: Point,
+    /// Style of the CO2 text
+    co2_text_style: MonoTextStyle<'a, C>,
+    /// Position of the ethanol or TVOC text
+    voc_position: Point,
+    /// Style of the ethanol or TVOC text
+    voc_text_style: MonoTextStyle<'a, C>,
+    /// Position of the temperature text
+    temperature_position: Point,
+    /// Style of the temperature text
+    temperature_text_style: MonoTextStyle<'a, C>,
+    /// Position of the humidity text
+    humidity_position: Point,
+    /// Style of the humidity text
+    humidity_text_style: MonoTextStyle<'a, C>,
+    /// Position of the sensor initialization message
+    sensor_init_position: Point,
+    /// Style of the sensor initialization message
+    sensor_init_text_style: MonoTextStyle<'a, C>,
+    /// Position for min text in CO2 history
+    minmax_min_position: Point,
+    /// Position for max text in CO2 history
+    minmax_max_position: Point,
+    /// Style for min/max labels in CO2 history chart
+    minmax_text_style: MonoTextStyle<'a, C>,
+    /// Position for firmware version text
+    firmware_version_position: Point,
+    /// Style for firmware version text
+    firmware_version_text_style: MonoTextStyle<'a, C>,
+    /// Position of the battery percentage, below the battery icon
+    battery_percentage_position: Point,
+    /// Style for the battery percentage
+    battery_percentage_text_style: MonoTextStyle<'a, C>,
+    /// Position of the reading age, below the battery area
+    reading_age_position: Point,
+    /// Style for the reading age
+    reading_age_text_style: MonoTextStyle<'a, C>,
+    /// Position of the poor recent quality indicator, below the reading age
+    quality_indicator_position: Point,
+    /// Position of the first line of the compact screen
+    compact_line1_position: Point,
+    /// Position of the second line of the compact screen
+    compact_line2_position: Point,
+    /// Style of the compact screen text
+    compact_text_style: MonoTextStyle<'a, C>,
+    /// Top left corner of the compact screen CO2 sparkline
+    sparkline_position: Point,
+    /// Size of the compact screen CO2 sparkline
+    sparkline_size: Size,
+    /// Position of the large value on the focus screen
+    focus_value_position: Point,
+    /// Style of the large value on the focus screen
+    focus_value_text_style: MonoTextStyle<'a, C>,
+    /// Position of the line with the other metrics on the focus screen
+    focus_others_position: Point,
+    /// Bar chart starting Y position
+    chart_start_y: i32,
+    /// Bar chart height
+    chart_height: i32,
+    /// Bar chart width
+    chart_width: i32,
+}
+
+impl<C: Palette> Settings<'_, C> {
+    /// Creates a new `Settings` instance by loading BMP images and setting up text styles
+    fn new() -> Result<Self, &'static str> {
+        let bat_000 = Bmp::from_slice(include_bytes!("media/bat_000.bmp")).map_err(|_| "Failed to load bat_000.bmp")?;
+        let bat_020 = Bmp::from_slice(include_bytes!("media/bat_020.bmp")).map_err(|_| "Failed to load bat_020.bmp")?;
+        let bat_040 = Bmp::from_slice(include_bytes!("media/bat_040.bmp")).map_err(|_| "Failed to load bat_040.bmp")?;
+        let bat_060 = Bmp::from_slice(include_bytes!("media/bat_060.bmp")).map_err(|_| "Failed to load bat_060.bmp")?;
+        let bat_080 = Bmp::from_slice(include_bytes!("media/bat_080.bmp")).map_err(|_| "Failed to load bat_080.bmp")?;
+        let bat_100 = Bmp::from_slice(include_bytes!("media/bat_100.bmp")).map_err(|_| "Failed to load bat_100.bmp")?;
+        let bat_mains =
+            Bmp::from_slice(include_bytes!("media/bat_mains.bmp")).map_err(|_| "Failed to load bat_mains.bmp")?;
+        let settings_icon =
+            Bmp::from_slice(include_bytes!("media/settings.bmp")).map_err(|_| "Failed to load settings.bmp")?;
+        let aqi_good =
+            Bmp::from_slice(include_bytes!("media/aqi_good.bmp")).map_err(|_| "Failed to load aqi_good.bmp")?;
+        let aqi_moderate =
+            Bmp::from_slice(include_bytes!("media/aqi_moderate.bmp")).map_err(|_| "Failed to load aqi_moderate.bmp")?;
+        let aqi_poor =
+            Bmp::from_slice(include_bytes!("media/aqi_poor.bmp")).map_err(|_| "Failed to load aqi_poor.bmp")?;
+
+        Ok(Self {
+            bat: [bat_000, bat_020, bat_040, bat_060, bat_080, bat_100],
+            bat_mains,
+            init_icon: settings_icon,
+            aqi_icons: [aqi_good, aqi_moderate, aqi_poor],
+            bat_position: Point::new(108, 1),
+            air_quality_text_style: MonoTextStyleBuilder::new()
+                .font(&FONT_8X13_BOLD)
+                .text_color(C::FOREGROUND)
+                .build(),
+            air_quality_index_text_style: MonoTextStyleBuilder::new()
+                .font(&FONT_6X13_BOLD)
+                .text_color(C::FOREGROUND)
+                .build(),
+            air_quality_position: Point::new(0, 0),
             co2_position: Point::new(0, 14),
             co2_text_style: MonoTextStyleBuilder::new()
                 .font(&FONT_6X13)
-                .text_color(BinaryColor::On)
+                .text_color(C::FOREGROUND)
                 .build(),
-            etoh_position: Point::new(0, 26),
-            etoh_text_style: MonoTextStyleBuilder::new()
+            voc_position: Point::new(0, 26),
+            voc_text_style: MonoTextStyleBuilder::new()
                 .font(&FONT_6X13)
-                .text_color(BinaryColor::On)
+                .text_color(C::FOREGROUND)
                 .build(),
             temperature_position: Point::new(0, 38),
             temperature_text_style: MonoTextStyleBuilder::new()
                 .font(&FONT_6X13)
-                .text_color(BinaryColor::On)
+                .text_color(C::FOREGROUND)
                 .build(),
             humidity_position: Point::new(0, 50),
             humidity_text_style: MonoTextStyleBuilder::new()
                 .font(&FONT_6X13)
-                .text_color(BinaryColor::On)
+                .text_color(C::FOREGROUND)
                 .build(),
             sensor_init_position: Point::new(0, 30),
             sensor_init_text_style: MonoTextStyleBuilder::new()
                 .font(&FONT_6X13)
-                .text_color(BinaryColor::On)
+                .text_color(C::FOREGROUND)
                 .build(),
             minmax_min_position: Point::new(0, 57),
             minmax_max_position: Point::new(64, 57),
             minmax_text_style: MonoTextStyleBuilder::new()
                 .font(&FONT_5X8)
-                .text_color(BinaryColor::On)
+                .text_color(C::FOREGROUND)
                 .build(),
-            firmware_version_position: Point::new(108, 15),
+            firmware_version_position: Point::new(108, BATTERY_AREA_HEIGHT + 4),
             firmware_version_text_style: MonoTextStyleBuilder::new()
                 .font(&FONT_5X8)
-                .text_color(BinaryColor::On)
+                .text_color(C::FOREGROUND)
+                .build(),
+            battery_percentage_position: Point::new(108, 13),
+            battery_percentage_text_style: MonoTextStyleBuilder::new()
+                .font(&FONT_5X8)
+                .text_color(C::FOREGROUND)
+                .build(),
+            reading_age_position: Point::new(108, BATTERY_AREA_HEIGHT + 4),
+            reading_age_text_style: MonoTextStyleBuilder::new()
+                .font(&FONT_5X8)
+                .text_color(C::FOREGROUND)
                 .build(),
+            quality_indicator_position: Point::new(108, BATTERY_AREA_HEIGHT + 14),
+            compact_line1_position: Point::new(0, 0),
+            compact_line2_position: Point::new(0, 9),
+            compact_text_style: MonoTextStyleBuilder::new()
+                .font(&FONT_5X8)
+                .text_color(C::FOREGROUND)
+                .build(),
+            sparkline_position: Point::new(0, 20),
+            sparkline_size: Size::new(128, 44),
+            focus_value_position: Point::new(0, 20),
+            focus_value_text_style: MonoTextStyleBuilder::new()
+                .font(&FONT_10X20)
+                .text_color(C::FOREGROUND)
+                .build(),
+            focus_others_position: Point::new(0, 54),
             chart_start_y: 17,
             chart_height: 39,
             chart_width: 128,
@@ -400,15 +1367,42 @@ impl Settings<'_> {
         }
     }
 
-    /// Clears only the battery icon area (preserves main content)
+    /// Returns the face icon for an air quality index
+    const fn get_aqi_icon(&self, aqi: AirQualityIndex) -> &Bmp<'static, Gray8> {
+        match aqi {
+            AirQualityIndex::Excellent | AirQualityIndex::Good => &self.aqi_icons[0],
+            AirQualityIndex::Moderate => &self.aqi_icons[1],
+            AirQualityIndex::Poor | AirQualityIndex::Unhealthy => &self.aqi_icons[2],
+        }
+    }
+
+    /// Draws the face icon for an air quality index at the start of the air quality line
+    fn draw_aqi_icon<D>(&self, display: &mut D, aqi: AirQualityIndex)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let aqi_image = Image::new(self.get_aqi_icon(aqi), self.air_quality_position);
+        aqi_image.draw(&mut Thresholded::new(display)).unwrap_or_default();
+    }
+
+    /// Returns the color emphasis and alarm states are drawn in, `None` to fall back to inversion or solid fills
+    const fn accent_color() -> Option<C> {
+        match COLOR_SCHEME {
+            ColorScheme::Mono => None,
+            ColorScheme::Accent => C::ACCENT,
+        }
+    }
+
+    /// Clears only the battery area (preserves main content)
     fn clear_battery_area<D>(&self, display: &mut D)
     where
-        D: DrawTarget<Color = BinaryColor>,
+        D: DrawTarget<Color = C>,
     {
-        // Battery icon is 20x11 pixels at position (108, 1)
-        let battery_area = Rectangle::new(self.bat_position, Size::new(20, 11));
+        // Battery icon is 20x11 pixels at position (108, 1), the percentage text goes below it
+        #[allow(clippy::cast_sign_loss)]
+        let battery_area = Rectangle::new(self.bat_position, Size::new(20, BATTERY_AREA_HEIGHT as u32));
         battery_area
-            .into_styled(PrimitiveStyle::with_fill(BinaryColor::Off))
+            .into_styled(PrimitiveStyle::with_fill(C::BACKGROUND))
             .draw(display)
             .unwrap_or_default();
     }
@@ -416,19 +1410,19 @@ impl Settings<'_> {
     /// Clears the main content area (preserves battery icon)
     fn clear_main_area<D>(&self, display: &mut D)
     where
-        D: DrawTarget<Color = BinaryColor>,
+        D: DrawTarget<Color = C>,
     {
-        // Clear everything except the battery icon area
+        // Clear everything except the battery icon area, including the lit background of an emphasized metric
         // Clear the main content area (everything to the left of battery icon)
         #[allow(clippy::cast_sign_loss)]
         let main_left_area = Rectangle::new(Point::new(0, 0), Size::new(self.bat_position.x.max(0) as u32, 64));
         main_left_area
-            .into_styled(PrimitiveStyle::with_fill(BinaryColor::Off))
+            .into_styled(PrimitiveStyle::with_fill(C::BACKGROUND))
             .draw(display)
             .unwrap_or_default();
 
-        // Clear the area below the battery icon (y > battery_bottom)
-        let battery_bottom = self.bat_position.y + 11; // Battery icon is 11 pixels tall
+        // Clear the area below the battery area (y > battery_bottom)
+        let battery_bottom = self.bat_position.y + BATTERY_AREA_HEIGHT;
         if battery_bottom < 64 {
             #[allow(clippy::cast_sign_loss)]
             let main_bottom_area = Rectangle::new(
@@ -436,33 +1430,58 @@ impl Settings<'_> {
                 Size::new(20, (64 - battery_bottom).max(0) as u32),
             );
             main_bottom_area
-                .into_styled(PrimitiveStyle::with_fill(BinaryColor::Off))
+                .into_styled(PrimitiveStyle::with_fill(C::BACKGROUND))
                 .draw(display)
                 .unwrap_or_default();
         }
     }
 
+    /// Draws the battery icon and, if enabled, the battery percentage below it
+    fn draw_battery<D>(&self, display: &mut D, state: &SystemState)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        // The text has no background, clear a previous percentage first
+        self.clear_battery_area(display);
+        let battery_level = state.get_battery_level();
+        self.draw_battery_icon(display, &battery_level);
+
+        // The percentage is measured on battery only, there is nothing meaningful to show while charging
+        if SHOW_BATTERY_PERCENTAGE && battery_level != BatteryLevel::Charging {
+            let mut percentage_text: String<5> = String::new();
+            let _ = write!(percentage_text, "{}%", state.get_battery_percent());
+            Text::with_baseline(
+                &percentage_text,
+                self.battery_percentage_position,
+                self.battery_percentage_text_style,
+                Baseline::Top,
+            )
+            .draw(display)
+            .unwrap_or_default();
+        }
+    }
+
     /// Helper function to draw the battery icon
     fn draw_battery_icon<D>(&self, display: &mut D, battery_level: &BatteryLevel)
     where
-        D: DrawTarget<Color = BinaryColor>,
+        D: DrawTarget<Color = C>,
     {
         let battery_icon = self.get_battery_icon(battery_level);
         let bat_image = Image::new(battery_icon, self.bat_position);
-        bat_image.draw(&mut display.color_converted()).unwrap_or_default();
+        bat_image.draw(&mut Thresholded::new(display)).unwrap_or_default();
     }
 
-    /// Draws an initialization message when no sensor data is available
-    fn draw_initialization_message<D>(&self, display: &mut D)
+    /// Draws an initialization message when no sensor data is available, or the error standby message
+    fn draw_initialization_message<D>(&self, display: &mut D, sensor_error: Option<SensorFault>)
     where
-        D: DrawTarget<Color = BinaryColor>,
+        D: DrawTarget<Color = C>,
     {
         // Draw the settings icon
         let settings_image = Image::new(&self.init_icon, self.air_quality_position);
-        settings_image.draw(&mut display.color_converted()).unwrap_or_default();
+        settings_image.draw(&mut Thresholded::new(display)).unwrap_or_default();
 
         Text::with_baseline(
-            "Warming up sensors",
+            sensor_error.map_or("Warming up sensors", SensorFault::message),
             self.sensor_init_position,
             self.sensor_init_text_style,
             Baseline::Top,
@@ -471,150 +1490,578 @@ impl Settings<'_> {
         .unwrap_or_default();
     }
 
-    /// Draws sensor data to the display
-    fn draw_sensor_data<D>(&self, display: &mut D, sensor_data: &SensorData)
+    /// Draws why the system was reset, with the stale tasks of a watchdog reset depending on `BOOT_REASON_DETAIL`
+    fn draw_boot_reason<D>(&self, display: &mut D, reason: ResetReason)
     where
-        D: DrawTarget<Color = BinaryColor>,
+        D: DrawTarget<Color = C>,
     {
-        // Draw the air quality text
-        let mut aq_text: String<12> = String::new();
-        let _ = write!(aq_text, "{:?}", sensor_data.air_quality);
         Text::with_baseline(
-            &aq_text,
-            self.air_quality_position,
-            self.air_quality_text_style,
+            reason.message(),
+            self.sensor_init_position,
+            self.sensor_init_text_style,
             Baseline::Top,
         )
         .draw(display)
         .unwrap_or_default();
 
-        // Draw the CO2 text
-        let mut co2_text: String<16> = String::new();
-        let _ = write!(co2_text, "CO2: {} ppm", sensor_data.co2);
-        Text::with_baseline(&co2_text, self.co2_position, self.co2_text_style, Baseline::Top)
-            .draw(display)
-            .unwrap_or_default();
-
-        // Draw the Ethanol text
-        let mut etoh_text: String<16> = String::new();
-        let _ = write!(etoh_text, "EtOH: {} ppb", sensor_data.etoh);
-        Text::with_baseline(&etoh_text, self.etoh_position, self.etoh_text_style, Baseline::Top)
-            .draw(display)
-            .unwrap_or_default();
-
-        // Draw the temperature text with raw and adjusted values
-        let mut temp_text: String<32> = String::new();
-        let _ = write!(
-            temp_text,
-            "Temp C r/a: {:.1}/{:.1}",
-            sensor_data.raw_temperature, sensor_data.temperature
-        );
+        if BOOT_REASON_DETAIL != BootReasonDetail::Culprits {
+            return;
+        }
+        // One line per task that does not fit on the current one anymore
+        let mut culprits: String<BOOT_REASON_CAPACITY> = String::new();
+        let mut line_start = 0;
+        for task_id in reason.stale_tasks() {
+            if culprits.is_empty() {
+                let _ = write!(culprits, "Stale:");
+            }
+            let mut name: String<16> = String::new();
+            let _ = write!(name, "{task_id:?}");
+            if culprits.len() - line_start + 1 + name.len() > BOOT_REASON_LINE_CHARS {
+                let _ = culprits.push('\n');
+                line_start = culprits.len();
+            } else {
+                let _ = culprits.push(' ');
+            }
+            let _ = culprits.push_str(&name);
+        }
         Text::with_baseline(
-            &temp_text,
-            self.temperature_position,
-            self.temperature_text_style,
+            &culprits,
+            self.sensor_init_position + Point::new(0, 14),
+            self.minmax_text_style,
             Baseline::Top,
         )
         .draw(display)
         .unwrap_or_default();
+    }
 
-        // Draw the humidity text with raw and adjusted values
-        let mut humidity_text: String<32> = String::new();
-        let _ = write!(
-            humidity_text,
-            "Hum % r/a: {:.1}/{:.1}",
-            sensor_data.raw_humidity, sensor_data.humidity
-        );
+    /// Draws the seconds left of the ENS160 warmup in place of the initialization message
+    fn draw_warmup_countdown<D>(&self, display: &mut D, remaining: u16)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let settings_image = Image::new(&self.init_icon, self.air_quality_position);
+        settings_image.draw(&mut Thresholded::new(display)).unwrap_or_default();
+
+        let mut countdown_text: String<20> = String::new();
+        let _ = write!(countdown_text, "Warming up: {remaining}s");
         Text::with_baseline(
-            &humidity_text,
-            self.humidity_position,
-            self.humidity_text_style,
+            &countdown_text,
+            self.sensor_init_position,
+            self.sensor_init_text_style,
             Baseline::Top,
         )
         .draw(display)
         .unwrap_or_default();
     }
 
-    /// Draws CO2 history bar chart to the display
-    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap, clippy::cast_sign_loss)]
-    fn draw_co2_history<D>(&self, display: &mut D, co2_history: &[u16])
+    /// Draws the storage mode notice, telling how to wake the device again
+    fn draw_storage_mode_message<D>(&self, display: &mut D)
     where
-        D: DrawTarget<Color = BinaryColor>,
+        D: DrawTarget<Color = C>,
     {
-        // Draw the title "CO2 history" where air quality normally appears
         Text::with_baseline(
-            "CO2 history",
-            self.air_quality_position,
-            self.air_quality_text_style,
+            "Storage mode",
+            self.sensor_init_position,
+            self.sensor_init_text_style,
             Baseline::Top,
         )
         .draw(display)
         .unwrap_or_default();
-
-        if co2_history.is_empty() {
-            // Show message if no history available
-            Text::with_baseline("No data yet", self.co2_position, self.co2_text_style, Baseline::Top)
-                .draw(display)
-                .unwrap_or_default();
-            return;
-        }
-
-        // Find min and max CO2 values for scaling
-        let min_co2 = *co2_history.iter().min().unwrap_or(&0);
-        let max_co2 = *co2_history.iter().max().unwrap_or(&1000);
-
-        // Avoid division by zero
-        let range = if max_co2 > min_co2 { max_co2 - min_co2 } else { 1 };
-
-        // Bar chart area: configured in Settings
-        let chart_start_y = self.chart_start_y;
-        let chart_height = self.chart_height;
-        let chart_width = self.chart_width;
-        #[allow(clippy::cast_possible_truncation)]
-        let bar_width = chart_width / co2_history.len().max(1) as i32;
-
-        // Draw bars
-        for (i, &co2_value) in co2_history.iter().enumerate() {
-            // Calculate bar height (scaled to chart area)
-            let normalized_value = co2_value.saturating_sub(min_co2);
-            let bar_height = if range > 0 {
-                (i32::from(normalized_value) * chart_height) / i32::from(range)
-            } else {
-                1
-            };
-
-            // Calculate bar position
-            #[allow(clippy::cast_possible_truncation)]
-            let bar_x = i as i32 * bar_width;
-            let bar_y = chart_start_y + chart_height - bar_height; // Draw from bottom up
-
-            // Draw hatched bar to reduce power consumption
-            self.draw_hatched_bar(
-                display,
-                Point::new(bar_x, bar_y),
-                Size::new(
-                    (bar_width - 1).max(0) as u32, // -1 for spacing between bars, ensure non-negative
-                    bar_height.max(0) as u32,
-                ),
-            );
-        }
-
-        // Draw min/max labels - using configured positions and smaller font
-        let mut min_text: String<16> = String::new();
-        let _ = write!(min_text, "Min: {min_co2}");
         Text::with_baseline(
-            &min_text,
-            self.minmax_min_position,
-            self.minmax_text_style,
+            "Connect USB to wake",
+            self.sensor_init_position + Point::new(0, 14),
+            self.sensor_init_text_style,
             Baseline::Top,
         )
         .draw(display)
         .unwrap_or_default();
+    }
 
-        let mut max_text: String<16> = String::new();
-        let _ = write!(max_text, "Max: {max_co2}");
-        Text::with_baseline(
-            &max_text,
+    /// Draws sensor data to the display
+    #[allow(clippy::too_many_lines)]
+    fn draw_sensor_data<D>(
+        &self,
+        display: &mut D,
+        sensor_data: &SensorData,
+        co2_trend: Co2Trend,
+        temperature_unit: TemperatureUnit,
+    ) where
+        D: DrawTarget<Color = C>,
+    {
+        // Draw the air quality text, the longest combined form "AQI 1 (Excellent)" is 102px wide in the narrow font
+        // The icon takes the start of the line, a label next to it is moved past it (at most 88px wide)
+        if matches!(AQI_DISPLAY, AqiDisplay::Icon | AqiDisplay::IconAndLabel) {
+            self.draw_aqi_icon(display, sensor_data.air_quality);
+        }
+        let mut aq_text: String<20> = String::new();
+        let mut aq_position = self.air_quality_position;
+        let aq_text_style = match AQI_DISPLAY {
+            AqiDisplay::Label => {
+                let _ = write!(aq_text, "{:?}", sensor_data.air_quality);
+                self.air_quality_text_style
+            }
+            AqiDisplay::Icon => self.air_quality_text_style,
+            AqiDisplay::IconAndLabel => {
+                let _ = write!(aq_text, "{:?}", sensor_data.air_quality);
+                aq_position += Point::new(AQI_ICON_WIDTH + 3, 0);
+                self.air_quality_text_style
+            }
+            AqiDisplay::LabelAndIndex => {
+                let _ = write!(
+                    aq_text,
+                    "AQI {} ({:?})",
+                    aqi_to_index(sensor_data.air_quality),
+                    sensor_data.air_quality
+                );
+                self.air_quality_index_text_style
+            }
+        };
+        Text::with_baseline(&aq_text, aq_position, aq_text_style, Baseline::Top)
+            .draw(display)
+            .unwrap_or_default();
+
+        // Draw the CO2 text
+        let mut co2_text: String<16> = String::new();
+        let settling_marker = if SHOW_SETTLING_INDICATOR && sensor_data.settling {
+            "~"
+        } else {
+            ""
+        };
+        let suspect_marker = if sensor_data.co2_suspect { "?" } else { "" };
+        let _ = write!(
+            co2_text,
+            "CO2: {settling_marker}{} ppm{suspect_marker}",
+            sensor_data.co2
+        );
+        let co2_text_end = Self::draw_text(
+            display,
+            &co2_text,
+            self.co2_position,
+            self.co2_text_style,
+            EMPHASIZED_METRIC == Some(EmphasizedMetric::Co2)
+                || (COLOR_SCHEME == ColorScheme::Accent && sensor_data.co2 >= CO2_ALARM_THRESHOLD),
+        );
+        if SHOW_CO2_TREND {
+            self.draw_trend_arrow(display, co2_text_end + Point::new(3, 0), co2_trend);
+        }
+
+        // Draw the ethanol or TVOC text, the longest form "TVOC: 65535 ppb High" is 120px wide
+        let mut voc_text: String<24> = String::new();
+        let _ = write!(voc_text, "{}: {} ppb", sensor_data.voc_kind.label(), sensor_data.voc);
+        if SHOW_VOC_BAND {
+            let band = match sensor_data.voc_kind {
+                VocKind::Ethanol => etoh_band(sensor_data.voc),
+                VocKind::Tvoc => tvoc_band(sensor_data.voc),
+            };
+            let _ = write!(voc_text, " {band}");
+        }
+        Self::draw_text(
+            display,
+            &voc_text,
+            self.voc_position,
+            self.voc_text_style,
+            EMPHASIZED_METRIC == Some(EmphasizedMetric::Voc),
+        );
+
+        // Draw the temperature text with raw and adjusted values
+        let mut temp_text: String<32> = String::new();
+        let _ = write!(
+            temp_text,
+            "Temp {} r/a: {:.1}/{:.1}",
+            temperature_unit.symbol(),
+            temperature_unit.from_celsius(sensor_data.raw_temperature),
+            temperature_unit.from_celsius(sensor_data.temperature)
+        );
+        Self::draw_text(
+            display,
+            &temp_text,
+            self.temperature_position,
+            self.temperature_text_style,
+            EMPHASIZED_METRIC == Some(EmphasizedMetric::Temperature),
+        );
+
+        // Draw the humidity text with raw and adjusted values, or the adjusted value and the dew point
+        // At most "Hum % r/a: 100.0/100.0" or "Hum 100.0% Dew -40.0F", well within the buffer
+        let mut humidity_text: String<32> = String::new();
+        if SHOW_DEW_POINT {
+            let _ = write!(
+                humidity_text,
+                "Hum {:.decimals$}% Dew {:.1}{}",
+                sensor_data.humidity,
+                temperature_unit.from_celsius(sensor_data.dew_point),
+                temperature_unit.symbol(),
+                decimals = HUMIDITY_DECIMALS
+            );
+        } else {
+            let _ = write!(
+                humidity_text,
+                "Hum % r/a: {:.decimals$}/{:.decimals$}",
+                sensor_data.raw_humidity,
+                sensor_data.humidity,
+                decimals = HUMIDITY_DECIMALS
+            );
+        }
+        Self::draw_text(
+            display,
+            &humidity_text,
+            self.humidity_position,
+            self.humidity_text_style,
+            EMPHASIZED_METRIC == Some(EmphasizedMetric::Humidity),
+        );
+    }
+
+    /// Draws a text at the top left `position`. Emphasized text is drawn in the accent color, or without one inverted
+    /// as dark text on a lit background filling its bounding box. Returns the position after the text
+    fn draw_text<D>(
+        display: &mut D,
+        text: &str,
+        position: Point,
+        style: MonoTextStyle<'_, C>,
+        emphasized: bool,
+    ) -> Point
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let text = Text::with_baseline(text, position, style, Baseline::Top);
+        if !emphasized {
+            return text.draw(display).unwrap_or_default();
+        }
+
+        if let Some(accent) = Self::accent_color() {
+            let mut accent_style = style;
+            accent_style.text_color = Some(accent);
+            return Text {
+                character_style: accent_style,
+                ..text
+            }
+            .draw(display)
+            .unwrap_or_default();
+        }
+
+        text.bounding_box()
+            .into_styled(PrimitiveStyle::with_fill(C::FOREGROUND))
+            .draw(display)
+            .unwrap_or_default();
+        let mut inverted_style = style;
+        inverted_style.text_color = Some(C::BACKGROUND);
+        Text {
+            character_style: inverted_style,
+            ..text
+        }
+        .draw(display)
+        .unwrap_or_default()
+    }
+
+    /// Draws CO2 history bar chart to the display, `read_interval` in seconds spaces the bars for the time axis
+    fn draw_co2_history<D>(&self, display: &mut D, co2_history: &[u16], read_interval: u64, scale: ChartScale)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        // Draw the title "CO2 history" where air quality normally appears, naming a logarithmic axis
+        let title = match scale {
+            ChartScale::Linear => "CO2 history",
+            ChartScale::Logarithmic => "CO2 log scale",
+        };
+        Text::with_baseline(
+            title,
+            self.air_quality_position,
+            self.air_quality_text_style,
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap_or_default();
+
+        if co2_history.is_empty() {
+            // Show message if no history available
+            Text::with_baseline("No data yet", self.co2_position, self.co2_text_style, Baseline::Top)
+                .draw(display)
+                .unwrap_or_default();
+            return;
+        }
+
+        // Find min and max CO2 values for scaling
+        let min_co2 = *co2_history.iter().min().unwrap_or(&0);
+        let max_co2 = *co2_history.iter().max().unwrap_or(&1000);
+
+        // The bars are drawn from the scaled levels, the logarithmic chart reaches down to its floor
+        let bottom = match scale {
+            ChartScale::Linear => min_co2,
+            ChartScale::Logarithmic => min_co2.min(CO2_CHART_LOG_FLOOR),
+        };
+        let (scaled_min, scaled_max) = (scale_co2(scale, bottom), scale_co2(scale, max_co2));
+
+        // Avoid division by zero
+        let range = if scaled_max > scaled_min {
+            scaled_max - scaled_min
+        } else {
+            1
+        };
+
+        // Bars at or above the alarm threshold stand out, the scaling keeps the order of the levels
+        self.draw_history_bars(
+            display,
+            co2_history.iter().map(|&co2| scale_co2(scale, co2)),
+            scaled_min,
+            range,
+            read_interval,
+            |scaled| COLOR_SCHEME == ColorScheme::Accent && scaled >= scale_co2(scale, CO2_ALARM_THRESHOLD),
+        );
+
+        let mut min_text: String<16> = String::new();
+        let _ = write!(min_text, "Min: {min_co2}");
+        let mut max_text: String<16> = String::new();
+        let _ = write!(max_text, "Max: {max_co2}");
+        self.draw_minmax_labels(display, &min_text, &max_text);
+    }
+
+    /// Draws humidity history bar chart to the display, `read_interval` in seconds spaces the bars for the time axis
+    /// The bars are scaled to the fixed `HUMIDITY_CHART_MIN` to `HUMIDITY_CHART_MAX` band instead of the range of the
+    /// history, so a few tenths of a percent of sensor noise do not fill the chart
+    fn draw_humidity_history<D>(&self, display: &mut D, humidity_history: &[u8], read_interval: u64)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        // Draw the title "RH history" where air quality normally appears
+        Text::with_baseline(
+            "RH history",
+            self.air_quality_position,
+            self.air_quality_text_style,
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap_or_default();
+
+        if humidity_history.is_empty() {
+            // Show message if no history available
+            Text::with_baseline("No data yet", self.co2_position, self.co2_text_style, Baseline::Top)
+                .draw(display)
+                .unwrap_or_default();
+            return;
+        }
+
+        let min_humidity = *humidity_history.iter().min().unwrap_or(&0);
+        let max_humidity = *humidity_history.iter().max().unwrap_or(&100);
+
+        self.draw_history_bars(
+            display,
+            humidity_history
+                .iter()
+                .map(|&humidity| u16::from(humidity.clamp(HUMIDITY_CHART_MIN, HUMIDITY_CHART_MAX))),
+            u16::from(HUMIDITY_CHART_MIN),
+            u16::from(HUMIDITY_CHART_MAX - HUMIDITY_CHART_MIN),
+            read_interval,
+            |_| false,
+        );
+
+        let mut min_text: String<16> = String::new();
+        let _ = write!(min_text, "Min: {min_humidity}%");
+        let mut max_text: String<16> = String::new();
+        let _ = write!(max_text, "Max: {max_humidity}%");
+        self.draw_minmax_labels(display, &min_text, &max_text);
+    }
+
+    /// Draws the bars of a history chart with the time axis below, oldest value first
+    /// A bar at `min` is empty, one at `min + range` takes the full chart height. `emphasized` picks the bars drawn to
+    /// stand out
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+    fn draw_history_bars<D>(
+        &self,
+        display: &mut D,
+        values: impl ExactSizeIterator<Item = u16>,
+        min: u16,
+        range: u16,
+        read_interval: u64,
+        emphasized: impl Fn(u16) -> bool,
+    ) where
+        D: DrawTarget<Color = C>,
+    {
+        let count = values.len();
+
+        // Bar chart area: configured in Settings, the time axis labels take the bottom rows
+        let chart_start_y = self.chart_start_y;
+        let chart_height = if SHOW_CHART_TIME_LABELS {
+            self.chart_height - CHART_TIME_LABEL_HEIGHT
+        } else {
+            self.chart_height
+        };
+        let chart_width = self.chart_width;
+        let bar_width = chart_width / count.max(1) as i32;
+
+        // Draw bars
+        for (i, value) in values.enumerate() {
+            // Calculate bar height (scaled to chart area)
+            let normalized_value = value.saturating_sub(min);
+            let bar_height = if range > 0 {
+                (i32::from(normalized_value) * chart_height) / i32::from(range)
+            } else {
+                1
+            };
+
+            // Calculate bar position
+            let bar_x = i as i32 * bar_width;
+            let bar_y = chart_start_y + chart_height - bar_height; // Draw from bottom up
+
+            // Draw hatched bar to reduce power consumption
+            self.draw_hatched_bar(
+                display,
+                Point::new(bar_x, bar_y),
+                Size::new(
+                    (bar_width - 1).max(0) as u32, // -1 for spacing between bars, ensure non-negative
+                    bar_height.max(0) as u32,
+                ),
+                emphasized(value),
+            );
+        }
+
+        if SHOW_CHART_TIME_LABELS && count > 0 {
+            self.draw_chart_time_labels(
+                display,
+                chart_start_y + chart_height + 1,
+                (count as u64 - 1) * read_interval,
+            );
+        }
+    }
+
+    /// Draws the CO2 level in large seven-segment digits centered in the main area, with the air quality label below
+    /// `FONT_10X20` is the largest font at hand and too small to read across a room, so the digits are drawn as
+    /// filled rectangles
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    fn draw_co2_large<D>(&self, display: &mut D, sensor_data: &SensorData)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let mut co2_text: String<8> = String::new();
+        let _ = write!(co2_text, "{}", sensor_data.co2);
+
+        // Five digit levels get narrower digits, so they still fit left of the battery icon
+        let main_width = self.bat_position.x;
+        let digits = co2_text.len() as i32;
+        let digit_width = CO2_LARGE_DIGIT_WIDTH.min((main_width - (digits - 1) * CO2_LARGE_DIGIT_GAP) / digits.max(1));
+        let total_width = digits * digit_width + (digits - 1) * CO2_LARGE_DIGIT_GAP;
+        let color = if COLOR_SCHEME == ColorScheme::Accent && sensor_data.co2 >= CO2_ALARM_THRESHOLD {
+            Self::accent_color().unwrap_or(C::FOREGROUND)
+        } else {
+            C::FOREGROUND
+        };
+
+        let mut origin = Point::new((main_width - total_width) / 2, 2);
+        for digit in co2_text.bytes() {
+            Self::draw_seven_segment_digit(display, origin, digit_width, digit - b'0', color);
+            origin.x += digit_width + CO2_LARGE_DIGIT_GAP;
+        }
+
+        let mut aq_text: String<16> = String::new();
+        let _ = write!(aq_text, "{:?}", sensor_data.air_quality);
+        Text::with_text_style(
+            &aq_text,
+            Point::new(main_width / 2, 2 + CO2_LARGE_DIGIT_HEIGHT + 6),
+            self.air_quality_text_style,
+            TextStyleBuilder::new()
+                .alignment(Alignment::Center)
+                .baseline(Baseline::Top)
+                .build(),
+        )
+        .draw(display)
+        .unwrap_or_default();
+    }
+
+    /// Draws the idle screen, the time as `(hour, minute)` in large digits if known or else the logo
+    /// The content moves by a few pixels every minute, so no pixel stays lit for hours
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    fn draw_idle_screen<D>(&self, display: &mut D, time: Option<(u8, u8)>)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let step = (Instant::now().as_secs() / 60 % 8) as i32;
+        let shift = Point::new(step % 4 * 4, step / 4 * 6);
+
+        let Some((hour, minute)) = time else {
+            let logo_position = Point::new(4, 18) + shift;
+            Text::with_baseline("AQ Monitor", logo_position, self.air_quality_text_style, Baseline::Top)
+                .draw(display)
+                .unwrap_or_default();
+            Text::with_baseline(
+                FIRMWARE_VERSION,
+                logo_position + Point::new(0, 16),
+                self.firmware_version_text_style,
+                Baseline::Top,
+            )
+            .draw(display)
+            .unwrap_or_default();
+            return;
+        };
+
+        // Four narrow digits with a colon in the middle fit left of the battery icon with room to move
+        let digit_width = 16;
+        let colon_width = 12;
+        let mut origin = Point::new(2, 8) + shift;
+        for (index, digit) in [hour / 10, hour % 10, minute / 10, minute % 10].into_iter().enumerate() {
+            Self::draw_seven_segment_digit(display, origin, digit_width, digit, C::FOREGROUND);
+            origin.x += digit_width + CO2_LARGE_DIGIT_GAP;
+            if index == 1 {
+                for dot_y in [CO2_LARGE_DIGIT_HEIGHT / 3, CO2_LARGE_DIGIT_HEIGHT * 2 / 3] {
+                    Rectangle::new(
+                        Point::new(origin.x + (colon_width - CO2_LARGE_SEGMENT) / 2, origin.y + dot_y - 2),
+                        Size::new(CO2_LARGE_SEGMENT as u32, CO2_LARGE_SEGMENT as u32),
+                    )
+                    .into_styled(PrimitiveStyle::with_fill(C::FOREGROUND))
+                    .draw(display)
+                    .unwrap_or_default();
+                }
+                origin.x += colon_width;
+            }
+        }
+    }
+
+    /// Draws a seven-segment digit with its top left corner at `origin`
+    #[allow(clippy::cast_sign_loss)]
+    fn draw_seven_segment_digit<D>(display: &mut D, origin: Point, width: i32, digit: u8, color: C)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let half = CO2_LARGE_DIGIT_HEIGHT / 2;
+        let thickness = CO2_LARGE_SEGMENT;
+        // Top left corner and size of the segments a to g
+        let segments = [
+            (Point::new(0, 0), (width, thickness)),
+            (Point::new(width - thickness, 0), (thickness, half)),
+            (Point::new(width - thickness, half), (thickness, half)),
+            (Point::new(0, CO2_LARGE_DIGIT_HEIGHT - thickness), (width, thickness)),
+            (Point::new(0, half), (thickness, half)),
+            (Point::new(0, 0), (thickness, half)),
+            (Point::new(0, half - thickness / 2), (width, thickness)),
+        ];
+        let lit = SEVEN_SEGMENT_DIGITS.get(usize::from(digit)).copied().unwrap_or(0);
+        for (index, (offset, (segment_width, segment_height))) in segments.into_iter().enumerate() {
+            if lit & (1 << index) == 0 {
+                continue;
+            }
+            Rectangle::new(
+                origin + offset,
+                Size::new(segment_width.max(0) as u32, segment_height.max(0) as u32),
+            )
+            .into_styled(PrimitiveStyle::with_fill(color))
+            .draw(display)
+            .unwrap_or_default();
+        }
+    }
+
+    /// Draws the min/max labels of a history chart, using configured positions and smaller font
+    fn draw_minmax_labels<D>(&self, display: &mut D, min_text: &str, max_text: &str)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        Text::with_baseline(
+            min_text,
+            self.minmax_min_position,
+            self.minmax_text_style,
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap_or_default();
+
+        Text::with_baseline(
+            max_text,
             self.minmax_max_position,
             self.minmax_text_style,
             Baseline::Top,
@@ -623,20 +2070,438 @@ impl Settings<'_> {
         .unwrap_or_default();
     }
 
+    /// Draws the time axis below a history chart, the age of the oldest bar on the left and "now" on the right
+    fn draw_chart_time_labels<D>(&self, display: &mut D, y: i32, span_secs: u64)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let minutes = span_secs / 60;
+        let mut span_text: String<8> = String::new();
+        let _ = match minutes {
+            0 => Ok(()),
+            1..=119 => write!(span_text, "-{minutes}m"),
+            _ => write!(span_text, "-{}h", minutes / 60),
+        };
+        Text::with_baseline(&span_text, Point::new(0, y), self.minmax_text_style, Baseline::Top)
+            .draw(display)
+            .unwrap_or_default();
+
+        Text::with_text_style(
+            "now",
+            Point::new(self.chart_width, y),
+            self.minmax_text_style,
+            TextStyleBuilder::new()
+                .alignment(Alignment::Right)
+                .baseline(Baseline::Top)
+                .build(),
+        )
+        .draw(display)
+        .unwrap_or_default();
+    }
+
+    /// Draws all metrics on two dense lines, with a CO2 sparkline below
+    /// Each line stays within 21 characters so it fits left of the battery icon
+    fn draw_compact<D>(
+        &self,
+        display: &mut D,
+        sensor_data: &SensorData,
+        co2_history: &[u16],
+        temperature_unit: TemperatureUnit,
+    ) where
+        D: DrawTarget<Color = C>,
+    {
+        let mut line1: String<24> = String::new();
+        let _ = write!(line1, "{:?} CO2 {}", sensor_data.air_quality, sensor_data.co2);
+        Text::with_baseline(
+            &line1,
+            self.compact_line1_position,
+            self.compact_text_style,
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap_or_default();
+
+        let mut line2: String<24> = String::new();
+        let _ = write!(
+            line2,
+            "{:.1}{} {:.0}% {} {}",
+            temperature_unit.from_celsius(sensor_data.temperature),
+            temperature_unit.symbol(),
+            sensor_data.humidity,
+            sensor_data.voc_kind.label(),
+            sensor_data.voc
+        );
+        Text::with_baseline(
+            &line2,
+            self.compact_line2_position,
+            self.compact_text_style,
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap_or_default();
+
+        self.draw_sparkline(display, co2_history);
+    }
+
+    /// Draws the focus metric with its label and large digits, and the other metrics on a small line below
+    fn draw_focus<D>(
+        &self,
+        display: &mut D,
+        sensor_data: &SensorData,
+        focus_metric: FocusMetric,
+        temperature_unit: TemperatureUnit,
+    ) where
+        D: DrawTarget<Color = C>,
+    {
+        let temperature = temperature_unit.from_celsius(sensor_data.temperature);
+        let mut label: String<16> = String::new();
+        let mut value: String<16> = String::new();
+        let mut others: String<24> = String::new();
+        match focus_metric {
+            FocusMetric::Co2 => {
+                let _ = write!(label, "CO2 ppm");
+                let _ = write!(value, "{}", sensor_data.co2);
+                let _ = write!(
+                    others,
+                    "{:.1}{} {:.0}%",
+                    temperature,
+                    temperature_unit.symbol(),
+                    sensor_data.humidity
+                );
+            }
+            FocusMetric::Temperature => {
+                let _ = write!(label, "Temp {}", temperature_unit.symbol());
+                let _ = write!(value, "{temperature:.1}");
+                let _ = write!(others, "CO2 {} {:.0}%", sensor_data.co2, sensor_data.humidity);
+            }
+            FocusMetric::Humidity => {
+                let _ = write!(label, "Humidity %");
+                let _ = write!(value, "{:.0}", sensor_data.humidity);
+                let _ = write!(
+                    others,
+                    "CO2 {} {:.1}{}",
+                    sensor_data.co2,
+                    temperature,
+                    temperature_unit.symbol()
+                );
+            }
+        }
+
+        Text::with_baseline(
+            &label,
+            self.air_quality_position,
+            self.air_quality_text_style,
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap_or_default();
+        Text::with_baseline(
+            &value,
+            self.focus_value_position,
+            self.focus_value_text_style,
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap_or_default();
+        Text::with_baseline(
+            &others,
+            self.focus_others_position,
+            self.compact_text_style,
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap_or_default();
+    }
+
+    /// Draws the CO2 history as a line connecting the scaled values
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    fn draw_sparkline<D>(&self, display: &mut D, co2_history: &[u16])
+    where
+        D: DrawTarget<Color = C>,
+    {
+        if co2_history.len() < 2 {
+            return;
+        }
+
+        let min_co2 = *co2_history.iter().min().unwrap_or(&0);
+        let max_co2 = *co2_history.iter().max().unwrap_or(&1000);
+        let range = i32::from(max_co2.saturating_sub(min_co2).max(1));
+
+        let width = self.sparkline_size.width as i32 - 1;
+        let height = self.sparkline_size.height as i32 - 1;
+        let step = width / (co2_history.len() as i32 - 1);
+
+        let to_point = |i: usize, co2: u16| {
+            let x = self.sparkline_position.x + i as i32 * step;
+            let y = self.sparkline_position.y + height - (i32::from(co2 - min_co2) * height) / range;
+            Point::new(x, y)
+        };
+
+        for (i, pair) in co2_history.windows(2).enumerate() {
+            if let [from, to] = *pair {
+                Line::new(to_point(i, from), to_point(i + 1, to))
+                    .into_styled(PrimitiveStyle::with_stroke(C::FOREGROUND, 1))
+                    .draw(display)
+                    .unwrap_or_default();
+            }
+        }
+    }
+
+    /// Draws the diagnostics screen with the per-device I2C error counters
+    fn draw_diagnostics<D>(&self, display: &mut D, diagnostics: &Diagnostics)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        Text::with_baseline(
+            "Diagnostics",
+            self.air_quality_position,
+            self.air_quality_text_style,
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap_or_default();
+
+        let lines = [
+            ("AHT21 err", BusDevice::Aht21, self.co2_position),
+            ("ENS160 err", BusDevice::Ens160, self.voc_position),
+            ("OLED err", BusDevice::Display, self.temperature_position),
+        ];
+        for (label, device, position) in lines {
+            let mut text: String<24> = String::new();
+            let _ = write!(text, "{label}: {}", diagnostics.i2c_errors(device));
+            Text::with_baseline(&text, position, self.co2_text_style, Baseline::Top)
+                .draw(display)
+                .unwrap_or_default();
+        }
+
+        // How long the ENS160 has been producing valid data, eCO2 keeps stabilizing over hours
+        let mut valid_text: String<24> = String::new();
+        match diagnostics.ens160_valid_for() {
+            Some(valid_for) => {
+                let minutes = valid_for.as_secs() / 60;
+                let _ = write!(valid_text, "Valid: {}h{:02}m", minutes / 60, minutes % 60);
+            }
+            None => {
+                let _ = write!(valid_text, "Valid: --");
+            }
+        }
+        Text::with_baseline(&valid_text, self.humidity_position, self.co2_text_style, Baseline::Top)
+            .draw(display)
+            .unwrap_or_default();
+    }
+
+    /// Draws the session statistics
+    fn draw_stats<D>(&self, display: &mut D, state: &SystemState)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        Text::with_baseline(
+            "Session",
+            self.air_quality_position,
+            self.air_quality_text_style,
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap_or_default();
+
+        let mut exceedances_text: String<24> = String::new();
+        let _ = write!(
+            exceedances_text,
+            "CO2>{}: {}x",
+            CO2_EXCEEDANCE_THRESHOLD,
+            state.get_co2_exceedances()
+        );
+        Text::with_baseline(&exceedances_text, self.co2_position, self.co2_text_style, Baseline::Top)
+            .draw(display)
+            .unwrap_or_default();
+
+        let minutes = state.get_session_duration().as_secs() / 60;
+        let mut since_text: String<24> = String::new();
+        let _ = write!(since_text, "Since: {}h{:02}m", minutes / 60, minutes % 60);
+        Text::with_baseline(&since_text, self.voc_position, self.co2_text_style, Baseline::Top)
+            .draw(display)
+            .unwrap_or_default();
+
+        let extremes = state.get_extremes_of(STATS_EXTREMES);
+        if extremes.is_empty() {
+            return;
+        }
+        // The title names the session, so only all-time extremes are marked
+        let scope_suffix = match STATS_EXTREMES {
+            ExtremesScope::Session => "",
+            ExtremesScope::AllTime => " (all)",
+        };
+        let mut co2_text: String<24> = String::new();
+        let _ = write!(
+            co2_text,
+            "CO2 {}-{}{}",
+            extremes.co2_min, extremes.co2_max, scope_suffix
+        );
+        Text::with_baseline(&co2_text, self.temperature_position, self.co2_text_style, Baseline::Top)
+            .draw(display)
+            .unwrap_or_default();
+
+        let unit = state.get_temperature_unit();
+        let mut range_text: String<24> = String::new();
+        let _ = write!(
+            range_text,
+            "{:.1}-{:.1}{} {:.0}-{:.0}%",
+            unit.from_celsius(extremes.temperature_min),
+            unit.from_celsius(extremes.temperature_max),
+            unit.symbol(),
+            extremes.humidity_min,
+            extremes.humidity_max
+        );
+        Text::with_baseline(&range_text, self.humidity_position, self.co2_text_style, Baseline::Top)
+            .draw(display)
+            .unwrap_or_default();
+    }
+
+    /// Draws how long ago the last reading arrived, e.g. "12m", in the 20 pixel wide column below the battery icon
+    fn draw_reading_age<D>(&self, display: &mut D, age: Option<Duration>)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        Rectangle::new(self.reading_age_position, Size::new(20, 8))
+            .into_styled(PrimitiveStyle::with_fill(C::BACKGROUND))
+            .draw(display)
+            .unwrap_or_default();
+
+        let Some(age) = age else {
+            return;
+        };
+        let minutes = age.as_secs() / 60;
+        let mut age_text: String<8> = String::new();
+        let _ = match minutes {
+            0 => write!(age_text, "<1m"),
+            1..=59 => write!(age_text, "{minutes}m"),
+            60..=5999 => write!(age_text, "{}h", minutes / 60),
+            _ => write!(age_text, ">99h"),
+        };
+        Text::with_baseline(
+            &age_text,
+            self.reading_age_position,
+            self.reading_age_text_style,
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap_or_default();
+    }
+
+    /// Draws a notice over the top line that CO2 and VOC are not read on a low battery, on every screen as the
+    /// values shown are from before
+    fn draw_reduced_sensing_notice<D>(&self, display: &mut D)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        #[allow(clippy::cast_sign_loss)]
+        let top_line = Rectangle::new(Point::new(0, 0), Size::new(self.bat_position.x.max(0) as u32, 13));
+        top_line
+            .into_styled(PrimitiveStyle::with_fill(C::BACKGROUND))
+            .draw(display)
+            .unwrap_or_default();
+        Text::with_baseline(
+            "CO2 paused",
+            self.air_quality_position,
+            self.air_quality_text_style,
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap_or_default();
+    }
+
+    /// Draws the low battery warning as a banner of dark text on a lit background over the bottom of the main area
+    fn draw_low_battery_warning<D>(&self, display: &mut D)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        #[allow(clippy::cast_sign_loss)]
+        let banner = Rectangle::new(
+            Point::new(0, LOW_BATTERY_BANNER_TOP),
+            Size::new(self.bat_position.x.max(0) as u32, 14),
+        );
+        banner
+            .into_styled(PrimitiveStyle::with_fill(C::FOREGROUND))
+            .draw(display)
+            .unwrap_or_default();
+        let mut inverted_style = self.air_quality_text_style;
+        inverted_style.text_color = Some(C::BACKGROUND);
+        Text::with_baseline(
+            "LOW BATTERY",
+            Point::new(2, LOW_BATTERY_BANNER_TOP + 1),
+            inverted_style,
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap_or_default();
+    }
+
+    /// Draws the marker for poor quality among the recent readings
+    fn draw_quality_indicator<D>(&self, display: &mut D)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        Text::with_baseline(
+            "!",
+            self.quality_indicator_position,
+            self.reading_age_text_style,
+            Baseline::Top,
+        )
+        .draw(display)
+        .unwrap_or_default();
+    }
+
+    /// Draws a 7x7 pixel trend arrow, vertically centered on a line of text starting at `position`
+    #[allow(clippy::unused_self)]
+    fn draw_trend_arrow<D>(&self, display: &mut D, position: Point, trend: Co2Trend)
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let top = position + Point::new(0, 3);
+        let fill = PrimitiveStyle::with_fill(C::FOREGROUND);
+        match trend {
+            Co2Trend::Rising => Triangle::new(top + Point::new(3, 0), top + Point::new(0, 6), top + Point::new(6, 6))
+                .into_styled(fill)
+                .draw(display)
+                .unwrap_or_default(),
+            Co2Trend::Falling => Triangle::new(top, top + Point::new(6, 0), top + Point::new(3, 6))
+                .into_styled(fill)
+                .draw(display)
+                .unwrap_or_default(),
+            Co2Trend::Flat => Line::new(top + Point::new(0, 3), top + Point::new(6, 3))
+                .into_styled(PrimitiveStyle::with_stroke(C::FOREGROUND, 1))
+                .draw(display)
+                .unwrap_or_default(),
+        }
+    }
+
     /// Draws a hatched bar pattern to reduce power consumption compared to solid fill
+    /// An emphasized bar is hatched in the accent color, or without one filled solid
     #[allow(clippy::unused_self, clippy::cast_possible_wrap)]
-    fn draw_hatched_bar<D>(&self, display: &mut D, position: Point, size: Size)
+    fn draw_hatched_bar<D>(&self, display: &mut D, position: Point, size: Size, emphasized: bool)
     where
-        D: DrawTarget<Color = BinaryColor>,
+        D: DrawTarget<Color = C>,
     {
         if size.width == 0 || size.height == 0 {
             return;
         }
 
-        // Draw the outline of the bar first
         let bar_rect = Rectangle::new(position, size);
+        let color = match (emphasized, Self::accent_color()) {
+            (true, Some(accent)) => accent,
+            (true, None) => {
+                bar_rect
+                    .into_styled(PrimitiveStyle::with_fill(C::FOREGROUND))
+                    .draw(display)
+                    .unwrap_or_default();
+                return;
+            }
+            (false, _) => C::FOREGROUND,
+        };
+
+        // Draw the outline of the bar first
         bar_rect
-            .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .into_styled(PrimitiveStyle::with_stroke(color, 1))
             .draw(display)
             .unwrap_or_default();
 
@@ -649,7 +2514,7 @@ impl Settings<'_> {
         for y in (start_y + 1..end_y - 1).step_by(3) {
             if end_x > start_x {
                 Line::new(Point::new(start_x, y), Point::new(end_x, y))
-                    .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+                    .into_styled(PrimitiveStyle::with_stroke(color, 1))
                     .draw(display)
                     .unwrap_or_default();
             }
@@ -657,16 +2522,277 @@ impl Settings<'_> {
     }
 }
 
-/// Mode switching task that sends ToggleDisplayMode events every 10 seconds
+/// Maps a CO2 level to the bar value of the history chart in the given scale
+const fn scale_co2(scale: ChartScale, co2: u16) -> u16 {
+    match scale {
+        ChartScale::Linear => co2,
+        ChartScale::Logarithmic => log2_fixed(co2),
+    }
+}
+
+/// Base 2 logarithm with 12 fractional bits, the exponent takes the 4 bits above them
+/// There is no `f32::ln` without std. Between powers of two the fraction `x` is bent from a straight line by
+/// `0.3467 * x * (1 - x)`, which keeps the error below 0.01, within a pixel of the chart height
+#[allow(clippy::cast_possible_truncation)]
+const fn log2_fixed(value: u16) -> u16 {
+    // The logarithm of zero is undefined, it is drawn like a level of one
+    let value = if value == 0 { 1 } else { value };
+    let exponent = value.ilog2();
+    // Position between the powers of two with 16 fractional bits
+    let linear = (((value - (1 << exponent)) as u32) << 16) >> exponent;
+    let bend = ((linear * (0x1_0000 - linear)) >> 16) * 355 / 1024;
+    ((exponent << 12) | ((linear + bend) >> 4)) as u16
+}
+
+/// Maps the ENS160 air quality index to its numeric 1 (excellent) to 5 (unhealthy) value
+pub const fn aqi_to_index(aqi: AirQualityIndex) -> u8 {
+    match aqi {
+        AirQualityIndex::Excellent => 1,
+        AirQualityIndex::Good => 2,
+        AirQualityIndex::Moderate => 3,
+        AirQualityIndex::Poor => 4,
+        AirQualityIndex::Unhealthy => 5,
+    }
+}
+
+/// Maps an ethanol level in ppb to a qualitative band, abbreviated to fit behind the value
+const fn etoh_band(etoh: u16) -> &'static str {
+    if etoh >= ETOH_HIGH_PPB {
+        "High"
+    } else if etoh >= ETOH_MODERATE_PPB {
+        "Mod"
+    } else {
+        "Low"
+    }
+}
+
+/// Maps a TVOC level in ppb to a qualitative band, abbreviated to fit behind the value
+const fn tvoc_band(tvoc: u16) -> &'static str {
+    if tvoc >= TVOC_HIGH_PPB {
+        "High"
+    } else if tvoc >= TVOC_MODERATE_PPB {
+        "Mod"
+    } else {
+        "Low"
+    }
+}
+
+/// Mode switching task that sends `ToggleDisplayMode` events every 10 seconds, or ends a display freeze on its timeout
 #[embassy_executor::task]
 pub async fn mode_switch_task() {
     loop {
         Timer::after(TOGGLE_MODE).await;
+        storage_mode::park_if_active("Mode switch").await;
 
-        // Send toggle mode event to orchestrator
-        send_event(Event::ToggleDisplayMode).await;
+        let frozen_until = SYSTEM_STATE.lock().await.get_display_frozen_until();
+        match frozen_until {
+            // Unfreeze on the timeout, so a forgotten freeze does not stick
+            Some(until) if Instant::now() >= until => send_event(Event::SetDisplayFreeze(false)).await,
+            // Stay on the frozen screen
+            Some(_) => {}
+            // Send toggle mode event to orchestrator
+            None => send_event(Event::ToggleDisplayMode).await,
+        }
 
         // Report task success for watchdog health monitoring
         report_task_success(TaskId::ModeSwitch).await;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+
+    use super::*;
+    use crate::HOST_TEST_LOCK;
+
+    /// Height of the chart area below the title, without the time axis labels
+    const BAR_AREA_HEIGHT: i32 = 39 - CHART_TIME_LABEL_HEIGHT;
+
+    /// An empty frame
+    const fn blank() -> Framebuffer {
+        Framebuffer {
+            buffer: [0; SCREENSHOT_SIZE],
+        }
+    }
+
+    /// Whether the pixel at `x`, `y` of a drawn frame is on
+    #[allow(clippy::cast_sign_loss)]
+    fn lit(framebuffer: &Framebuffer, x: i32, y: i32) -> bool {
+        framebuffer.buffer[(y / 8 * 128 + x) as usize] & (1 << (y % 8)) != 0
+    }
+
+    /// Draws the CO2 history in the given scale and measures the height of each bar from its left outline
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    fn bar_heights(co2_history: &[u16], scale: ChartScale) -> Vec<i32> {
+        let settings: Settings = Settings::new().unwrap();
+        let mut framebuffer = blank();
+        settings.draw_co2_history(&mut framebuffer, co2_history, 300, scale);
+
+        let bar_width = settings.chart_width / co2_history.len() as i32;
+        (0..co2_history.len() as i32)
+            .map(|i| {
+                (settings.chart_start_y..settings.chart_start_y + BAR_AREA_HEIGHT)
+                    .filter(|&y| lit(&framebuffer, i * bar_width, y))
+                    .count() as i32
+            })
+            .collect()
+    }
+
+    /// Exact bar height in pixels of a level over a bottom of the chart, in the logarithmic scale
+    fn exact_log_height(co2: u16, bottom: u16, max: u16) -> f64 {
+        let log = |level: u16| f64::from(level).log2();
+        (log(co2) - log(bottom)) / (log(max) - log(bottom)) * f64::from(BAR_AREA_HEIGHT)
+    }
+
+    #[test]
+    fn log_scale_spreads_out_the_indoor_levels() {
+        let history = [420, 450, 500, 800, 1500, 2500];
+        let linear = bar_heights(&history, ChartScale::Linear);
+        let log = bar_heights(&history, ChartScale::Logarithmic);
+
+        // Linear bars start at the lowest level of the history
+        for (&co2, &height) in history.iter().zip(&linear) {
+            let exact = i32::from(co2 - 420) * BAR_AREA_HEIGHT / (2500 - 420);
+            assert_eq!(height, exact, "linear bar of {co2} ppm");
+        }
+        // Logarithmic bars start at the floor of 400 ppm
+        for (&co2, &height) in history.iter().zip(&log) {
+            let exact = exact_log_height(co2, CO2_CHART_LOG_FLOOR, 2500);
+            assert!(
+                (exact - 1.0..exact + 1.0).contains(&f64::from(height)),
+                "log bar of {co2} ppm"
+            );
+        }
+
+        // The peak fills the chart in both scales, the levels below 1000 ppm stand taller in the logarithmic one
+        assert_eq!(linear[5], BAR_AREA_HEIGHT);
+        assert_eq!(log[5], BAR_AREA_HEIGHT);
+        for i in 1..4 {
+            assert!(log[i] > linear[i], "bar of {} ppm", history[i]);
+        }
+        assert!(log.is_sorted());
+    }
+
+    #[test]
+    fn battery_icons_render_at_the_luma_threshold() {
+        let settings: Settings = Settings::new().unwrap();
+        let mut frames: Vec<[u8; SCREENSHOT_SIZE]> = Vec::new();
+        for level in [
+            BatteryLevel::Bat000,
+            BatteryLevel::Bat020,
+            BatteryLevel::Bat040,
+            BatteryLevel::Bat060,
+            BatteryLevel::Bat080,
+            BatteryLevel::Bat100,
+            BatteryLevel::Charging,
+        ] {
+            let mut thresholded = blank();
+            settings.draw_battery_icon(&mut thresholded, &level);
+
+            let icon = settings.get_battery_icon(&level);
+            for Pixel(point, color) in icon.pixels() {
+                let position = point + settings.bat_position;
+                assert_eq!(
+                    lit(&thresholded, position.x, position.y),
+                    color.luma() >= ICON_LUMA_THRESHOLD,
+                    "pixel {point} of the {level:?} icon"
+                );
+            }
+
+            // The fixed conversion the icons were drawn with before draws them the same
+            let mut converted = blank();
+            Image::new(icon, settings.bat_position)
+                .draw(&mut converted.color_converted())
+                .unwrap();
+            assert_eq!(thresholded.buffer, converted.buffer, "{level:?} icon");
+
+            assert!(!frames.contains(&thresholded.buffer), "{level:?} icon is not unique");
+            frames.push(thresholded.buffer);
+        }
+    }
+
+    #[test]
+    fn screens_render_like_the_color_conversion() {
+        let _guard = HOST_TEST_LOCK.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        block_on(async {
+            let settings: Settings = Settings::new().unwrap();
+            {
+                let mut state = SYSTEM_STATE.lock().await;
+                *state = SystemState::new();
+                state.set_battery_percent(64);
+                for co2 in [620, 840, 1250, 1720, 980] {
+                    state.add_co2_measurement(co2);
+                    state.add_humidity_measurement(48.0);
+                }
+                state.set_last_sensor_data(SensorData {
+                    temperature: 21.5,
+                    raw_temperature: 24.0,
+                    humidity: 48.0,
+                    raw_humidity: 44.0,
+                    dew_point: 10.1,
+                    co2: 1720,
+                    co2_suspect: false,
+                    voc: 120,
+                    voc_kind: VocKind::Ethanol,
+                    air_quality: AirQualityIndex::Moderate,
+                    settling: false,
+                    quality: ReadingQuality::Good,
+                });
+            }
+
+            // The screens were drawn through the identity `color_converted` before they took the target colors
+            for mode in [
+                DisplayMode::RawData,
+                DisplayMode::Co2History,
+                DisplayMode::HumidityHistory,
+                DisplayMode::Co2Large,
+                DisplayMode::Diagnostics,
+                DisplayMode::Compact,
+                DisplayMode::Stats,
+                DisplayMode::Focus,
+            ] {
+                SYSTEM_STATE.lock().await.set_display_mode(mode);
+                let mut direct = blank();
+                handle_display_command(DisplayCommand::ToggleMode, &mut direct, &settings).await;
+                let mut converted = blank();
+                handle_display_command(
+                    DisplayCommand::ToggleMode,
+                    &mut converted.color_converted::<BinaryColor>(),
+                    &settings,
+                )
+                .await;
+
+                assert!(direct.buffer.iter().any(|&byte| byte != 0), "{mode:?} screen is empty");
+                assert_eq!(direct.buffer, converted.buffer, "{mode:?} screen");
+            }
+        });
+    }
+
+    #[test]
+    fn log2_fixed_is_accurate_and_keeps_the_order() {
+        let mut previous = 0;
+        for value in 1..=u16::MAX {
+            let log = log2_fixed(value);
+            let error = f64::from(log) / 4096.0 - f64::from(value).log2();
+            assert!(error.abs() < 0.01, "log2 of {value} off by {error}");
+            assert!(log >= previous, "log2 of {value} below the one of {}", value - 1);
+            previous = log;
+        }
+    }
+
+    #[test]
+    fn log2_fixed_bars_are_within_a_pixel() {
+        // The bar heights of the chart from the floor to every peak, as drawn without the rounding to whole pixels
+        for max in (CO2_CHART_LOG_FLOOR + 1..=u16::MAX).step_by(7) {
+            let bottom = log2_fixed(CO2_CHART_LOG_FLOOR);
+            let range = log2_fixed(max) - bottom;
+            for co2 in (CO2_CHART_LOG_FLOOR..=max).step_by(usize::from((max - CO2_CHART_LOG_FLOOR) / 200).max(1)) {
+                let height = f64::from(log2_fixed(co2) - bottom) * f64::from(BAR_AREA_HEIGHT) / f64::from(range);
+                let exact = exact_log_height(co2, CO2_CHART_LOG_FLOOR, max);
+                let error = height - exact;
+                assert!(error.abs() < 0.6, "bar of {co2} ppm up to {max} ppm off by {error}");
+            }
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//! E-paper display backend, replacing the SSD1306 with the `epaper` feature
+//!
+//! Drives a Waveshare 2.9" V2 panel (SSD1680 controller, 296x128) over SPI. The 128x64 layout of the OLED is drawn
+//! with the same `Settings` routines, scaled up by two and centered on the panel. E-paper keeps its image without
+//! power but refreshes slowly and wears with every refresh, so the panel is only refreshed once per reading, when
+//! the sensors enter or leave the error standby, when the display is unfrozen and when the history is cleared. All
+//! other commands (battery updates, mode toggles, the reading age) only redraw the frame buffer and show up with the
+//! next refresh. A refresh waits `REDRAW_COALESCE_WINDOW` for further commands, so e.g. a cleared history and the
+//! following reading share one refresh. Between refreshes the controller is kept in deep sleep.
+//!
+//! The driver waits for the panel's busy line by polling, which blocks the executor for the duration of a refresh
+//! (about 2 seconds for a full one). Combine with `display-core1` to keep core 0 free during refreshes.
+
+use defmt::{Debug2Format, error, info};
+use embassy_rp::{
+    gpio::{Input, Output},
+    peripherals::SPI1,
+    spi::{Blocking, Spi},
+};
+use embassy_time::{Delay, Duration, Instant, Timer};
+use embedded_graphics::{pixelcolor::BinaryColor, prelude::*, primitives::Rectangle};
+use embedded_hal_bus::spi::ExclusiveDevice;
+use epd_waveshare::{
+    color::{Color, TriColor},
+    epd2in9_v2::{Display2in9, Epd2in9},
+    prelude::{DisplayRotation, RefreshLut, WaveshareDisplay},
+};
+
+use super::{
+    BOOT_REASON_TIME, DisplayCommand, Palette, boot_reason_to_show, handle_display_command,
+    initialize_display_settings, next_display_command, show_initial_screen, shown_while_frozen,
+};
+#[cfg(feature = "debug-serial")]
+use crate::diagnostics::record_loop_time;
+use crate::{
+    diagnostics::record_redraw,
+    storage_mode,
+    system_state::SYSTEM_STATE,
+    watchdog::{TaskId, report_task_failure, report_task_success},
+};
+
+/// SPI clock of the panel in Hz, well below the 20 MHz the SSD1680 supports for writes
+pub const SPI_FREQUENCY: u32 = 4_000_000;
+
+/// How long a refresh waits for further commands, commands arriving in the meantime are shown with the same refresh
+/// Much longer than on the OLED, as every refresh takes seconds and wears the panel
+const REDRAW_COALESCE_WINDOW: Duration = Duration::from_millis(1000);
+
+/// Number of quick refreshes between two full refreshes
+/// Quick refreshes do not flash the panel but leave ghosting behind, which a full refresh clears
+const QUICK_REFRESHES_PER_FULL: u8 = 9;
+
+/// Width of the panel in landscape orientation
+const PANEL_WIDTH: i32 = 296;
+
+/// Height of the panel in landscape orientation
+const PANEL_HEIGHT: i32 = 128;
+
+/// Each pixel of the 128x64 layout is drawn as a square of this size on the panel
+const SCALE: i32 = 2;
+
+/// The 128x64 layout the `Settings` draw routines use
+const LAYOUT: Rectangle = Rectangle::new(Point::zero(), Size::new(128, 64));
+
+/// Top left corner of the scaled layout, centering it on the panel
+const LAYOUT_OFFSET: Point = Point::new((PANEL_WIDTH - 128 * SCALE) / 2, (PANEL_HEIGHT - 64 * SCALE) / 2);
+
+const _: () = assert!(
+    128 * SCALE <= PANEL_WIDTH && 64 * SCALE <= PANEL_HEIGHT,
+    "The scaled layout must fit the panel"
+);
+
+/// SPI device of the panel, the bus is not shared
+type EpaperSpi = ExclusiveDevice<Spi<'static, SPI1, Blocking>, Output<'static>, Delay>;
+
+/// Panel driver
+type Epaper = Epd2in9<EpaperSpi, Input<'static>, Output<'static>, Output<'static>, Delay>;
+
+/// Peripherals and pins the e-paper panel is connected to
+pub struct EpaperHardware {
+    /// SPI bus, transmit only
+    pub spi: Spi<'static, SPI1, Blocking>,
+    /// Chip select, active low
+    pub cs: Output<'static>,
+    /// Data/command select
+    pub dc: Output<'static>,
+    /// Reset, active low
+    pub rst: Output<'static>,
+    /// Busy line, high while the panel is refreshing
+    pub busy: Input<'static>,
+}
+
+/// Colors of black/white/red panels, which can draw the `Settings` screens with `ColorScheme::Accent` in red
+/// The fitted 2.9" V2 panel is black and white only and is drawn in `BinaryColor`
+impl Palette for TriColor {
+    const BACKGROUND: Self = Self::White;
+    const FOREGROUND: Self = Self::Black;
+    const ACCENT: Option<Self> = Some(Self::Chromatic);
+}
+
+/// Panel driver together with its SPI device and refresh bookkeeping
+struct Panel {
+    /// Panel driver
+    epd: Epaper,
+    /// SPI device of the panel
+    spi: EpaperSpi,
+    /// Delay for the driver's busy waits
+    delay: Delay,
+    /// Quick refreshes since the last full refresh
+    quick_refreshes: u8,
+}
+
+impl Panel {
+    /// Wakes the controller, shows the frame and puts the controller back to deep sleep
+    /// Refreshes fully every `QUICK_REFRESHES_PER_FULL` quick refreshes, or when `force_full` is set
+    fn refresh(&mut self, frame: &Display2in9, force_full: bool) -> bool {
+        let full = force_full || self.quick_refreshes >= QUICK_REFRESHES_PER_FULL;
+        let lut = if full { RefreshLut::Full } else { RefreshLut::Quick };
+
+        let result = self
+            .epd
+            .wake_up(&mut self.spi, &mut self.delay)
+            .and_then(|()| self.epd.set_lut(&mut self.spi, &mut self.delay, Some(lut)))
+            .and_then(|()| {
+                self.epd
+                    .update_and_display_frame(&mut self.spi, frame.buffer(), &mut self.delay)
+            })
+            .and_then(|()| self.epd.sleep(&mut self.spi, &mut self.delay));
+
+        match result {
+            Ok(()) => {
+                self.quick_refreshes = if full { 0 } else { self.quick_refreshes + 1 };
+                true
+            }
+            Err(e) => {
+                error!("Failed to refresh e-paper display (continuing): {}", Debug2Format(&e));
+                false
+            }
+        }
+    }
+}
+
+/// Draw target wrapper scaling the 128x64 layout up by `SCALE` and centering it on the panel
+struct Scaled<'a, D> {
+    /// The wrapped draw target
+    target: &'a mut D,
+}
+
+impl<'a, D> Scaled<'a, D> {
+    /// Wraps a draw target
+    const fn new(target: &'a mut D) -> Self {
+        Self { target }
+    }
+}
+
+impl<D> Dimensions for Scaled<'_, D> {
+    fn bounding_box(&self) -> Rectangle {
+        LAYOUT
+    }
+}
+
+impl<D> DrawTarget for Scaled<'_, D>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    type Color = BinaryColor;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.target.draw_iter(
+            pixels
+                .into_iter()
+                .filter(|Pixel(point, _)| LAYOUT.contains(*point))
+                .flat_map(|Pixel(point, color)| {
+                    let origin = LAYOUT_OFFSET + point * SCALE;
+                    (0..SCALE * SCALE).map(move |i| Pixel(origin + Point::new(i % SCALE, i / SCALE), color))
+                }),
+        )
+    }
+}
+
+/// Display task for the e-paper panel
+#[embassy_executor::task]
+pub async fn display_task(hardware: EpaperHardware) {
+    let EpaperHardware { spi, cs, dc, rst, busy } = hardware;
+    let mut delay = Delay;
+    let Ok(mut spi_device) = ExclusiveDevice::new(spi, cs, Delay);
+
+    // Perform critical hardware initialization
+    let epd = match Epd2in9::new(&mut spi_device, busy, dc, rst, &mut delay, None) {
+        Ok(epd) => epd,
+        Err(e) => {
+            error!("Failed to initialize e-paper display: {}", Debug2Format(&e));
+            return;
+        }
+    };
+    let mut panel = Panel {
+        epd,
+        spi: spi_device,
+        delay,
+        quick_refreshes: 0,
+    };
+
+    let mut frame = Display2in9::default();
+    frame.set_rotation(DisplayRotation::Rotate90);
+    frame.clear(Color::White).unwrap_or_default();
+
+    // Create settings for the display
+    let Some(settings) = initialize_display_settings() else {
+        return;
+    };
+
+    // Tell briefly why the system was reset before the startup screen
+    if let Some(reason) = boot_reason_to_show() {
+        settings.draw_boot_reason(&mut Scaled::new(&mut frame.color_converted()), reason);
+        if !panel.refresh(&frame, true) {
+            return;
+        }
+        Timer::after(BOOT_REASON_TIME).await;
+        frame.clear(Color::White).unwrap_or_default();
+    }
+
+    // Show initial startup screen
+    show_initial_screen(&mut Scaled::new(&mut frame.color_converted()), &settings).await;
+    if !panel.refresh(&frame, true) {
+        return;
+    }
+
+    let task_id = TaskId::Display;
+    report_task_success(task_id).await;
+    info!("E-paper display initialized");
+
+    // When the drawn frame is refreshed, `None` while no refresh is pending
+    let mut refresh_at: Option<Instant> = None;
+    let mut coalesced = 0;
+    // Whether the pending refresh is a full one, to clear the ghosting along with a forced redraw
+    let mut full_refresh = false;
+
+    // Main display loop - all errors here are considered transient
+    loop {
+        // The panel never sleeps on a timeout, it keeps its image without power anyway
+        let command = next_display_command(None, None, refresh_at).await;
+
+        if command == DisplayCommand::Flush {
+            refresh_at = None;
+            #[cfg(feature = "debug-serial")]
+            let iteration_start = Instant::now();
+
+            if panel.refresh(&frame, full_refresh) {
+                report_task_success(task_id).await;
+            } else {
+                report_task_failure(task_id).await;
+            }
+            record_redraw(coalesced).await;
+            coalesced = 0;
+            full_refresh = false;
+            #[cfg(feature = "debug-serial")]
+            record_loop_time(task_id, iteration_start).await;
+            continue;
+        }
+
+        if command == DisplayCommand::StorageMode {
+            // The notice stays visible on the unpowered panel while the device is stored
+            frame.clear(Color::White).unwrap_or_default();
+            settings.draw_storage_mode_message(&mut Scaled::new(&mut frame.color_converted()));
+            panel.refresh(&frame, true);
+            storage_mode::park("Display").await;
+        }
+
+        if SYSTEM_STATE.lock().await.is_display_frozen() && !shown_while_frozen(command) {
+            // The screen is kept as it is, unfreezing redraws everything
+            report_task_success(task_id).await;
+            continue;
+        }
+
+        #[cfg(feature = "debug-serial")]
+        let iteration_start = Instant::now();
+
+        // Night mode needs no dithering, the panel does not emit light
+        handle_display_command(command, &mut Scaled::new(&mut frame.color_converted()), &settings).await;
+
+        if !matches!(
+            command,
+            DisplayCommand::SensorData { .. }
+                | DisplayCommand::SensorErrorChanged
+                | DisplayCommand::BatteryWarningChanged
+                | DisplayCommand::Unfrozen
+                | DisplayCommand::HistoryCleared
+                | DisplayCommand::ForceRedraw
+        ) {
+            // Shown with the next refresh
+            report_task_success(task_id).await;
+            continue;
+        }
+
+        full_refresh |= command == DisplayCommand::ForceRedraw;
+
+        // Refreshed when the coalescing window closes, commands until then are shown with the same refresh
+        if refresh_at.is_some() {
+            coalesced += 1;
+        } else {
+            refresh_at = Some(Instant::now() + REDRAW_COALESCE_WINDOW);
+        }
+        report_task_success(task_id).await;
+        #[cfg(feature = "debug-serial")]
+        record_loop_time(task_id, iteration_start).await;
+    }
+}
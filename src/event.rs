@@ -1,21 +1,72 @@
-//! Events and system channel for sending and receiving events
+//! Events and system channels for sending and receiving events
+//!
+//! Control events (battery, charging, display mode) and bulk sensor data travel on separate channels, so a backlog
+//! of sensor data can never hold up control events and control events are always handled first.
 
-use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use defmt::{error, info};
+use embassy_futures::select::{Either, select};
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    channel::{Channel, TrySendError},
+};
+use embassy_time::{Duration, Instant};
 use ens160_aq::data::AirQualityIndex;
 
-/// System event channel for sending and receiving events
-pub static EVENT_CHANNEL: Channel<CriticalSectionRawMutex, Event, EVENT_CHANNEL_CAPACITY> = Channel::new();
-/// The capacity of the event channel
-const EVENT_CHANNEL_CAPACITY: usize = 10;
+use crate::system_state::{ExtremesScope, SensorFault, VocKind};
 
-/// Sends an event to the system channel
+/// Channel for control events (battery, charging, display mode, night mode)
+static CONTROL_EVENT_CHANNEL: Channel<CriticalSectionRawMutex, Event, CONTROL_EVENT_CHANNEL_CAPACITY> = Channel::new();
+/// The capacity of the control event channel
+const CONTROL_EVENT_CHANNEL_CAPACITY: usize = 10;
+
+/// Channel for sensor data events
+static SENSOR_EVENT_CHANNEL: Channel<CriticalSectionRawMutex, Event, SENSOR_EVENT_CHANNEL_CAPACITY> = Channel::new();
+/// The capacity of the sensor data event channel
+const SENSOR_EVENT_CHANNEL_CAPACITY: usize = 4;
+
+/// How long a sender may be blocked on a full channel before it is logged as an error
+const BACKLOG_ERROR_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// Sends an event to the matching system channel
 pub async fn send_event(event: Event) {
-    EVENT_CHANNEL.sender().send(event).await;
+    #[cfg(feature = "trace-events")]
+    crate::usb_serial::trace_event(&event);
+
+    match event {
+        Event::SensorData { .. } | Event::ClimateData { .. } => {
+            send_to_channel(&SENSOR_EVENT_CHANNEL, "Sensor", event).await;
+        }
+        _ => send_to_channel(&CONTROL_EVENT_CHANNEL, "Control", event).await,
+    }
 }
 
-/// Receives the next event from the system channel
+/// Sends an event to a channel, logging when the channel is full and the sender has to wait for the orchestrator
+async fn send_to_channel<const N: usize>(
+    channel: &Channel<CriticalSectionRawMutex, Event, N>,
+    name: &str,
+    event: Event,
+) {
+    if let Err(TrySendError::Full(event)) = channel.try_send(event) {
+        info!("{} event channel full, waiting for orchestrator", name);
+        let start = Instant::now();
+        channel.send(event).await;
+        let waited = start.elapsed();
+        if waited >= BACKLOG_ERROR_THRESHOLD {
+            error!("{} event channel was blocked for {} ms", name, waited.as_millis());
+        }
+    }
+}
+
+/// Receives the next event, control events take precedence over sensor data
 pub async fn receive_event() -> Event {
-    EVENT_CHANNEL.receiver().receive().await
+    match select(CONTROL_EVENT_CHANNEL.receive(), SENSOR_EVENT_CHANNEL.receive()).await {
+        Either::First(event) | Either::Second(event) => event,
+    }
+}
+
+/// Whether more sensor data is waiting to be processed
+pub fn sensor_data_pending() -> bool {
+    !SENSOR_EVENT_CHANNEL.is_empty()
 }
 
 /// The event type used in the system, representing various system events
@@ -31,17 +82,65 @@ pub enum Event {
         humidity: f32,
         /// Raw humidity in percentage (uncalibrated)
         raw_humidity: f32,
+        /// Dew point in degrees Celsius, from the display temperature and the calibrated humidity
+        dew_point: f32,
         /// CO2 level in ppm
         co2: u16,
-        /// TVOC level in ppb
-        etoh: u16,
+        /// Whether the raw CO2 level was implausibly low (below the atmospheric baseline)
+        co2_suspect: bool,
+        /// Ethanol or TVOC level in ppb, depending on `voc_kind`
+        voc: u16,
+        /// Which figure `voc` holds
+        voc_kind: VocKind,
         /// Air quality index data
         air_quality: AirQualityIndex,
+        /// Whether the ENS160 is still settling after a large compensation change
+        settling: bool,
+    },
+    /// Temperature and humidity readings while the ENS160 sleeps for reduced sensing on a low battery
+    ClimateData {
+        /// Temperature in degrees Celsius (display value with offset)
+        temperature: f32,
+        /// Raw temperature in degrees Celsius (without offset)
+        raw_temperature: f32,
+        /// Humidity in percentage (calibrated)
+        humidity: f32,
+        /// Raw humidity in percentage (uncalibrated)
+        raw_humidity: f32,
+        /// Dew point in degrees Celsius, from the display temperature and the calibrated humidity
+        dew_point: f32,
     },
-    /// Battery charging state event (true = charging, false = not charging)
+    /// External power was detected, sent by the VSYS task on the change to charging
     BatteryCharging,
-    /// Battery level event (0-100 percentage)
+    /// Battery level event (0-100 percentage), sent by the VSYS task while on battery, so it also ends charging
     BatteryLevel(u8),
     /// Display mode toggle request
     ToggleDisplayMode,
+    /// Switch night mode on (true) or off (false)
+    SetNightMode(bool),
+    /// Sensors failed to initialize and the sensor task entered standby (with the fault), or they recovered (`None`)
+    SensorError(Option<SensorFault>),
+    /// Freeze the display on the current screen (true) or unfreeze it (false)
+    SetDisplayFreeze(bool),
+    /// Clear the CO2 history and the datalog, requested through the serial console
+    ClearHistory,
+    /// Clear the display and repaint the current screen completely, after a glitch or a settings change affecting it
+    ForceRedraw,
+    /// Seconds left of the ENS160 warmup at startup, sent every second until the first reading
+    WarmupProgress(u16),
+    /// The humidity calibrator detected a rapid environmental change, e.g. a window was opened
+    EnvironmentChanged,
+    /// Switch the displayed temperature unit between Celsius and Fahrenheit, e.g. from a button press
+    ToggleTemperatureUnit,
+    /// Shut down into storage mode, requested through the serial console
+    EnterStorageMode,
+    /// Reset the minimum and maximum readings of the given scope, requested through the serial console
+    ResetExtremes(ExtremesScope),
+    /// Set the read interval in seconds at runtime, 0 follows the power profile again
+    SetReadInterval(u32),
+    /// The battery dropped below `LOW_BATTERY_WARNING_PERCENT` while not charging, sent once until it is charged or
+    /// rises above `LOW_BATTERY_WARNING_CLEAR_PERCENT`
+    BatteryLow,
+    /// The sensor task switched to reduced sensing on a low battery (true) or back to full sensing (false)
+    ReducedSensing(bool),
 }
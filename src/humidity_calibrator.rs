@@ -1,8 +1,10 @@
 //! Humidity calibration module for adaptive baseline and statistical drift correction.
 
-use defmt::info;
+use defmt::{Format, info};
 use heapless::Vec;
 
+use crate::verbosity::should_log_verbose;
+
 /// Number of initial readings to treat as baseline truth
 const INITIAL_BASELINE_READINGS: usize = 5;
 
@@ -36,6 +38,70 @@ const LONG_TERM_DRIFT_THRESHOLD: f32 = 10.0; // 10% deviation from expected
 /// Very conservative long-term drift learning rate
 const LONG_TERM_DRIFT_LEARNING_RATE: f32 = 0.005; // Even slower than regular drift correction
 
+/// Climate the device is used in, selects the indoor humidity model of the long-term statistical correction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum HumidityClimate {
+    /// Dry climates, indoor air is drier than in temperate regions
+    Arid,
+    /// Temperate climates
+    Temperate,
+    /// Humid climates, indoor air is more humid than in temperate regions
+    Humid,
+}
+
+impl HumidityClimate {
+    /// Indoor humidity model as (humidity at 25°C, lowest and highest expected humidity) in percent
+    /// The range includes 5% seasonal variation from HVAC differences
+    const fn indoor_model(self) -> (f32, f32, f32) {
+        match self {
+            Self::Arid => (35.0, 15.0, 55.0),
+            Self::Temperate => (45.0, 25.0, 65.0),
+            Self::Humid => (55.0, 35.0, 75.0),
+        }
+    }
+
+    /// Name of the climate as used on the serial console
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Arid => "arid",
+            Self::Temperate => "temperate",
+            Self::Humid => "humid",
+        }
+    }
+
+    /// Parses a climate name as used on the serial console
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Arid, Self::Temperate, Self::Humid]
+            .into_iter()
+            .find(|climate| climate.name() == name)
+    }
+}
+
+/// User configuration of the humidity calibrator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HumidityCalibratorConfig {
+    /// Climate selecting the expected indoor humidity for the long-term statistical correction
+    pub climate: HumidityClimate,
+    /// Whether the long-term statistical correction towards the indoor humidity model is applied
+    /// Disable it where the model does not hold, e.g. a basement that really is at 75% RH
+    /// The short-term baseline drift correction is not affected
+    pub long_term_correction: bool,
+    /// Whether the adaptive calibration is applied at all, without it the raw humidity is passed on
+    /// Disable it to rely on the user humidity offset alone, which is applied on top either way
+    pub adaptive: bool,
+}
+
+impl HumidityCalibratorConfig {
+    /// Creates the default configuration
+    pub const fn new() -> Self {
+        Self {
+            climate: HumidityClimate::Temperate,
+            long_term_correction: true,
+            adaptive: true,
+        }
+    }
+}
+
 /// Recent humidity reading for change rate analysis
 #[derive(Clone, Copy)]
 struct RecentReading {
@@ -48,6 +114,8 @@ struct RecentReading {
 /// 1. Adaptive baseline: Handles rapid environmental changes by establishing and updating baselines
 /// 2. Statistical expectation: Provides long-term drift correction against theoretical indoor humidity ranges
 pub struct HumidityCalibrator {
+    /// User configuration
+    config: HumidityCalibratorConfig,
     /// Recent readings for change rate analysis
     recent_readings: Vec<RecentReading, CHANGE_HISTORY_SIZE>,
     /// Current drift correction offset
@@ -78,6 +146,7 @@ impl HumidityCalibrator {
     /// Create a new humidity calibrator
     pub const fn new() -> Self {
         Self {
+            config: HumidityCalibratorConfig::new(),
             recent_readings: Vec::new(),
             humidity_offset: 0.0,
             current_baseline: None,
@@ -93,20 +162,28 @@ impl HumidityCalibrator {
         }
     }
 
+    /// Sets the user configuration, takes effect with the next measurement
+    /// Disabling the long-term correction also drops the statistical offset learned so far
+    pub const fn set_config(&mut self, config: HumidityCalibratorConfig) {
+        if !config.long_term_correction {
+            self.long_term_statistical_offset = 0.0;
+        }
+        self.config = config;
+    }
+
     /// Expected indoor humidity based on temperature
-    /// Indoor environments typically maintain 30-60% RH, with seasonal variations
-    fn expected_indoor_humidity(temperature_c: f32) -> f32 {
+    /// Temperate indoor environments typically maintain 30-60% RH, with seasonal variations, the climate shifts this
+    fn expected_indoor_humidity(temperature_c: f32, climate: HumidityClimate) -> f32 {
         // Empirical model for indoor humidity based on temperature
         // Cooler indoor temps tend to have higher relative humidity
         // Warmer indoor temps tend to have lower relative humidity
-        let base_humidity = 45.0; // Base indoor humidity target
+        let (base_humidity, min_humidity, max_humidity) = climate.indoor_model();
         let temp_coefficient = -0.5; // RH decreases as temperature increases
-        let seasonal_variation = 5.0; // Account for seasonal HVAC differences
 
         let expected = base_humidity + (25.0 - temperature_c) * temp_coefficient;
 
         // Clamp to reasonable indoor range with seasonal variation
-        expected.clamp(30.0 - seasonal_variation, 60.0 + seasonal_variation)
+        expected.clamp(min_humidity, max_humidity)
     }
 
     /// Detect rapid humidity changes and baseline shifts
@@ -275,11 +352,12 @@ impl HumidityCalibrator {
 
         self.baseline_reading_count += 1;
 
-        if self.baseline_reading_count >= INITIAL_BASELINE_READINGS {
+        if self.baseline_reading_count >= INITIAL_BASELINE_READINGS
+            && let Some(baseline) = self.current_baseline
+        {
             info!(
                 "Humidity calibration: Baseline established at {}% from {} initial readings",
-                self.current_baseline.unwrap(),
-                INITIAL_BASELINE_READINGS
+                baseline, INITIAL_BASELINE_READINGS
             );
         }
 
@@ -301,7 +379,7 @@ impl HumidityCalibrator {
             return;
         }
 
-        let expected = Self::expected_indoor_humidity(temperature);
+        let expected = Self::expected_indoor_humidity(temperature, self.config.climate);
         let statistical_error = raw_humidity - expected;
 
         if statistical_error.abs() >= LONG_TERM_DRIFT_THRESHOLD {
@@ -340,7 +418,7 @@ impl HumidityCalibrator {
                 self.humidity_offset,
                 self.humidity_offset - old_offset
             );
-        } else {
+        } else if should_log_verbose() {
             info!(
                 "Humidity calibration: Reading {}% within drift threshold of baseline {}% (drift: {}% < {}%)",
                 raw_humidity, baseline, drift, MIN_DRIFT_THRESHOLD
@@ -353,15 +431,17 @@ impl HumidityCalibrator {
         // First, detect if this is a rapid change
         let (_, change_magnitude) = self.detect_rapid_change(raw_humidity);
 
-        info!(
-            "Humidity calibration: T={}°C, RH={}%, Rapid change={}, Change={}%, Stable readings={}, Baseline readings={}",
-            temperature,
-            raw_humidity,
-            self.in_rapid_change_period,
-            change_magnitude,
-            self.stable_reading_count,
-            self.baseline_reading_count
-        );
+        if should_log_verbose() {
+            info!(
+                "Humidity calibration: T={}°C, RH={}%, Rapid change={}, Change={}%, Stable readings={}, Baseline readings={}",
+                temperature,
+                raw_humidity,
+                self.in_rapid_change_period,
+                change_magnitude,
+                self.stable_reading_count,
+                self.baseline_reading_count
+            );
+        }
 
         // Handle rapid changes and check if we should continue processing
         if self.handle_rapid_change(raw_humidity) {
@@ -381,7 +461,9 @@ impl HumidityCalibrator {
             self.update_long_term_stability(drift);
 
             // Check for long-term statistical drift (independent of baseline drift)
-            self.apply_long_term_drift_correction(temperature, raw_humidity);
+            if self.config.long_term_correction {
+                self.apply_long_term_drift_correction(temperature, raw_humidity);
+            }
 
             // Apply short-term baseline drift correction
             self.apply_baseline_drift_correction(baseline, raw_humidity);
@@ -391,12 +473,24 @@ impl HumidityCalibrator {
     /// Apply calibration to a humidity reading
     /// Uses hybrid approach: adaptive baseline for rapid changes + statistical expectation for long-term drift
     pub fn calibrate_humidity(&self, _temperature: f32, raw_humidity: f32) -> f32 {
+        if !self.config.adaptive {
+            if should_log_verbose() {
+                info!(
+                    "Humidity calibration: Adaptive calibration disabled - returning raw value {}%",
+                    raw_humidity
+                );
+            }
+            return raw_humidity;
+        }
+
         // During initial baseline establishment, return raw values
         if self.baseline_reading_count < INITIAL_BASELINE_READINGS {
-            info!(
-                "Humidity calibration: Establishing baseline ({}/{}) - returning raw value {}%",
-                self.baseline_reading_count, INITIAL_BASELINE_READINGS, raw_humidity
-            );
+            if should_log_verbose() {
+                info!(
+                    "Humidity calibration: Establishing baseline ({}/{}) - returning raw value {}%",
+                    self.baseline_reading_count, INITIAL_BASELINE_READINGS, raw_humidity
+                );
+            }
             return raw_humidity;
         }
 
@@ -414,21 +508,28 @@ impl HumidityCalibrator {
             "HYBRID_DRIFT_CORRECTION"
         };
 
-        info!(
-            "Humidity calibration: {} - Applied baseline offset {} + statistical offset {} to {}% -> {}%{}",
-            status,
-            self.humidity_offset,
-            self.long_term_statistical_offset,
-            raw_humidity,
-            final_value,
-            if was_clamped { " (clamped)" } else { "" }
-        );
+        if should_log_verbose() {
+            info!(
+                "Humidity calibration: {} - Applied baseline offset {} + statistical offset {} to {}% -> {}%{}",
+                status,
+                self.humidity_offset,
+                self.long_term_statistical_offset,
+                raw_humidity,
+                final_value,
+                if was_clamped { " (clamped)" } else { "" }
+            );
+        }
 
         final_value
     }
 
+    /// Whether a rapid environmental change is in progress, e.g. a window was opened
+    pub const fn in_rapid_change(&self) -> bool {
+        self.in_rapid_change_period
+    }
+
     /// Get calibration status information
-    pub const fn get_calibration_info(&self) -> (bool, f32, f32, usize, bool, usize) {
+    pub const fn get_calibration_info(&self) -> (bool, f32, f32, usize, bool, usize, bool) {
         let is_calibrated = self.baseline_reading_count >= INITIAL_BASELINE_READINGS;
         (
             is_calibrated,
@@ -437,6 +538,43 @@ impl HumidityCalibrator {
             self.baseline_reading_count,
             self.in_rapid_change_period,
             self.long_term_stable_count,
+            self.config.long_term_correction,
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expected_indoor_humidity_follows_the_climate() {
+        for (climate, at_15, at_25, at_35) in [
+            (HumidityClimate::Arid, 30.0, 35.0, 40.0),
+            (HumidityClimate::Temperate, 40.0, 45.0, 50.0),
+            (HumidityClimate::Humid, 50.0, 55.0, 60.0),
+        ] {
+            for (temperature_c, expected) in [(15.0, at_15), (25.0, at_25), (35.0, at_35)] {
+                let humidity = HumidityCalibrator::expected_indoor_humidity(temperature_c, climate);
+                assert!(
+                    (humidity - expected).abs() < 0.001,
+                    "{humidity}% at {temperature_c}°C in the {} climate, expected {expected}%",
+                    climate.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn expected_indoor_humidity_stays_within_the_climate_range() {
+        for climate in [
+            HumidityClimate::Arid,
+            HumidityClimate::Temperate,
+            HumidityClimate::Humid,
+        ] {
+            let (_, min_humidity, max_humidity) = climate.indoor_model();
+            assert!((HumidityCalibrator::expected_indoor_humidity(-40.0, climate) - min_humidity).abs() < 0.001);
+            assert!((HumidityCalibrator::expected_indoor_humidity(85.0, climate) - max_humidity).abs() < 0.001);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! Clock of the shared I2C bus, stepped down when the bus keeps failing
+//!
+//! Long or marginal wiring can corrupt transfers at the configured clock. When `ERROR_THRESHOLD` I2C errors pile up
+//! within `ERROR_WINDOW` the clock is halved, down to `MIN_FREQUENCY`. The peripheral is reconfigured in place while
+//! holding the bus mutex, so the devices on the shared bus keep working without being recreated. The clock never
+//! goes back up until the next reboot.
+
+use defmt::{Debug2Format, error, info};
+#[cfg(target_os = "none")]
+use embassy_embedded_hal::SetConfig;
+#[cfg(target_os = "none")]
+use embassy_rp::i2c::Config as I2cConfig;
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex, signal::Signal};
+use embassy_time::{Duration, Instant};
+
+#[cfg(target_os = "none")]
+use crate::I2cBus;
+
+/// Configured I2C clock in Hz, the bus runs at 100 kHz (standard mode)
+/// The devices would support 400 kHz, but the hand-wired bus gets more margin at 100 kHz, which is still fast enough
+/// for the few bytes per reading and the display updates
+pub const FREQUENCY: u32 = 100_000;
+
+/// Lowest I2C clock in Hz the bus is stepped down to
+const MIN_FREQUENCY: u32 = 25_000;
+
+/// Number of I2C errors within `ERROR_WINDOW` that lower the clock
+const ERROR_THRESHOLD: u8 = 5;
+
+/// Window in which I2C errors are counted towards `ERROR_THRESHOLD`
+const ERROR_WINDOW: Duration = Duration::from_secs(600);
+
+const _: () = assert!(MIN_FREQUENCY <= FREQUENCY, "MIN_FREQUENCY must not exceed FREQUENCY");
+
+/// Bus clock and error window bookkeeping
+static BUS_SPEED: Mutex<CriticalSectionRawMutex, BusSpeed> = Mutex::new(BusSpeed::new());
+
+/// Signal requesting the I2C clock to be lowered
+static DOWNGRADE_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Current bus clock and the errors counted towards the next downgrade
+struct BusSpeed {
+    /// Current I2C clock in Hz
+    frequency: u32,
+    /// When the current error window started, `None` until the first error
+    window_start: Option<Instant>,
+    /// Errors counted in the current window
+    errors_in_window: u8,
+}
+
+impl BusSpeed {
+    /// Creates the bookkeeping for the configured clock without any errors
+    const fn new() -> Self {
+        Self {
+            frequency: FREQUENCY,
+            window_start: None,
+            errors_in_window: 0,
+        }
+    }
+
+    /// Counts an error, returns whether the threshold was reached and the clock can still be lowered
+    fn count_error(&mut self) -> bool {
+        let now = Instant::now();
+        if self.window_start.is_none_or(|start| now - start > ERROR_WINDOW) {
+            self.window_start = Some(now);
+            self.errors_in_window = 0;
+        }
+        self.errors_in_window = self.errors_in_window.saturating_add(1);
+
+        if self.errors_in_window < ERROR_THRESHOLD || self.frequency <= MIN_FREQUENCY {
+            return false;
+        }
+        self.window_start = None;
+        true
+    }
+}
+
+/// Counts a failed I2C transaction, requesting a lower clock when errors pile up
+pub async fn record_bus_error() {
+    if BUS_SPEED.lock().await.count_error() {
+        DOWNGRADE_SIGNAL.signal(());
+    }
+}
+
+/// Gets the current I2C clock in Hz
+pub async fn frequency() -> u32 {
+    BUS_SPEED.lock().await.frequency
+}
+
+/// I2C speed task, lowers the bus clock when requested
+#[cfg(target_os = "none")]
+#[embassy_executor::task]
+pub async fn i2c_speed_task(i2c_bus: &'static I2cBus) {
+    loop {
+        DOWNGRADE_SIGNAL.wait().await;
+
+        let mut bus_speed = BUS_SPEED.lock().await;
+        let frequency = (bus_speed.frequency / 2).max(MIN_FREQUENCY);
+        let mut config = I2cConfig::default();
+        config.frequency = frequency;
+
+        // Holding the bus lock, no transfer can be in flight while the peripheral is reconfigured
+        match i2c_bus.lock().await.set_config(&config) {
+            Ok(()) => {
+                info!(
+                    "Repeated I2C errors, bus clock lowered from {} to {} kHz",
+                    bus_speed.frequency / 1000,
+                    frequency / 1000
+                );
+                bus_speed.frequency = frequency;
+            }
+            Err(e) => error!("Failed to lower I2C bus clock: {}", Debug2Format(&e)),
+        }
+    }
+}
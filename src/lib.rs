@@ -0,0 +1,104 @@
+//! RP2350 based air quality monitor firmware library
+//!
+//! Holds the modules of the firmware, the binary in `main.rs` sets up the peripherals and spawns the tasks. The
+//! hardware code only builds for the RP2350 (`target_os = "none"`), the remaining logic also builds on the host with
+//! the `test-host` feature, so its unit tests run there with `cargo test --features test-host --target <host triple>`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+// The library only shares the modules with the binary and the host tests, it is no public API
+#![allow(
+    clippy::must_use_candidate,
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc,
+    clippy::new_without_default,
+    clippy::return_self_not_must_use
+)]
+// `mul_add` is only available with std, the firmware is built without
+#![cfg_attr(feature = "std", allow(clippy::suboptimal_flops))]
+// On the host the hardware code is left out, and with it the only users of some of the logic and imports
+#![cfg_attr(not(target_os = "none"), allow(dead_code, unused_imports))]
+
+#[cfg(target_os = "none")]
+use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice;
+#[cfg(target_os = "none")]
+use embassy_rp::{
+    i2c::{Async, I2c},
+    peripherals::I2C0,
+};
+#[cfg(all(target_os = "none", feature = "display-core1"))]
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+#[cfg(all(target_os = "none", not(feature = "display-core1")))]
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+#[cfg(target_os = "none")]
+use embassy_sync::mutex::Mutex;
+
+/// Firmware version string
+pub const FIRMWARE_VERSION: &str = concat!("v", env!("CARGO_PKG_VERSION"));
+
+pub mod datalog;
+#[cfg(feature = "demo")]
+pub mod demo;
+pub mod diagnostics;
+pub mod display;
+pub mod event;
+pub mod humidity_calibrator;
+pub mod i2c_speed;
+pub mod orchestrate;
+pub mod power_profile;
+#[cfg(feature = "rtc")]
+pub mod rtc;
+pub mod sensor;
+pub mod settings_store;
+pub mod storage_mode;
+pub mod system_state;
+pub mod usb_serial;
+pub mod verbosity;
+pub mod vsys;
+pub mod watchdog;
+
+/// Raw mutex guarding the shared I2C bus, only accessed from core 0
+#[cfg(all(target_os = "none", not(feature = "display-core1")))]
+pub type I2cBusRawMutex = NoopRawMutex;
+
+/// Raw mutex guarding the shared I2C bus
+/// With the display task on core 1 the bus is accessed from both cores, so it needs the multicore-safe
+/// critical section (a hardware spinlock with `critical-section-impl`)
+#[cfg(all(target_os = "none", feature = "display-core1"))]
+pub type I2cBusRawMutex = CriticalSectionRawMutex;
+
+/// The shared I2C bus
+#[cfg(target_os = "none")]
+pub type I2cBus = Mutex<I2cBusRawMutex, I2c<'static, I2C0, Async>>;
+
+/// A device on the shared I2C bus
+#[cfg(target_os = "none")]
+pub type I2cBusDevice = I2cDevice<'static, I2cBusRawMutex, I2c<'static, I2C0, Async>>;
+
+/// Serializes the host tests that share the global state, e.g. the system state, the settings store or the mock clock
+#[cfg(test)]
+pub(crate) static HOST_TEST_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Logger for the host tests, the defmt output of the code under test is dropped
+#[cfg(test)]
+#[defmt::global_logger]
+struct HostLogger;
+
+#[cfg(test)]
+// SAFETY: nothing is written, so there is no state to guard against concurrent use
+unsafe impl defmt::Logger for HostLogger {
+    fn acquire() {}
+
+    unsafe fn flush() {}
+
+    unsafe fn release() {}
+
+    unsafe fn write(_bytes: &[u8]) {}
+}
+
+/// Turns a defmt panic in the host tests into a regular test failure
+#[cfg(test)]
+#[defmt::panic_handler]
+#[allow(clippy::panic)]
+fn host_panic() -> ! {
+    panic!("defmt panic")
+}
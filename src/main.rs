@@ -5,34 +5,58 @@
 #![no_std]
 #![no_main]
 
-/// Firmware version string
-pub const FIRMWARE_VERSION: &str = concat!("v", env!("CARGO_PKG_VERSION"));
-
+#[cfg(feature = "demo")]
+use air_quality_monitor::demo;
+#[cfg(feature = "rtc")]
+use air_quality_monitor::rtc;
+#[cfg(not(feature = "demo"))]
+use air_quality_monitor::sensor;
+use air_quality_monitor::{
+    I2cBus, display, i2c_speed, orchestrate, settings_store,
+    system_state::{STARTUP_DISPLAY_MODE, SYSTEM_STATE, StartupDisplayMode},
+    usb_serial, vsys, watchdog,
+};
+use defmt::info;
 use defmt_rtt as _;
 use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice;
+#[cfg(feature = "display-core1")]
+use embassy_executor::Executor;
 use embassy_executor::Spawner;
+#[cfg(any(not(feature = "demo"), feature = "epaper"))]
+use embassy_rp::gpio::{Input, Pull};
+#[cfg(feature = "display-core1")]
+use embassy_rp::multicore::{Stack, spawn_core1};
 use embassy_rp::{
-    adc::InterruptHandler as AdcInterruptHandler,
+    adc::{Adc, Config as AdcConfig, InterruptHandler as AdcInterruptHandler},
     bind_interrupts,
     block::ImageDef,
     clocks::{ClockConfig, CoreVoltage},
     config::Config,
-    gpio::{Input, Pull},
-    i2c::{Async, Config as I2cConfig, I2c, InterruptHandler},
-    peripherals::I2C0,
+    i2c::{Config as I2cConfig, I2c, InterruptHandler},
+    pac,
+    peripherals::{I2C0, USB},
+    usb::{Driver as UsbDriver, InterruptHandler as UsbInterruptHandler},
+};
+#[cfg(feature = "epaper")]
+use embassy_rp::{
+    gpio::{Level, Output},
+    spi::{Config as SpiConfig, Spi},
 };
-use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
+use embassy_sync::mutex::Mutex;
+use embassy_time::Timer;
 use panic_probe as _;
 use static_cell::StaticCell;
 
-mod display;
-mod event;
-mod humidity_calibrator;
-mod orchestrate;
-mod sensor;
-mod system_state;
-mod vsys;
-mod watchdog;
+/// Fixed delay in milliseconds before the peripherals are set up and the tasks spawned, 0 starts right away
+/// Staggers the startup of several monitors powered up together, so they do not all hit their buses at once
+const BOOT_DELAY_MS: u64 = 0;
+
+/// Maximum random delay in milliseconds added to `BOOT_DELAY_MS`, drawn anew on every boot
+const BOOT_DELAY_RANDOM_MS: u64 = 0;
+
+/// Stack size for core 1 when it runs the display task
+#[cfg(feature = "display-core1")]
+const CORE1_STACK_SIZE: usize = 8192;
 
 // Firmware image type for bootloader
 #[unsafe(link_section = ".start_block")]
@@ -42,6 +66,7 @@ pub static IMAGE_DEF: ImageDef = ImageDef::secure_exe();
 bind_interrupts!(struct Irqs {
         I2C0_IRQ => InterruptHandler<I2C0>;
         ADC_IRQ_FIFO => AdcInterruptHandler;
+        USBCTRL_IRQ => UsbInterruptHandler<USB>;
     }
 );
 
@@ -52,30 +77,72 @@ async fn main(spawner: Spawner) {
     let mut config = Config::new(ClockConfig::system_freq(18_000_000).unwrap());
     config.clocks.core_voltage = CoreVoltage::V0_90;
     let p = embassy_rp::init(config);
+    watchdog::init_reset_reason();
+
+    // Stagger the startup, nothing is touched before the delay has passed
+    let boot_delay = boot_delay_ms();
+    if boot_delay > 0 {
+        info!("Delaying boot by {} ms", boot_delay);
+        Timer::after_millis(boot_delay).await;
+    }
+
+    // Load persisted data and seed the system state with it
+    let stored_state = settings_store::init(p.FLASH).await;
+    settings_store::record_boot().await;
+    {
+        let mut state = SYSTEM_STATE.lock().await;
+        state.restore_extremes(stored_state.extremes);
+        state.set_settings(stored_state.settings);
+        state.set_display_mode(match STARTUP_DISPLAY_MODE {
+            StartupDisplayMode::ResumeLast => stored_state.display_mode,
+            StartupDisplayMode::Fixed(mode) => mode,
+        });
+    }
 
     // I2C setup
     let sda = p.PIN_16;
     let scl = p.PIN_17;
     let i2c0 = p.I2C0;
-    let i2c = I2c::new_async(i2c0, scl, sda, Irqs, I2cConfig::default());
-    static I2C_BUS: StaticCell<Mutex<NoopRawMutex, I2c<'static, I2C0, Async>>> = StaticCell::new();
+    let mut i2c_config = I2cConfig::default();
+    i2c_config.frequency = i2c_speed::FREQUENCY;
+    let i2c = I2c::new_async(i2c0, scl, sda, Irqs, i2c_config);
+    static I2C_BUS: StaticCell<I2cBus> = StaticCell::new();
     let i2c_bus = I2C_BUS.init(Mutex::new(i2c));
 
-    // Initialize the I2C devices
-    let i2c_device_aht21 = I2cDevice::new(i2c_bus);
-    let i2c_device_ens160 = I2cDevice::new(i2c_bus);
-    let i2c_device_ssd1306 = I2cDevice::new(i2c_bus);
+    // Initialize the I2C devices, the sensor task creates its own so it can retry a failed initialization
+    #[cfg(not(feature = "epaper"))]
+    let display_hardware = I2cDevice::new(i2c_bus);
+    #[cfg(feature = "rtc")]
+    let i2c_device_rtc = I2cDevice::new(i2c_bus);
 
     // Initialize the interrupt pin for ENS160
+    #[cfg(not(feature = "demo"))]
     let ens160_int = Input::new(p.PIN_18, Pull::Up);
 
+    // E-paper display on its own SPI bus, wired like the Waveshare Pico e-Paper modules
+    #[cfg(feature = "epaper")]
+    let display_hardware = {
+        let mut spi_config = SpiConfig::default();
+        spi_config.frequency = display::EPAPER_SPI_FREQUENCY;
+        display::EpaperHardware {
+            spi: Spi::new_blocking_txonly(p.SPI1, p.PIN_10, p.PIN_11, spi_config),
+            cs: Output::new(p.PIN_9, Level::High),
+            dc: Output::new(p.PIN_8, Level::Low),
+            rst: Output::new(p.PIN_12, Level::High),
+            busy: Input::new(p.PIN_13, Pull::None),
+        }
+    };
+
     // And spawn the tasks
+    #[cfg(not(feature = "demo"))]
     #[allow(clippy::unwrap_used)]
-    spawner
-        .spawn(sensor::sensor_task(i2c_device_aht21, i2c_device_ens160, ens160_int))
-        .unwrap();
+    spawner.spawn(sensor::sensor_task(i2c_bus, ens160_int)).unwrap();
+    #[cfg(feature = "demo")]
+    #[allow(clippy::unwrap_used)]
+    spawner.spawn(demo::demo_task()).unwrap();
+    spawn_display_task(display_hardware, p.CORE1, spawner);
     #[allow(clippy::unwrap_used)]
-    spawner.spawn(display::display_task(i2c_device_ssd1306)).unwrap();
+    spawner.spawn(i2c_speed::i2c_speed_task(i2c_bus)).unwrap();
     #[allow(clippy::unwrap_used)]
     spawner.spawn(display::mode_switch_task()).unwrap();
     #[allow(clippy::unwrap_used)]
@@ -83,5 +150,65 @@ async fn main(spawner: Spawner) {
     #[allow(clippy::unwrap_used)]
     spawner.spawn(orchestrate::orchestrate_task()).unwrap();
     #[allow(clippy::unwrap_used)]
-    spawner.spawn(vsys::vsys_voltage_task(p.ADC, p.PIN_29)).unwrap();
+    spawner
+        .spawn(vsys::vsys_voltage_task(
+            Adc::new(p.ADC, Irqs, AdcConfig::default()),
+            p.PIN_29,
+        ))
+        .unwrap();
+    #[allow(clippy::unwrap_used)]
+    spawner.spawn(settings_store::persistence_task()).unwrap();
+    #[cfg(feature = "rtc")]
+    #[allow(clippy::unwrap_used)]
+    spawner.spawn(rtc::rtc_task(i2c_device_rtc)).unwrap();
+
+    // USB serial console
+    usb_serial::init(spawner, UsbDriver::new(p.USB, Irqs));
+}
+
+/// Delay in milliseconds before the peripherals are set up, `BOOT_DELAY_MS` plus a random part of up to
+/// `BOOT_DELAY_RANDOM_MS`
+fn boot_delay_ms() -> u64 {
+    if BOOT_DELAY_RANDOM_MS == 0 {
+        return BOOT_DELAY_MS;
+    }
+    // The random bit of the ring oscillator samples its jitter, so devices booting together draw different delays
+    let random = (0..32).fold(0u32, |random, _| {
+        (random << 1) | u32::from(pac::ROSC.randombit().read().randombit())
+    });
+    BOOT_DELAY_MS + u64::from(random) % (BOOT_DELAY_RANDOM_MS + 1)
+}
+
+/// Spawns the display task on core 0 alongside all other tasks
+#[cfg(not(feature = "display-core1"))]
+fn spawn_display_task(
+    display_hardware: display::DisplayHardware,
+    _core1: embassy_rp::Peri<'static, embassy_rp::peripherals::CORE1>,
+    spawner: Spawner,
+) {
+    #[allow(clippy::unwrap_used)]
+    spawner.spawn(display::display_task(display_hardware)).unwrap();
+}
+
+/// Spawns the display task on its own executor on core 1, so rendering and flushing do not hold up core 0
+///
+/// All state the display task touches is shared through `CriticalSectionRawMutex` based channels and mutexes
+/// (`DISPLAY_CHANNEL`, `SYSTEM_STATE`, `DIAGNOSTICS`, the watchdog health), and the I2C bus uses
+/// `I2cBusRawMutex`, so no further synchronization is needed across cores.
+#[cfg(feature = "display-core1")]
+fn spawn_display_task(
+    display_hardware: display::DisplayHardware,
+    core1: embassy_rp::Peri<'static, embassy_rp::peripherals::CORE1>,
+    _spawner: Spawner,
+) {
+    static CORE1_STACK: StaticCell<Stack<CORE1_STACK_SIZE>> = StaticCell::new();
+    static CORE1_EXECUTOR: StaticCell<Executor> = StaticCell::new();
+
+    spawn_core1(core1, CORE1_STACK.init(Stack::new()), move || {
+        let executor = CORE1_EXECUTOR.init(Executor::new());
+        executor.run(|spawner| {
+            #[allow(clippy::unwrap_used)]
+            spawner.spawn(display::display_task(display_hardware)).unwrap();
+        })
+    });
 }
@@ -1,87 +1,436 @@
 //! The main orchestrator task for the system
 
+use defmt::{error, info};
+use embassy_futures::select::{Either, select};
+use embassy_time::{Duration, Instant, Timer};
+
 use crate::{
-    display::{DisplayCommand, send_display_command},
-    event::{Event, receive_event},
-    system_state::{SYSTEM_STATE, SensorData},
+    datalog,
+    diagnostics::get_diagnostics,
+    display::{DISPLAY_FREEZE_TIMEOUT, DisplayChannel, DisplayCommand, DisplaySink, SHOW_BATTERY_PERCENTAGE},
+    event::{Event, receive_event, sensor_data_pending},
+    power_profile::MAX_READ_INTERVAL,
+    sensor::{MIN_READ_INTERVAL, request_storage_mode, update_battery_for_sensing},
+    settings_store, storage_mode,
+    system_state::{FocusMetric, ReadingQuality, SYSTEM_STATE, SensorData, SystemState},
+    usb_serial,
+    verbosity::should_log_verbose,
+    vsys::LOW_BATTERY_WARNING_CLEAR_PERCENT,
     watchdog::{TaskId, report_task_success},
 };
 
+/// Whether battery updates only redraw the display when the battery icon (or the percentage, if shown) changes
+/// The icon has only six levels, so most percentage changes would flush the display for nothing
+const REDRAW_BATTERY_ON_ICON_CHANGE_ONLY: bool = true;
+
+/// Minimum time the charging icon stays on after plugging in, so a brief plug-in does not just flicker it
+/// An unplug within this time is held back until it has passed, never longer
+const MIN_CHARGING_ICON_TIME: Duration = Duration::from_secs(5);
+
+/// Number of VSYS measurements an unplug is held back for while charging, so a single measurement dipping below the
+/// charging threshold does not flip the charging icon to the battery level and back. 0 shows an unplug right away
+/// Plugging in is never held back
+const UNPLUG_CONFIRM_MEASUREMENTS: u32 = 1;
+
+/// Extra time on top of the confirming VSYS measurements, for the measurement and the delivery of its event
+const UNPLUG_CONFIRM_MARGIN: Duration = Duration::from_secs(1);
+
+/// CO2 change in ppm from one reading to the next that puts the CO2 level in focus
+const FOCUS_CO2_CHANGE: u16 = 100;
+
+/// Temperature change in degrees Celsius from one reading to the next that puts the temperature in focus
+const FOCUS_TEMPERATURE_CHANGE: f32 = 1.0;
+
+/// Humidity change in percentage points from one reading to the next that puts the humidity in focus
+const FOCUS_HUMIDITY_CHANGE: f32 = 5.0;
+
+/// Number of readings a metric stays in focus after its last notable change
+const FOCUS_HOLD_READINGS: u8 = 3;
+
+/// Humidity change in percentage points below which the previous humidity is kept for display and history, e.g. 0.2
+/// A dead-band, not smoothing: a slow drift accumulates until it exceeds the band. 0 takes every change
+const HUMIDITY_DEAD_BAND: f32 = 0.0;
+
+/// Temperature change in degrees Celsius below which the previous temperature is kept, e.g. 0.1. 0 takes every change
+const TEMPERATURE_DEAD_BAND: f32 = 0.0;
+
+/// CO2 change in ppm below which the previous CO2 level is kept, e.g. 10. 0 takes every change
+const CO2_DEAD_BAND: u16 = 0;
+
+/// Metrics whose smoothing is reset when the humidity calibrator detects a rapid environmental change
+struct EnvironmentChangeReset {
+    /// The next humidity is taken as is, bypassing `HUMIDITY_DEAD_BAND`
+    humidity: bool,
+    /// The next temperature is taken as is, bypassing `TEMPERATURE_DEAD_BAND`
+    temperature: bool,
+    /// The next CO2 level is taken as is, bypassing `CO2_DEAD_BAND`, and the CO2 trend restarts from it
+    co2: bool,
+}
+
+/// Smoothing reset on a rapid environmental change, so the display follows e.g. an opened window right away instead
+/// of lagging through the dead-bands and the smoothed CO2 trend. Nothing is reset by default
+const ENVIRONMENT_CHANGE_RESET: EnvironmentChangeReset = EnvironmentChangeReset {
+    humidity: false,
+    temperature: false,
+    co2: false,
+};
+
+/// Battery events of an unplug held back until the charging icon has been shown long enough and the unplug is
+/// confirmed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct HeldUnplug {
+    /// When the unplug may be shown
+    release_at: Instant,
+    /// Latest battery level
+    level: u8,
+    /// Whether the battery ran low meanwhile, the warning would cover the charging icon
+    low: bool,
+}
+
+impl HeldUnplug {
+    /// The events to process once the unplug is shown, the battery level before its low battery warning
+    fn release(self) -> impl Iterator<Item = Event> {
+        [
+            Some(Event::BatteryLevel(self.level)),
+            self.low.then_some(Event::BatteryLow),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
 /// Main coordination task that implements the system's event loop
 #[embassy_executor::task]
 pub async fn orchestrate_task() {
+    let mut held: Option<HeldUnplug> = None;
+    let mut display = DisplayChannel;
+
     loop {
-        let event = receive_event().await;
-        process_event(event).await;
+        let event = match held {
+            Some(unplug) => match select(receive_event(), Timer::at(unplug.release_at)).await {
+                Either::First(event) => event,
+                Either::Second(()) => {
+                    held = None;
+                    for event in unplug.release() {
+                        process_event(event, &mut display).await;
+                    }
+                    continue;
+                }
+            },
+            None => receive_event().await,
+        };
+
+        let release_at = match event {
+            Event::BatteryLevel(_) if held.is_none() => charging_icon_release_at().await,
+            _ => None,
+        };
+        let (next_held, event) = hold_back_unplug(held, event, release_at);
+        if next_held.is_some() && held.is_none() {
+            info!("Unplug not confirmed yet, keeping the charging icon");
+        }
+        held = next_held;
+
+        if let Some(event) = event {
+            process_event(event, &mut display).await;
+        }
+    }
+}
+
+/// Holds back the battery events of an unplug while the charging icon is shown, returns what is held afterwards and
+/// the event to process right away, if any
+/// `release_at` is when the charging icon may be replaced by an unplug, for a battery level arriving while nothing is
+/// held. A further level while held only updates the level, the unplug is confirmed at the same time, and a low
+/// battery warning waits for the level. Plugging in again drops the unplug
+const fn hold_back_unplug(
+    held: Option<HeldUnplug>,
+    event: Event,
+    release_at: Option<Instant>,
+) -> (Option<HeldUnplug>, Option<Event>) {
+    match (event, held, release_at) {
+        (Event::BatteryLevel(level), Some(unplug), _) => (Some(HeldUnplug { level, ..unplug }), None),
+        (Event::BatteryLevel(level), None, Some(release_at)) => (
+            Some(HeldUnplug {
+                release_at,
+                level,
+                low: false,
+            }),
+            None,
+        ),
+        (Event::BatteryLow, Some(unplug), _) => (Some(HeldUnplug { low: true, ..unplug }), None),
+        (Event::BatteryCharging, _, _) => (None, Some(event)),
+        _ => (held, Some(event)),
+    }
+}
+
+/// When the charging icon may be replaced by an unplug, `None` if it is not shown or may be replaced right away
+async fn charging_icon_release_at() -> Option<Instant> {
+    let (charging_since, vsys_interval) = {
+        let state = SYSTEM_STATE.lock().await;
+        (
+            state.get_charging_since()?,
+            state.get_settings().power_profile.params().vsys_interval,
+        )
+    };
+    unplug_release_at(charging_since, vsys_interval, Instant::now())
+}
+
+/// When an unplug at `now` may replace the charging icon shown since `charging_since`, `None` if right away
+/// The icon stays for `MIN_CHARGING_ICON_TIME` after plugging in, and until `UNPLUG_CONFIRM_MEASUREMENTS` further VSYS
+/// measurements `vsys_interval` apart could have reported charging again
+fn unplug_release_at(charging_since: Instant, vsys_interval: Duration, now: Instant) -> Option<Instant> {
+    let confirmed_at = if UNPLUG_CONFIRM_MEASUREMENTS == 0 {
+        now
+    } else {
+        now + vsys_interval * UNPLUG_CONFIRM_MEASUREMENTS + UNPLUG_CONFIRM_MARGIN
+    };
+    let release_at = (charging_since + MIN_CHARGING_ICON_TIME).max(confirmed_at);
+    (now < release_at).then_some(release_at)
+}
+
+/// Puts the metric that changed most notably since the previous reading in focus, ageing the focus otherwise
+/// Changes are weighed against their thresholds, so a CO2 jump of twice `FOCUS_CO2_CHANGE` beats a temperature change
+/// just above `FOCUS_TEMPERATURE_CHANGE`
+fn update_focus(state: &mut SystemState, sensor_data: &SensorData) {
+    let Some(previous) = state.last_sensor_data.as_ref() else {
+        return;
+    };
+    let changes = [
+        (
+            FocusMetric::Co2,
+            f32::from(sensor_data.co2.abs_diff(previous.co2)) / f32::from(FOCUS_CO2_CHANGE),
+        ),
+        (
+            FocusMetric::Temperature,
+            (sensor_data.temperature - previous.temperature).abs() / FOCUS_TEMPERATURE_CHANGE,
+        ),
+        (
+            FocusMetric::Humidity,
+            (sensor_data.humidity - previous.humidity).abs() / FOCUS_HUMIDITY_CHANGE,
+        ),
+    ];
+
+    let most_notable = changes
+        .into_iter()
+        .filter(|(_, weight)| *weight >= 1.0)
+        .max_by(|(_, a), (_, b)| a.total_cmp(b));
+    let Some((metric, _)) = most_notable else {
+        state.age_focus();
+        return;
+    };
+    if metric != state.get_focus_metric() {
+        info!("Focus on {} after a notable change", metric);
     }
+    state.set_focus(metric, FOCUS_HOLD_READINGS);
 }
 
-/// Processes the received event and sends appropriate commands to other components
-async fn process_event(event: Event) {
+/// Returns `previous` if `value` is within `band` of it, `value` otherwise
+fn hold_within(value: f32, previous: f32, band: f32) -> f32 {
+    if (value - previous).abs() < band {
+        previous
+    } else {
+        value
+    }
+}
+
+/// Processes the received event and sends appropriate commands to other components, the display commands to `display`
+#[allow(clippy::too_many_lines)]
+async fn process_event(event: Event, display: &mut impl DisplaySink) {
+    // Tasks are parking or parked, nothing may wake the display or sensors until the reset
+    if storage_mode::is_active().await {
+        info!("Storage mode active, dropping event");
+        return;
+    }
+
     match event {
         Event::SensorData {
             temperature,
             raw_temperature,
             humidity,
             raw_humidity,
+            dew_point,
             co2,
-            etoh,
+            co2_suspect,
+            voc,
+            voc_kind,
             air_quality,
+            settling,
         } => {
+            // Keep the previous values for changes within the dead-bands, so the display does not flip back and forth
+            // between two close values. The raw values are shown as measured
+            // A rapid environmental change lifts the dead-bands of the metrics selected in `ENVIRONMENT_CHANGE_RESET`
+            let (temperature, humidity, co2) = {
+                let mut state = SYSTEM_STATE.lock().await;
+                let reset = state.take_environment_changed();
+                let temperature_band = if reset && ENVIRONMENT_CHANGE_RESET.temperature {
+                    0.0
+                } else {
+                    TEMPERATURE_DEAD_BAND
+                };
+                let humidity_band = if reset && ENVIRONMENT_CHANGE_RESET.humidity {
+                    0.0
+                } else {
+                    HUMIDITY_DEAD_BAND
+                };
+                let co2_band = if reset && ENVIRONMENT_CHANGE_RESET.co2 {
+                    0
+                } else {
+                    CO2_DEAD_BAND
+                };
+                state
+                    .last_sensor_data
+                    .as_ref()
+                    .map_or((temperature, humidity, co2), |previous| {
+                        (
+                            hold_within(temperature, previous.temperature, temperature_band),
+                            hold_within(humidity, previous.humidity, humidity_band),
+                            if co2.abs_diff(previous.co2) < co2_band {
+                                previous.co2
+                            } else {
+                                co2
+                            },
+                        )
+                    })
+            };
+
+            let ens160_valid = get_diagnostics().await.ens160_valid_for().is_some();
+            let quality = ReadingQuality::classify(co2_suspect, settling, ens160_valid);
+
             // Create sensor data structure
             let sensor_data = SensorData {
                 temperature,
                 raw_temperature,
                 humidity,
                 raw_humidity,
+                dew_point,
                 co2,
-                etoh,
+                co2_suspect,
+                voc,
+                voc_kind,
                 air_quality,
+                settling,
+                quality,
             };
 
-            // Update system state with new sensor data and CO2 history
+            // Update system state with new sensor data, CO2 and humidity history and quality window
             {
                 let mut state = SYSTEM_STATE.lock().await;
+                state.set_warmup_remaining(None);
+                state.add_reading_quality(quality);
+                if should_log_verbose() {
+                    info!(
+                        "Reading quality: {}, worst recent: {}",
+                        quality,
+                        state.get_worst_recent_quality()
+                    );
+                }
                 state.add_co2_measurement(co2);
+                state.add_humidity_measurement(humidity);
+                if state.update_co2_exceedances(co2) {
+                    info!(
+                        "CO2 exceedance {} of the session at {} ppm",
+                        state.get_co2_exceedances(),
+                        co2
+                    );
+                }
+                state.update_extremes(&sensor_data);
+                update_focus(&mut state, &sensor_data);
+                usb_serial::stream_reading(&sensor_data);
                 state.set_last_sensor_data(sensor_data);
             }
+            datalog::record(co2, temperature, humidity).await;
+
+            // Drawing superseded data is wasted bus time, catch up on the backlog first
+            if sensor_data_pending() {
+                info!("Newer sensor data queued, skipping display update");
+                report_task_success(TaskId::Orchestrator).await;
+                return;
+            }
+
+            // Evaluate the alarm on the reading that is actually drawn, so the flash follows it
+            let co2_alarm_raised = SYSTEM_STATE.lock().await.update_co2_alarm(co2);
 
             // Send display command
-            send_display_command(DisplayCommand::SensorData {
-                temperature,
-                raw_temperature,
-                humidity,
-                raw_humidity,
-                co2,
-                etoh,
-                air_quality,
-            })
-            .await;
+            display
+                .send(DisplayCommand::SensorData {
+                    temperature,
+                    raw_temperature,
+                    humidity,
+                    raw_humidity,
+                    dew_point,
+                    co2,
+                    co2_suspect,
+                    voc,
+                    voc_kind,
+                    air_quality,
+                    settling,
+                    quality,
+                })
+                .await;
+
+            if co2_alarm_raised {
+                info!("CO2 alarm raised at {} ppm", co2);
+                display.send(DisplayCommand::AlarmFlash).await;
+            }
         }
         Event::BatteryCharging => {
+            update_battery_for_sensing(None);
             // Update system state
-            {
+            let (icon_changed, warning_cleared) = {
                 let mut state = SYSTEM_STATE.lock().await;
+                let previous_level = state.get_battery_level();
+                let warning_cleared = state.is_low_battery_warning();
                 state.set_charging(true);
-            }
+                state.set_low_battery_warning(false);
+                (state.get_battery_level() != previous_level, warning_cleared)
+            };
 
-            send_display_command(DisplayCommand::UpdateBatteryCharging).await;
+            if icon_changed || !REDRAW_BATTERY_ON_ICON_CHANGE_ONLY {
+                display.send(DisplayCommand::UpdateBatteryCharging).await;
+            }
+            if warning_cleared {
+                info!("Low battery warning cleared by charging");
+                display.send(DisplayCommand::BatteryWarningChanged).await;
+            }
         }
         Event::BatteryLevel(level) => {
+            update_battery_for_sensing(Some(level));
             // Update system state
-            {
+            let (battery_changed, warning_cleared) = {
                 let mut state = SYSTEM_STATE.lock().await;
+                let previous_level = state.get_battery_level();
+                let previous_percent = state.get_battery_percent();
                 state.set_charging(false);
                 state.set_battery_percent(level);
-            }
+                // The warning clears with hysteresis, a level between the two thresholds keeps it
+                let warning_cleared = state.is_low_battery_warning() && level > LOW_BATTERY_WARNING_CLEAR_PERCENT;
+                if warning_cleared {
+                    state.set_low_battery_warning(false);
+                }
+                (
+                    state.get_battery_level() != previous_level
+                        || (SHOW_BATTERY_PERCENTAGE && level != previous_percent),
+                    warning_cleared,
+                )
+            };
 
-            send_display_command(DisplayCommand::UpdateBatteryPercentage(level)).await;
+            if battery_changed || !REDRAW_BATTERY_ON_ICON_CHANGE_ONLY {
+                display.send(DisplayCommand::UpdateBatteryPercentage(level)).await;
+            }
+            if warning_cleared {
+                info!("Low battery warning cleared at {}%", level);
+                display.send(DisplayCommand::BatteryWarningChanged).await;
+            }
+        }
+        Event::BatteryLow => {
+            SYSTEM_STATE.lock().await.set_low_battery_warning(true);
+            info!("Low battery warning raised");
+            display.send(DisplayCommand::BatteryWarningChanged).await;
         }
         Event::ToggleDisplayMode => {
             // Check if we have sensor data and toggle mode if we do
             let should_toggle_and_data = {
                 let mut state = SYSTEM_STATE.lock().await;
-                if state.last_sensor_data.is_some() {
+                if state.last_sensor_data.is_some() && !state.is_display_frozen() {
                     state.toggle_display_mode();
                     (true, state.last_sensor_data.clone())
                 } else {
@@ -90,9 +439,392 @@ async fn process_event(event: Event) {
             };
 
             if should_toggle_and_data.0 {
-                send_display_command(DisplayCommand::ToggleMode).await;
+                display.send(DisplayCommand::ToggleMode).await;
+            }
+        }
+        Event::SetNightMode(night_mode) => {
+            let changed = {
+                let mut state = SYSTEM_STATE.lock().await;
+                let changed = state.is_night_mode() != night_mode;
+                state.set_night_mode(night_mode);
+                changed
+            };
+
+            if changed {
+                display.send(DisplayCommand::NightModeChanged).await;
+            }
+        }
+        Event::SensorError(sensor_error) => {
+            {
+                let mut state = SYSTEM_STATE.lock().await;
+                state.set_sensor_error(sensor_error);
+            }
+
+            display.send(DisplayCommand::SensorErrorChanged).await;
+        }
+        Event::SetDisplayFreeze(frozen) => {
+            let changed = {
+                let mut state = SYSTEM_STATE.lock().await;
+                let changed = state.is_display_frozen() != frozen;
+                // Freezing again while frozen restarts the timeout
+                state.set_display_frozen_until(frozen.then(|| Instant::now() + DISPLAY_FREEZE_TIMEOUT));
+                changed
+            };
+
+            if changed {
+                info!("Display {}", if frozen { "frozen" } else { "unfrozen" });
+                if !frozen {
+                    // Catch up on everything that was not drawn while frozen
+                    display.send(DisplayCommand::Unfrozen).await;
+                }
+            }
+        }
+        Event::ClearHistory => {
+            // Cleared in one go under the lock, so a draw sees either the full or the empty history
+            {
+                let mut state = SYSTEM_STATE.lock().await;
+                state.clear_co2_history();
+                state.reset_session_stats();
             }
+            datalog::clear().await;
+            info!("CO2 and humidity history, session statistics and datalog cleared");
+            display.send(DisplayCommand::HistoryCleared).await;
+        }
+        Event::ForceRedraw => {
+            info!("Forcing a full display redraw");
+            display.send(DisplayCommand::ForceRedraw).await;
+        }
+        Event::WarmupProgress(remaining) => {
+            SYSTEM_STATE.lock().await.set_warmup_remaining(Some(remaining));
+            display.send(DisplayCommand::WarmupProgress(remaining)).await;
+        }
+        Event::EnvironmentChanged => {
+            let EnvironmentChangeReset {
+                humidity,
+                temperature,
+                co2,
+            } = ENVIRONMENT_CHANGE_RESET;
+            if humidity || temperature || co2 {
+                info!("Rapid environmental change, resetting the smoothing of the next reading");
+                let mut state = SYSTEM_STATE.lock().await;
+                state.mark_environment_changed();
+                if co2 {
+                    state.reset_co2_trend();
+                }
+            }
+        }
+        Event::ToggleTemperatureUnit => {
+            let unit = {
+                let mut state = SYSTEM_STATE.lock().await;
+                let unit = state.get_temperature_unit().toggled();
+                state.set_temperature_unit(unit);
+                unit
+            };
+            info!("Temperature unit switched to {}", unit.symbol());
+            // Every temperature on screen changes, so repaint it all instead of waiting for the next reading
+            display.send(DisplayCommand::ForceRedraw).await;
+            if let Err(e) = settings_store::update(|stored| stored.settings.temperature_unit = unit).await {
+                error!("Failed to store the temperature unit: {}", e);
+            }
+        }
+        Event::EnterStorageMode => {
+            info!("Entering storage mode");
+            storage_mode::enter().await;
+            request_storage_mode();
+            display.send(DisplayCommand::StorageMode).await;
+        }
+        Event::ClimateData {
+            temperature,
+            raw_temperature,
+            humidity,
+            raw_humidity,
+            dew_point,
+        } => {
+            // The CO2 and VOC values of the last full reading stay on screen, marked by the reduced sensing notice.
+            // They are not recorded again, so the CO2 history and the datalog pause
+            let sensor_data = {
+                let mut state = SYSTEM_STATE.lock().await;
+                let sensor_data = state.last_sensor_data.clone().map(|previous| SensorData {
+                    temperature,
+                    raw_temperature,
+                    humidity,
+                    raw_humidity,
+                    dew_point,
+                    ..previous
+                });
+                if let Some(ref sensor_data) = sensor_data {
+                    state.add_humidity_measurement(humidity);
+                    state.update_extremes(sensor_data);
+                    state.set_last_sensor_data(sensor_data.clone());
+                }
+                sensor_data
+            };
+
+            if let Some(sensor_data) = sensor_data {
+                display
+                    .send(DisplayCommand::SensorData {
+                        temperature: sensor_data.temperature,
+                        raw_temperature: sensor_data.raw_temperature,
+                        humidity: sensor_data.humidity,
+                        raw_humidity: sensor_data.raw_humidity,
+                        dew_point: sensor_data.dew_point,
+                        co2: sensor_data.co2,
+                        co2_suspect: sensor_data.co2_suspect,
+                        voc: sensor_data.voc,
+                        voc_kind: sensor_data.voc_kind,
+                        air_quality: sensor_data.air_quality,
+                        settling: sensor_data.settling,
+                        quality: sensor_data.quality,
+                    })
+                    .await;
+            }
+        }
+        Event::SetReadInterval(secs) => {
+            // Takes effect with the next wait of the sensor task, bounded so the watchdog countdown still holds
+            let read_interval = (secs > 0).then(|| u64::from(secs).clamp(MIN_READ_INTERVAL, MAX_READ_INTERVAL));
+            SYSTEM_STATE.lock().await.set_read_interval_override(read_interval);
+            if let Some(read_interval) = read_interval {
+                info!("Read interval set to {} seconds", read_interval);
+            } else {
+                info!("Read interval follows the power profile again");
+            }
+        }
+        Event::ReducedSensing(reduced) => {
+            SYSTEM_STATE.lock().await.set_reduced_sensing(reduced);
+            info!("Reduced sensing {}", if reduced { "started" } else { "ended" });
+            display.send(DisplayCommand::ForceRedraw).await;
+        }
+        Event::ResetExtremes(scope) => {
+            SYSTEM_STATE.lock().await.reset_extremes(scope);
+            info!("Extremes reset: {}", scope.name());
+            display.send(DisplayCommand::ForceRedraw).await;
         }
     }
     report_task_success(TaskId::Orchestrator).await;
 }
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+    use ens160_aq::data::AirQualityIndex;
+
+    use super::*;
+    use crate::{
+        HOST_TEST_LOCK,
+        system_state::{BatteryLevel, DisplayMode, VocKind},
+    };
+
+    /// Records the display commands instead of drawing them
+    #[derive(Default)]
+    struct Recorder(Vec<DisplayCommand>);
+
+    impl DisplaySink for Recorder {
+        async fn send(&mut self, command: DisplayCommand) {
+            self.0.push(command);
+        }
+    }
+
+    /// A sensor reading with the given CO2 level
+    const fn reading(co2: u16) -> Event {
+        Event::SensorData {
+            temperature: 21.0,
+            raw_temperature: 23.5,
+            humidity: 45.0,
+            raw_humidity: 41.0,
+            dew_point: 8.6,
+            co2,
+            co2_suspect: false,
+            voc: 50,
+            voc_kind: VocKind::Ethanol,
+            air_quality: AirQualityIndex::Good,
+            settling: false,
+        }
+    }
+
+    /// Processes the events on a fresh system state, returns the display commands after handing the resulting state to
+    /// `check`
+    fn run(events: &[Event], check: impl FnOnce(&SystemState)) -> Vec<DisplayCommand> {
+        let _guard = HOST_TEST_LOCK.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        block_on(async {
+            *SYSTEM_STATE.lock().await = SystemState::new();
+            let mut display = Recorder::default();
+            for &event in events {
+                process_event(event, &mut display).await;
+            }
+            check(&*SYSTEM_STATE.lock().await);
+            display.0
+        })
+    }
+
+    /// CO2 level of a sensor data command, `None` for other commands
+    const fn drawn_co2(command: &DisplayCommand) -> Option<u16> {
+        match command {
+            DisplayCommand::SensorData { co2, .. } => Some(*co2),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn readings_are_drawn_and_recorded() {
+        let commands = run(&[reading(600), reading(800), reading(1600)], |state| {
+            assert_eq!(state.get_co2_history(), [600, 800, 1600]);
+            assert_eq!(state.last_sensor_data.as_ref().map(|data| data.co2), Some(1600));
+        });
+
+        let drawn: Vec<_> = commands.iter().map(drawn_co2).collect();
+        assert_eq!(drawn, [Some(600), Some(800), Some(1600), None]);
+        assert_eq!(commands.last(), Some(&DisplayCommand::AlarmFlash));
+    }
+
+    #[test]
+    fn toggles_advance_the_display_mode() {
+        let commands = run(
+            &[reading(600), Event::ToggleDisplayMode, Event::ToggleDisplayMode],
+            |state| {
+                assert_eq!(state.get_display_mode(), DisplayMode::HumidityHistory);
+            },
+        );
+
+        assert_eq!(commands[1..], [DisplayCommand::ToggleMode, DisplayCommand::ToggleMode]);
+    }
+
+    #[test]
+    fn toggles_wait_for_the_first_reading() {
+        let commands = run(&[Event::ToggleDisplayMode], |state| {
+            assert_eq!(state.get_display_mode(), DisplayMode::RawData);
+        });
+
+        assert!(commands.is_empty());
+    }
+
+    #[test]
+    fn toggles_are_ignored_while_frozen() {
+        let commands = run(
+            &[reading(600), Event::SetDisplayFreeze(true), Event::ToggleDisplayMode],
+            |state| {
+                assert_eq!(state.get_display_mode(), DisplayMode::RawData);
+            },
+        );
+
+        assert_eq!(commands.len(), 1);
+    }
+
+    #[test]
+    fn battery_updates_redraw_the_icon_on_changes() {
+        let commands = run(
+            &[
+                Event::BatteryLevel(80),
+                Event::BatteryLevel(78),
+                Event::BatteryLow,
+                Event::BatteryCharging,
+            ],
+            |state| {
+                assert_eq!(state.get_battery_level(), BatteryLevel::Charging);
+                assert!(!state.is_low_battery_warning());
+            },
+        );
+
+        assert_eq!(
+            commands,
+            [
+                DisplayCommand::UpdateBatteryPercentage(80),
+                DisplayCommand::BatteryWarningChanged,
+                DisplayCommand::UpdateBatteryCharging,
+                DisplayCommand::BatteryWarningChanged,
+            ]
+        );
+    }
+
+    #[test]
+    fn battery_level_follows_the_percentage() {
+        run(&[Event::BatteryCharging, Event::BatteryLevel(50)], |state| {
+            assert_eq!(state.get_battery_level(), BatteryLevel::Bat040);
+            assert_eq!(state.get_battery_percent(), 50);
+        });
+    }
+
+    /// Release time of the unplugs held in the tests
+    const RELEASE_AT: Instant = Instant::from_secs(10);
+
+    /// Unplugs at the given level while the charging icon has to stay
+    fn unplug(level: u8) -> Option<HeldUnplug> {
+        let (held, event) = hold_back_unplug(None, Event::BatteryLevel(level), Some(RELEASE_AT));
+        assert!(event.is_none());
+        held
+    }
+
+    #[test]
+    fn unplug_waits_for_the_charging_icon_time_and_a_confirming_measurement() {
+        let vsys_interval = Duration::from_secs(2);
+
+        // Just plugged in, the charging icon stays for its minimum time
+        assert_eq!(
+            unplug_release_at(Instant::from_secs(0), vsys_interval, Instant::from_secs(1)),
+            Some(Instant::from_secs(0) + MIN_CHARGING_ICON_TIME)
+        );
+        // Long plugged in, the next measurement has to confirm the unplug
+        assert_eq!(
+            unplug_release_at(Instant::from_secs(0), vsys_interval, Instant::from_secs(100)),
+            Some(Instant::from_secs(100) + vsys_interval * UNPLUG_CONFIRM_MEASUREMENTS + UNPLUG_CONFIRM_MARGIN)
+        );
+    }
+
+    #[test]
+    fn unplug_is_shown_right_away_without_charging_icon() {
+        let (held, event) = hold_back_unplug(None, Event::BatteryLevel(80), None);
+
+        assert_eq!(held, None);
+        assert!(matches!(event, Some(Event::BatteryLevel(80))));
+    }
+
+    #[test]
+    fn plugging_in_again_drops_the_held_unplug() {
+        let (held, event) = hold_back_unplug(unplug(80), Event::BatteryCharging, None);
+
+        assert_eq!(held, None);
+        assert!(matches!(event, Some(Event::BatteryCharging)));
+    }
+
+    #[test]
+    fn level_change_while_held_updates_the_level() {
+        let (held, event) = hold_back_unplug(unplug(80), Event::BatteryLevel(79), Some(Instant::from_secs(20)));
+
+        assert!(event.is_none());
+        let held = held.unwrap();
+        assert_eq!(held.release_at, RELEASE_AT);
+        assert!(matches!(
+            held.release().collect::<Vec<_>>()[..],
+            [Event::BatteryLevel(79)]
+        ));
+    }
+
+    #[test]
+    fn other_events_pass_while_held() {
+        let (held, event) = hold_back_unplug(unplug(80), Event::ToggleDisplayMode, None);
+
+        assert_eq!(held, unplug(80));
+        assert!(matches!(event, Some(Event::ToggleDisplayMode)));
+    }
+
+    #[test]
+    fn low_battery_waits_for_the_held_unplug() {
+        let (held, event) = hold_back_unplug(unplug(8), Event::BatteryLow, None);
+        assert!(event.is_none());
+
+        // Until the release the warning banner does not cover the charging icon, then it follows the level
+        let mut events = vec![Event::BatteryCharging];
+        events.extend(held.unwrap().release());
+        let commands = run(&events, |state| {
+            assert_eq!(state.get_battery_level(), BatteryLevel::Bat000);
+            assert!(state.is_low_battery_warning());
+        });
+        assert_eq!(
+            commands,
+            [
+                DisplayCommand::UpdateBatteryCharging,
+                DisplayCommand::UpdateBatteryPercentage(8),
+                DisplayCommand::BatteryWarningChanged,
+            ]
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Power profiles, a single setting bundling the behaviors that trade battery runtime against responsiveness
+//!
+//! Approximate runtime on a 2500mAh battery, of which around 90% is usable. The controller and display draw around
+//! 10-11mA, the ENS160 around 28mA in standard mode and next to nothing in deep sleep.
+//! + Eco: the ENS160 sleeps between readings and is only woken for its warmup, the display is switched off shortly
+//!   after each reading. Around 17mA on average, roughly 130 hours. The ENS160 restarts every cycle, so its readings
+//!   are less stable than in continuous operation.
+//! + Normal: the ENS160 runs continuously and is read every 5 minutes, the display stays on. Around 39mA, roughly
+//!   60 hours.
+//! + Performance: like normal, but read every minute and the battery is measured more often. The additional I2C
+//!   and ADC traffic is small against the ENS160, runtime is only slightly below normal.
+//!
+//! Single behaviors can be pinned regardless of the selected profile with `OVERRIDES`.
+
+use defmt::Format;
+use embassy_time::Duration;
+
+use crate::sensor::{CHARGING_READ_INTERVAL, MIN_READ_INTERVAL, READ_INTERVAL, WARMUP_TIME};
+
+/// Power profile selected by the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum PowerProfile {
+    /// Longest battery runtime, slower and less stable readings
+    Eco,
+    /// Continuous ENS160 operation with readings every 5 minutes
+    Normal,
+    /// Frequent readings and battery measurements, always-on display
+    Performance,
+}
+
+/// Behaviors configured by a power profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerParams {
+    /// Seconds between two sensor readings
+    pub read_interval: u64,
+    /// Number of ENS160 readings per iteration, the median of which is used
+    pub ens160_samples: usize,
+    /// Whether the ENS160 sleeps between readings, it is woken `WARMUP_TIME` before each reading
+    pub ens160_sleep: bool,
+    /// Interval between two battery voltage measurements
+    pub vsys_interval: Duration,
+    /// How long the display stays on after a reading, `None` keeps it on
+    pub display_timeout: Option<Duration>,
+}
+
+/// Individual overrides applied on top of the selected profile, `None` keeps the value of the profile
+#[derive(Debug, Clone, Copy)]
+struct PowerOverrides {
+    /// Overrides `PowerParams::read_interval`
+    read_interval: Option<u64>,
+    /// Overrides `PowerParams::ens160_samples`
+    ens160_samples: Option<usize>,
+    /// Overrides `PowerParams::ens160_sleep`
+    ens160_sleep: Option<bool>,
+    /// Overrides `PowerParams::vsys_interval`
+    vsys_interval: Option<Duration>,
+    /// Overrides `PowerParams::display_timeout`, `Some(None)` keeps the display on
+    #[allow(clippy::option_option)]
+    display_timeout: Option<Option<Duration>>,
+}
+
+/// Overrides in effect for all profiles
+const OVERRIDES: PowerOverrides = PowerOverrides {
+    read_interval: None,
+    ens160_samples: None,
+    ens160_sleep: None,
+    vsys_interval: None,
+    display_timeout: None,
+};
+
+/// All profiles, for compile-time checks
+const PROFILES: [PowerProfile; 3] = [PowerProfile::Eco, PowerProfile::Normal, PowerProfile::Performance];
+
+/// Longest read interval in seconds of any profile, the watchdog countdown is sized against this
+pub const MAX_READ_INTERVAL: u64 = {
+    let mut max = 0;
+    let mut i = 0;
+    while i < PROFILES.len() {
+        let read_interval = PROFILES[i].params().read_interval;
+        if read_interval > max {
+            max = read_interval;
+        }
+        i += 1;
+    }
+    max
+};
+
+/// Longest VSYS measurement interval of any profile, the watchdog staleness of the VSYS task is sized against this
+pub const MAX_VSYS_INTERVAL: Duration = {
+    let mut max = Duration::from_ticks(0);
+    let mut i = 0;
+    while i < PROFILES.len() {
+        let vsys_interval = PROFILES[i].params().vsys_interval;
+        if vsys_interval.as_ticks() > max.as_ticks() {
+            max = vsys_interval;
+        }
+        i += 1;
+    }
+    max
+};
+
+// A sleeping ENS160 is woken a full warmup ahead of the next reading, which has to fit into the read interval
+const _: () = {
+    let mut i = 0;
+    while i < PROFILES.len() {
+        let params = PROFILES[i].params();
+        assert!(
+            !params.ens160_sleep || params.read_interval > WARMUP_TIME,
+            "Read interval must exceed the ENS160 warmup when the ENS160 sleeps between readings"
+        );
+        i += 1;
+    }
+};
+
+// The watchdog countdown is sized against the longest interval of the profiles, a runtime interval stays within it
+const _: () = assert!(
+    MIN_READ_INTERVAL <= MAX_READ_INTERVAL,
+    "MIN_READ_INTERVAL must not exceed MAX_READ_INTERVAL"
+);
+const _: () = {
+    if let Some(read_interval) = CHARGING_READ_INTERVAL {
+        assert!(
+            read_interval >= MIN_READ_INTERVAL,
+            "CHARGING_READ_INTERVAL must not be below MIN_READ_INTERVAL"
+        );
+        assert!(
+            read_interval <= MAX_READ_INTERVAL,
+            "CHARGING_READ_INTERVAL must not exceed MAX_READ_INTERVAL"
+        );
+    }
+};
+
+impl PowerProfile {
+    /// Behaviors of the profile with `OVERRIDES` applied
+    pub const fn params(self) -> PowerParams {
+        let params = match self {
+            Self::Eco => PowerParams {
+                read_interval: 600,
+                ens160_samples: 1,
+                ens160_sleep: true,
+                vsys_interval: Duration::from_secs(30),
+                display_timeout: Some(Duration::from_secs(30)),
+            },
+            Self::Normal => PowerParams {
+                read_interval: READ_INTERVAL,
+                ens160_samples: 3,
+                ens160_sleep: false,
+                vsys_interval: Duration::from_secs(4),
+                display_timeout: None,
+            },
+            Self::Performance => PowerParams {
+                read_interval: 60,
+                ens160_samples: 3,
+                ens160_sleep: false,
+                vsys_interval: Duration::from_secs(2),
+                display_timeout: None,
+            },
+        };
+
+        PowerParams {
+            read_interval: match OVERRIDES.read_interval {
+                Some(read_interval) => read_interval,
+                None => params.read_interval,
+            },
+            ens160_samples: match OVERRIDES.ens160_samples {
+                Some(ens160_samples) => ens160_samples,
+                None => params.ens160_samples,
+            },
+            ens160_sleep: match OVERRIDES.ens160_sleep {
+                Some(ens160_sleep) => ens160_sleep,
+                None => params.ens160_sleep,
+            },
+            vsys_interval: match OVERRIDES.vsys_interval {
+                Some(vsys_interval) => vsys_interval,
+                None => params.vsys_interval,
+            },
+            display_timeout: match OVERRIDES.display_timeout {
+                Some(display_timeout) => display_timeout,
+                None => params.display_timeout,
+            },
+        }
+    }
+
+    /// Name of the profile as used on the serial console
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Eco => "eco",
+            Self::Normal => "normal",
+            Self::Performance => "performance",
+        }
+    }
+
+    /// Parses a profile name as used on the serial console
+    pub fn from_name(name: &str) -> Option<Self> {
+        PROFILES.into_iter().find(|profile| profile.name() == name)
+    }
+}
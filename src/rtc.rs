@@ -0,0 +1,370 @@
+//! Optional DS3231 real time clock on the shared I2C bus, providing wall-clock time across power cycles
+//!
+//! The clock is read at startup and periodically afterwards, in between the time is advanced with the uptime timer.
+//! Without a (working) RTC `now()` returns `None` and timestamps fall back to uptime only.
+
+use core::fmt;
+
+use defmt::{Format, error, info};
+use embassy_futures::select::{Either, select};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex, signal::Signal};
+use embassy_time::{Duration, Instant, Timer};
+#[cfg(target_os = "none")]
+use embedded_hal_async::i2c::I2c;
+
+#[cfg(target_os = "none")]
+use crate::{
+    I2cBusDevice,
+    diagnostics::{BusDevice, record_i2c_error},
+};
+use crate::{
+    event::{Event, send_event},
+    storage_mode,
+    system_state::SYSTEM_STATE,
+};
+
+/// I2C address of the DS3231
+const DS3231_ADDRESS: u8 = 0x68;
+
+/// First time keeping register (seconds), followed by minutes, hours, weekday, date, month and year
+const DS3231_TIME_REGISTER: u8 = 0x00;
+
+/// Status register, holds the oscillator stop flag
+const DS3231_STATUS_REGISTER: u8 = 0x0F;
+
+/// Oscillator stop flag, set when the RTC lost power and the time is invalid
+const DS3231_OSCILLATOR_STOP_FLAG: u8 = 0x80;
+
+/// How often the cached time is resynchronized from the RTC, correcting drift of the uptime timer
+const RESYNC_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// How often the quiet hours schedule is evaluated
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Seconds per day
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// First year representable by the DS3231 (its year register holds 00-99)
+const BASE_YEAR: u16 = 2000;
+
+/// Cached wall-clock time, `None` until the RTC has been read successfully
+static CLOCK: Mutex<CriticalSectionRawMutex, Option<ClockBase>> = Mutex::new(None);
+
+/// Signal carrying a new time to be written to the RTC
+static SET_TIME_SIGNAL: Signal<CriticalSectionRawMutex, DateTime> = Signal::new();
+
+/// A wall-clock date and time
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Format)]
+pub struct DateTime {
+    /// Year, 2000-2099
+    pub year: u16,
+    /// Month, 1-12
+    pub month: u8,
+    /// Day of month, 1-31
+    pub day: u8,
+    /// Hour, 0-23
+    pub hour: u8,
+    /// Minute, 0-59
+    pub minute: u8,
+    /// Second, 0-59
+    pub second: u8,
+}
+
+impl DateTime {
+    /// Creates a date and time, returns `None` if any field is out of range
+    pub const fn new(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Option<Self> {
+        if year < BASE_YEAR
+            || year >= BASE_YEAR + 100
+            || month < 1
+            || month > 12
+            || day < 1
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 59
+        {
+            return None;
+        }
+        Some(Self {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        })
+    }
+
+    /// Seconds since 2000-01-01 00:00:00
+    fn to_seconds(self) -> u64 {
+        let mut days = u64::from(self.day) - 1;
+        for year in BASE_YEAR..self.year {
+            days += if is_leap_year(year) { 366 } else { 365 };
+        }
+        for month in 1..self.month {
+            days += u64::from(days_in_month(self.year, month));
+        }
+        days * SECONDS_PER_DAY + u64::from(self.hour) * 3600 + u64::from(self.minute) * 60 + u64::from(self.second)
+    }
+
+    /// Date and time from seconds since 2000-01-01 00:00:00
+    #[allow(clippy::cast_possible_truncation)]
+    fn from_seconds(seconds: u64) -> Self {
+        let mut days = seconds / SECONDS_PER_DAY;
+        let time_of_day = seconds % SECONDS_PER_DAY;
+
+        let mut year = BASE_YEAR;
+        loop {
+            let year_days = if is_leap_year(year) { 366 } else { 365 };
+            if days < year_days {
+                break;
+            }
+            days -= year_days;
+            year += 1;
+        }
+
+        let mut month = 1;
+        loop {
+            let month_days = u64::from(days_in_month(year, month));
+            if days < month_days {
+                break;
+            }
+            days -= month_days;
+            month += 1;
+        }
+
+        Self {
+            year,
+            month,
+            day: days as u8 + 1,
+            hour: (time_of_day / 3600) as u8,
+            minute: (time_of_day % 3600 / 60) as u8,
+            second: (time_of_day % 60) as u8,
+        }
+    }
+
+    /// Day of week as stored by the DS3231, 1 (Monday) to 7 (Sunday)
+    #[allow(clippy::cast_possible_truncation)]
+    fn weekday(self) -> u8 {
+        // 2000-01-01 was a Saturday
+        ((self.to_seconds() / SECONDS_PER_DAY + 5) % 7) as u8 + 1
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// Wall-clock time read from the RTC together with the uptime instant it was read at
+#[derive(Clone, Copy)]
+struct ClockBase {
+    /// Seconds since 2000-01-01 00:00:00 at `at`
+    seconds: u64,
+    /// Uptime instant the RTC was read
+    at: Instant,
+}
+
+/// Gets the current wall-clock time, `None` if no RTC time is available
+pub async fn now() -> Option<DateTime> {
+    let base = (*CLOCK.lock().await)?;
+    Some(DateTime::from_seconds(base.seconds + base.at.elapsed().as_secs()))
+}
+
+/// Requests the RTC to be set to a new time
+pub fn set_time(date_time: DateTime) {
+    SET_TIME_SIGNAL.signal(date_time);
+}
+
+/// Whether a year is a leap year
+const fn is_leap_year(year: u16) -> bool {
+    year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400))
+}
+
+/// Number of days in a month of a year
+const fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Converts a BCD encoded register value to binary
+const fn from_bcd(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0x0F)
+}
+
+/// Converts a binary value to BCD for a register
+const fn to_bcd(value: u8) -> u8 {
+    ((value / 10) << 4) | (value % 10)
+}
+
+/// Failure of an access to the DS3231
+#[cfg(target_os = "none")]
+#[derive(Debug, Clone, Copy)]
+enum RtcError {
+    /// An I2C transaction with the DS3231 failed
+    Bus(&'static str),
+    /// The DS3231 answered, but holds no valid time
+    Time(&'static str),
+}
+
+#[cfg(target_os = "none")]
+impl RtcError {
+    /// Description of the failure
+    const fn message(self) -> &'static str {
+        match self {
+            Self::Bus(message) | Self::Time(message) => message,
+        }
+    }
+}
+
+/// Logs a failed access to the DS3231 as an error, a failed transaction counts as an I2C error like with the other
+/// devices
+#[cfg(target_os = "none")]
+async fn report_rtc_error(action: &str, error: RtcError) {
+    error!("{} failed: {}", action, error.message());
+    if let RtcError::Bus(_) = error {
+        record_i2c_error(BusDevice::Rtc).await;
+    }
+}
+
+/// Reads the current time from the DS3231
+#[cfg(target_os = "none")]
+async fn read_time(device: &mut I2cBusDevice) -> Result<DateTime, RtcError> {
+    let mut status = [0u8; 1];
+    device
+        .write_read(DS3231_ADDRESS, &[DS3231_STATUS_REGISTER], &mut status)
+        .await
+        .map_err(|_| RtcError::Bus("Failed to read RTC status"))?;
+    if status[0] & DS3231_OSCILLATOR_STOP_FLAG != 0 {
+        return Err(RtcError::Time("RTC lost power, time is not set"));
+    }
+
+    let mut registers = [0u8; 7];
+    device
+        .write_read(DS3231_ADDRESS, &[DS3231_TIME_REGISTER], &mut registers)
+        .await
+        .map_err(|_| RtcError::Bus("Failed to read RTC time"))?;
+
+    DateTime::new(
+        BASE_YEAR + u16::from(from_bcd(registers[6])),
+        from_bcd(registers[5] & 0x1F),
+        from_bcd(registers[4] & 0x3F),
+        // Always written in 24h mode
+        from_bcd(registers[2] & 0x3F),
+        from_bcd(registers[1] & 0x7F),
+        from_bcd(registers[0] & 0x7F),
+    )
+    .ok_or(RtcError::Time("RTC returned an invalid time"))
+}
+
+/// Writes a new time to the DS3231 and clears the oscillator stop flag
+#[cfg(target_os = "none")]
+#[allow(clippy::cast_possible_truncation)]
+async fn write_time(device: &mut I2cBusDevice, date_time: DateTime) -> Result<(), RtcError> {
+    device
+        .write(
+            DS3231_ADDRESS,
+            &[
+                DS3231_TIME_REGISTER,
+                to_bcd(date_time.second),
+                to_bcd(date_time.minute),
+                to_bcd(date_time.hour),
+                date_time.weekday(),
+                to_bcd(date_time.day),
+                to_bcd(date_time.month),
+                to_bcd((date_time.year - BASE_YEAR) as u8),
+            ],
+        )
+        .await
+        .map_err(|_| RtcError::Bus("Failed to write RTC time"))?;
+
+    let mut status = [0u8; 1];
+    device
+        .write_read(DS3231_ADDRESS, &[DS3231_STATUS_REGISTER], &mut status)
+        .await
+        .map_err(|_| RtcError::Bus("Failed to read RTC status"))?;
+    device
+        .write(
+            DS3231_ADDRESS,
+            &[DS3231_STATUS_REGISTER, status[0] & !DS3231_OSCILLATOR_STOP_FLAG],
+        )
+        .await
+        .map_err(|_| RtcError::Bus("Failed to clear RTC oscillator stop flag"))
+}
+
+/// Reads the RTC and updates the cached time, keeps the previous time if reading fails
+/// At boot a failure only means the RTC is absent or not set yet, which is no fault and in particular must not lower
+/// the bus clock as an I2C error would
+#[cfg(target_os = "none")]
+async fn sync_clock(device: &mut I2cBusDevice, at_boot: bool) {
+    match read_time(device).await {
+        Ok(date_time) => {
+            *CLOCK.lock().await = Some(ClockBase {
+                seconds: date_time.to_seconds(),
+                at: Instant::now(),
+            });
+            info!("RTC time: {}", date_time);
+        }
+        Err(e) if at_boot => info!("RTC unavailable, using uptime-only timestamps: {}", e.message()),
+        Err(e) => report_rtc_error("Reading RTC", e).await,
+    }
+}
+
+/// Whether an hour lies within the quiet hours
+const fn is_quiet_hour(hour: u8, (start, end): (u8, u8)) -> bool {
+    if start <= end {
+        hour >= start && hour < end
+    } else {
+        hour >= start || hour < end
+    }
+}
+
+/// RTC task, keeps the cached time in sync, applies new times and drives the quiet hours schedule
+#[cfg(target_os = "none")]
+#[embassy_executor::task]
+pub async fn rtc_task(mut device: I2cBusDevice) {
+    sync_clock(&mut device, true).await;
+    let mut last_sync = Instant::now();
+    let mut quiet = None;
+
+    loop {
+        match select(SET_TIME_SIGNAL.wait(), Timer::after(SCHEDULE_CHECK_INTERVAL)).await {
+            Either::First(date_time) => match write_time(&mut device, date_time).await {
+                Ok(()) => {
+                    info!("RTC set to {}", date_time);
+                    sync_clock(&mut device, false).await;
+                    last_sync = Instant::now();
+                }
+                Err(e) => report_rtc_error("Setting RTC", e).await,
+            },
+            Either::Second(()) => {
+                storage_mode::park_if_active("RTC").await;
+                if last_sync.elapsed() >= RESYNC_INTERVAL {
+                    sync_clock(&mut device, false).await;
+                    last_sync = Instant::now();
+                }
+            }
+        }
+
+        // Switch night mode only when entering or leaving quiet hours, so a manual toggle in between sticks
+        // Quiet hours are (start hour, end hour) in 24h format, the range may wrap around midnight
+        let quiet_hours = SYSTEM_STATE.lock().await.get_settings().quiet_hours;
+        if let (Some(quiet_hours), Some(date_time)) = (quiet_hours, now().await) {
+            let is_quiet = is_quiet_hour(date_time.hour, quiet_hours);
+            if quiet != Some(is_quiet) {
+                quiet = Some(is_quiet);
+                info!("Quiet hours {}", if is_quiet { "started" } else { "ended" });
+                send_event(Event::SetNightMode(is_quiet)).await;
+            }
+        }
+    }
+}
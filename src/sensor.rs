@@ -1,44 +1,324 @@
 //! Sensor task for reading data from AHT21 and ENS160 sensors.
+#[cfg(feature = "health-summary")]
+use core::fmt::Write;
+#[cfg(target_os = "none")]
+use core::pin::pin;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(target_os = "none")]
 use aht20_async::Aht20;
-use defmt::{Debug2Format, info};
+use defmt::{Debug2Format, error, info, warn};
+#[cfg(target_os = "none")]
 use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice;
-use embassy_rp::{
-    gpio::Input,
-    i2c::{Async, I2c},
-    peripherals::I2C0,
-};
-use embassy_sync::blocking_mutex::raw::NoopRawMutex;
-use embassy_time::{Delay, Timer};
-use ens160_aq::{
-    Ens160,
-    data::{AirQualityIndex, InterruptPinConfig},
-};
+use embassy_futures::select::{Either, select};
+#[cfg(target_os = "none")]
+use embassy_rp::{gpio::Input, pac};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+#[cfg(any(target_os = "none", feature = "debug-serial"))]
+use embassy_time::Instant;
+use embassy_time::{Delay, Duration, Timer, with_timeout};
+#[cfg(target_os = "none")]
+use embedded_hal_async::i2c::I2c;
+#[cfg(target_os = "none")]
+use ens160_aq::Ens160;
+use ens160_aq::data::{AirQualityIndex, InterruptPinConfig, OperationMode, Status, Validity};
+#[cfg(feature = "health-summary")]
+use heapless::String;
 use heapless::Vec;
+#[cfg(target_os = "none")]
 use moving_median::MovingMedian;
+#[cfg(target_os = "none")]
 use panic_probe as _;
 
+#[cfg(feature = "debug-serial")]
+use crate::diagnostics::record_loop_time;
+#[cfg(target_os = "none")]
+use crate::{I2cBus, I2cBusDevice};
 use crate::{
+    diagnostics::{
+        BusDevice, Ens160Compensation, get_diagnostics, record_ens160_compensation, record_ens160_validity,
+        record_i2c_error,
+    },
     event::{Event, send_event},
     humidity_calibrator::HumidityCalibrator,
+    settings_store::{MAX_USER_HUMIDITY_OFFSET, Settings},
+    storage_mode,
+    system_state::{SYSTEM_STATE, SensorFault, VocKind},
+    verbosity::should_log_verbose,
     watchdog::{TaskId, report_task_failure, report_task_success},
 };
+#[cfg(feature = "health-summary")]
+use crate::{system_state::ReadingQuality, usb_serial::send_health_summary};
+
+/// Maximum length of a health summary line
+#[cfg(feature = "health-summary")]
+const HEALTH_LINE_CAPACITY: usize = 96;
 
-/// Temperature offset for AHT21 sensor in degrees Celsius
-static AHT21_TEMPERATURE_OFFSET: f32 = -3.5;
+/// I2C address of the AHT21
+const AHT21_ADDRESS: u8 = 0x38;
+
+/// I2C address of the ENS160 on the combined AHT21/ENS160 board (ADDR pin high)
+const ENS160_ADDRESS: u8 = 0x53;
 
 /// Warmup time for ENS160 sensor in seconds
-const WARMUP_TIME: u64 = 180;
+pub const WARMUP_TIME: u64 = 180;
+
+/// Shortened warmup in seconds after a warm reboot on USB power, the ENS160 kept its power through the reboot
+const WARM_REBOOT_WARMUP_TIME: u64 = 15;
+
+/// Whether a warm reboot on USB power shortens the warmup, on battery the full warmup always applies
+const SHORTEN_WARMUP_ON_WARM_REBOOT: bool = true;
+
+const _: () = assert!(
+    WARM_REBOOT_WARMUP_TIME <= WARMUP_TIME,
+    "WARM_REBOOT_WARMUP_TIME must not exceed WARMUP_TIME"
+);
+
+/// Watchdog scratch register marking a warmed up ENS160, it survives resets but not a loss of power
+/// (scratch registers 4 to 7 are used by the bootrom)
+const ENS160_WARM_SCRATCH: usize = 0;
+
+/// Value of `ENS160_WARM_SCRATCH` while the ENS160 is warmed up and in continuous operation ("E160")
+const ENS160_WARM_MARKER: u32 = 0x4531_3630;
+
+/// Read interval for continuous operation (5 minutes), used by the normal power profile
+pub const READ_INTERVAL: u64 = 300;
+
+/// Read interval while charging (1 minute), as power is plentiful then, `None` keeps the interval of the power profile
+/// An interval set on the serial console takes precedence
+pub const CHARGING_READ_INTERVAL: Option<u64> = Some(60);
+
+/// Shortest read interval in seconds that can be set on the serial console, an iteration may take this long
+pub const MIN_READ_INTERVAL: u64 = MAX_ITERATION_TIME;
 
-/// Read interval for continuous operation (5 minutes)
-const READ_INTERVAL: u64 = 300;
+/// Maximum random deviation in seconds from the read interval, either way, 0 reads at the exact interval
+/// Spreads the readings so they do not lock onto periodic cycles in the environment, e.g. an HVAC system
+pub const READ_INTERVAL_JITTER: u64 = 0;
 
-/// Number of readings for ENS160 median calculation
+/// Upper bound in seconds for one sensor iteration including AHT21 recovery and all ENS160 median readings
+/// The watchdog countdown is sized against this, so it must hold even when every reading waits for its timeout
+pub const MAX_ITERATION_TIME: u64 = 30;
+
+/// Maximum number of readings for ENS160 median calculation, the power profile selects how many are taken
 const ENS160_MEDIAN_READINGS: usize = 3;
 
+/// How long to wait for the ENS160 data ready interrupt, the sensor delivers new data every second in standard mode
+const ENS160_DATA_READY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Interval at which the ENS160 status register is polled for new data when the interrupt is not used
+const ENS160_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How the sensor task learns that the ENS160 has new data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+enum DataReadySource {
+    /// Wait for the interrupt pin, falling back to polling if it never fires (e.g. pin not wired)
+    Interrupt,
+    /// Poll the status register, for boards without the interrupt wire
+    Polling,
+}
+
+/// Data ready source used at startup
+const DATA_READY_SOURCE: DataReadySource = DataReadySource::Interrupt;
+
+// All ENS160 readings timing out must still fit the iteration budget, including one extra timeout for the fallback
+// from interrupt to polling and 5 seconds for AHT21 recovery
+const _: () = assert!(
+    ENS160_DATA_READY_TIMEOUT.as_secs() * (ENS160_MEDIAN_READINGS as u64 + 1) + 5 <= MAX_ITERATION_TIME,
+    "MAX_ITERATION_TIME does not cover all ENS160 readings timing out"
+);
+
+/// Number of sensor initialization attempts at startup before entering the sensor error standby
+const SENSOR_INIT_ATTEMPTS: u8 = 4;
+
+/// Delay before the second initialization attempt, doubled for every further attempt
+const SENSOR_INIT_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Number of quick ENS160 initialization attempts within one sensor initialization attempt
+const ENS160_INIT_ATTEMPTS: u8 = 5;
+
+/// Delay before the second quick ENS160 initialization attempt, quadrupled for every further attempt
+const ENS160_INIT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Interval at which initialization is retried in the sensor error standby
+const SENSOR_STANDBY_RETRY_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Number of consecutive AHT21 read failures before a soft reset and re-calibration is attempted
+const AHT21_FAILURES_BEFORE_RESET: u8 = 2;
+
+/// Temperature compensation change (degrees Celsius) above which the ENS160 output needs to settle
+const COMPENSATION_TEMPERATURE_CHANGE_THRESHOLD: f32 = 2.0;
+
+/// Humidity compensation change (percentage points) above which the ENS160 output needs to settle
+const COMPENSATION_HUMIDITY_CHANGE_THRESHOLD: f32 = 5.0;
+
+/// Number of readings flagged as settling after a large compensation change
+const SETTLING_READINGS: u8 = 2;
+
+/// How the compensation humidity is brought to the whole percent the ENS160 driver takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+enum HumidityRounding {
+    /// Cut off the fraction, up to 1% low
+    Truncate,
+    /// Round to the nearest percent, at most 0.5% off
+    Nearest,
+}
+
+impl HumidityRounding {
+    /// Rounds a humidity in percent to whole percent
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn apply(self, rh: f32) -> u16 {
+        match self {
+            Self::Truncate => rh as u16,
+            Self::Nearest => (rh + 0.5) as u16,
+        }
+    }
+
+    /// Name of the rounding as reported on the serial console and in the log
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Truncate => "truncated",
+            Self::Nearest => "rounded",
+        }
+    }
+}
+
+/// Rounding of the humidity written to the ENS160 compensation
+const COMPENSATION_HUMIDITY_ROUNDING: HumidityRounding = HumidityRounding::Truncate;
+
+/// Number of readings flagged as settling after a forced fresh air baseline, the ENS160 takes about an hour to
+/// stabilize after the reset
+const BASELINE_RESET_SETTLING_READINGS: u8 = 12;
+
+/// Signal carrying commands for the sensor task, handled in between readings
+static SENSOR_COMMAND_SIGNAL: Signal<CriticalSectionRawMutex, SensorCommand> = Signal::new();
+
+/// Signal carrying the answer of the sensor task to `SensorCommand::DumpEns160Registers`
+static ENS160_DUMP_SIGNAL: Signal<CriticalSectionRawMutex, Result<Ens160RegisterDump, &'static str>> = Signal::new();
+
+/// How long a register dump request waits for the sensor task, which answers once a reading in progress is done
+const ENS160_DUMP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Commands for the sensor task from other tasks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SensorCommand {
+    /// Reset the ENS160 baseline to the current air
+    FreshAirBaseline,
+    /// Put the ENS160 into deep sleep and park the task for storage mode
+    StorageMode,
+    /// Read the ENS160 registers for the serial console, answered on `ENS160_DUMP_SIGNAL`
+    DumpEns160Registers,
+}
+
+/// Order in which the sensors are read in each iteration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+enum ReadOrder {
+    /// Read the AHT21 first and compensate the ENS160 with this cycle's temperature and humidity
+    Aht21First,
+    /// Read the ENS160 first, right after its data ready interrupt, compensated with the previous cycle's values
+    /// The compensation then lags one read interval behind, which matters little indoors where conditions drift slowly
+    Ens160First,
+}
+
+/// Sensor read order used by the sensor task
+const READ_ORDER: ReadOrder = ReadOrder::Aht21First;
+
+/// Whether the AHT21 is read again before every ENS160 median sample after the first to update the compensation
+/// The median samples span several seconds, during which temperature and humidity can change, e.g. when a window is
+/// opened. Refreshing the compensation tracks such changes, at the cost of an AHT21 read and a compensation write per
+/// extra sample on the shared I2C bus. Indoors conditions rarely change within seconds, so it is off by default and
+/// the compensation is written once per iteration.
+const COMPENSATE_EACH_ENS160_SAMPLE: bool = false;
+
+/// How often the sensor task reports its health to the watchdog
+/// The watchdog counts a task as unhealthy from a failure report until its next success report, and pushes out its
+/// countdown only on health checks (every minute) that find all tasks healthy. The granularity therefore decides how
+/// fast a failing or recovering sensor shows up in those checks, not how long the countdown to a reset is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+enum HealthReportGranularity {
+    /// One report at the end of each iteration, failed if any step failed. A flaky step can only keep the task
+    /// unhealthy, never hide behind a step that succeeded
+    Iteration,
+    /// A report after each step (AHT21 read, ENS160 compensation, ENS160 read) as soon as it finishes, plus the one at
+    /// the end. A failed step is seen by the next health check even while the iteration is still retrying, at the cost
+    /// of more false alarms from steps that recover within the iteration
+    Substep,
+}
+
+/// Health report granularity of the sensor task
+const HEALTH_REPORT_GRANULARITY: HealthReportGranularity = HealthReportGranularity::Iteration;
+
+/// When the humidity calibrator starts learning from the AHT21 readings
+/// The AHT21 sits next to the ENS160 hotplates, so for a while after power-up the board heats it up and it reads warm
+/// and dry. The calibrator takes its first readings as the humidity baseline, so learning from them biases the
+/// baseline low until the drift correction slowly pulls it back. Waiting for settled conditions gives a more accurate
+/// baseline from the start, at the cost of readings calibrated with the user offset only until then.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+enum CalibratorWarmupGate {
+    /// Learn from the first reading on
+    Immediate,
+    /// Learn once the ENS160 reports valid readings, it has then finished its own warmup
+    Ens160Valid,
+    /// Learn once the raw temperature changed less than `STABLE_TEMPERATURE_CHANGE` between two readings
+    StableTemperature,
+}
+
+/// Gate of the humidity calibrator learning
+const CALIBRATOR_WARMUP_GATE: CalibratorWarmupGate = CalibratorWarmupGate::Immediate;
+
+/// Largest raw temperature change (degrees Celsius) between two readings that counts as stable for
+/// `CalibratorWarmupGate::StableTemperature`
+const STABLE_TEMPERATURE_CHANGE: f32 = 0.2;
+
+/// Lowest plausible eCO2 in ppm, the ENS160 never reports below the atmospheric baseline in normal operation
+const ECO2_BASELINE: u16 = 400;
+
+/// Whether eCO2 values below `ECO2_BASELINE` are clamped to it (they are always flagged as suspect)
+const CLAMP_SUB_BASELINE_ECO2: bool = true;
+
+/// Battery percentage at or below which only the AHT21 is read while the ENS160 sleeps, extending the runtime on a
+/// low battery, `None` always reads both sensors
+const LOW_BATTERY_SENSING_THRESHOLD: Option<u8> = None;
+
+/// Percentage points the battery has to recover above `LOW_BATTERY_SENSING_THRESHOLD` before full sensing resumes
+const LOW_BATTERY_SENSING_HYSTERESIS: u8 = 5;
+
+/// Whether the battery is low enough for reduced sensing, updated from the battery events of the VSYS task
+static LOW_BATTERY: AtomicBool = AtomicBool::new(false);
+
+/// Probes whether a device acknowledges its address on the shared bus, with a single byte read that does not change
+/// the state of the AHT21 or the ENS160
+#[cfg(target_os = "none")]
+async fn i2c_present(i2c_bus: &'static I2cBus, address: u8) -> bool {
+    let mut byte = [0u8; 1];
+    I2cDevice::new(i2c_bus).read(address, &mut byte).await.is_ok()
+}
+
+/// Tells why a sensor failed by probing its address: without an acknowledge it is disconnected (e.g. a loose
+/// connector), with one it is present but faulty
+#[cfg(target_os = "none")]
+async fn diagnose_sensor_failure(
+    i2c_bus: &'static I2cBus,
+    address: u8,
+    disconnected: SensorFault,
+    faulty: SensorFault,
+) -> SensorFault {
+    let fault = if i2c_present(i2c_bus, address).await {
+        faulty
+    } else {
+        disconnected
+    };
+    warn!("Sensor failure diagnosed: {}", fault);
+    fault
+}
+
 /// Initialize the AHT21 sensor
-async fn initialize_aht21(
-    aht21_device: I2cDevice<'static, NoopRawMutex, I2c<'static, I2C0, Async>>,
-) -> Option<Aht20<I2cDevice<'static, NoopRawMutex, I2c<'static, I2C0, Async>>, Delay>> {
+#[cfg(target_os = "none")]
+async fn initialize_aht21(aht21_device: I2cBusDevice) -> Option<Aht20<I2cBusDevice, Delay>> {
     let mut aht21 = Aht20::new(aht21_device, Delay).await.ok()?;
     Timer::after_millis(100).await;
     info!("calibrate aht21");
@@ -48,22 +328,124 @@ async fn initialize_aht21(
     Some(aht21)
 }
 
-/// Initialize the ENS160 sensor
-async fn initialize_ens160(
-    ens160_device: I2cDevice<'static, NoopRawMutex, I2c<'static, I2C0, Async>>,
-) -> Option<Ens160<I2cDevice<'static, NoopRawMutex, I2c<'static, I2C0, Async>>, Delay>> {
+/// Soft-reset and re-calibrate the AHT21 in place
+/// Used to recover from failing reads without rebooting the whole system
+#[cfg(target_os = "none")]
+pub async fn reset_aht21(aht21: &mut Aht20<I2cBusDevice, Delay>) -> Result<(), &'static str> {
+    info!("Soft-resetting AHT21");
+    aht21.soft_reset().await.map_err(|_| "Failed to soft-reset AHT21")?;
+    // The AHT20/21 needs up to 20ms after a soft reset before it accepts commands again
+    Timer::after_millis(20).await;
+    aht21.calibrate().await.map_err(|_| "Failed to re-calibrate AHT21")?;
+    info!("AHT21 soft reset and re-calibration successful");
+    Timer::after_millis(1000).await;
+    Ok(())
+}
+
+/// Initialize the ENS160 sensor, retrying quickly as an I2C hiccup at power-on is common
+/// Returns `None` only once all `ENS160_INIT_ATTEMPTS` failed
+#[cfg(target_os = "none")]
+async fn initialize_ens160(ens160_device: I2cBusDevice) -> Option<Ens160<I2cBusDevice, Delay>> {
     let mut ens160 = Ens160::new(ens160_device, Delay);
+    let mut backoff = ENS160_INIT_BACKOFF;
 
-    if let Err(e) = ens160.initialize().await {
-        info!(
-            "Failed to initialize ENS160: {} - triggering system reset",
-            Debug2Format(&e)
+    for attempt in 1..=ENS160_INIT_ATTEMPTS {
+        match ens160.initialize().await {
+            Ok(()) => {
+                info!("ENS160 initialized successfully (attempt {})", attempt);
+                return Some(ens160);
+            }
+            Err(e) => {
+                info!(
+                    "Failed to initialize ENS160 (attempt {} of {}): {}",
+                    attempt,
+                    ENS160_INIT_ATTEMPTS,
+                    Debug2Format(&e)
+                );
+            }
+        }
+        if attempt < ENS160_INIT_ATTEMPTS {
+            Timer::after(backoff).await;
+            backoff = backoff * 4;
+        }
+    }
+    None
+}
+
+/// Number of ENS160 readings the median is taken over, as set by the power profile
+fn ens160_samples(settings: &Settings) -> usize {
+    settings
+        .power_profile
+        .params()
+        .ens160_samples
+        .clamp(1, ENS160_MEDIAN_READINGS)
+}
+
+/// Results of one sensor cycle, for the health summary line on the serial console
+#[cfg(feature = "health-summary")]
+struct CycleHealth {
+    /// Whether the AHT21 read successfully, `None` if the cycle ended before it was read
+    aht21_ok: Option<bool>,
+    /// Whether the ENS160 read successfully, `None` if the cycle ended before it was read
+    ens160_ok: Option<bool>,
+    /// Whether the ENS160 reported normal operation for all samples
+    ens160_valid: bool,
+    /// Number of ENS160 readings the median was taken over
+    samples: usize,
+    /// AHT21 and ENS160 I2C errors counted before the cycle, the summary shows the ones added during it
+    i2c_errors_before: u32,
+    /// Quality of the reading sent, `None` if no reading was sent
+    quality: Option<ReadingQuality>,
+}
+
+#[cfg(feature = "health-summary")]
+impl CycleHealth {
+    /// Starts the results of a cycle
+    const fn new(i2c_errors_before: u32) -> Self {
+        Self {
+            aht21_ok: None,
+            ens160_ok: None,
+            ens160_valid: false,
+            samples: 0,
+            i2c_errors_before,
+            quality: None,
+        }
+    }
+
+    /// Formats the results as one line of `key=value` pairs for host scripts, with the I2C errors counted now, e.g.
+    /// `health aht21=ok ens160=ok valid=1 samples=3 i2c_errors=0 quality=Good`
+    fn summary_line(&self, i2c_errors: u32) -> String<HEALTH_LINE_CAPACITY> {
+        const fn result(ok: Option<bool>) -> &'static str {
+            match ok {
+                Some(true) => "ok",
+                Some(false) => "fail",
+                None => "skipped",
+            }
+        }
+
+        let mut line: String<HEALTH_LINE_CAPACITY> = String::new();
+        let _ = write!(
+            line,
+            "health aht21={} ens160={} valid={} samples={} i2c_errors={} quality=",
+            result(self.aht21_ok),
+            result(self.ens160_ok),
+            u8::from(self.ens160_valid),
+            self.samples,
+            i2c_errors.saturating_sub(self.i2c_errors_before)
         );
-        return None;
+        let _ = match self.quality {
+            Some(quality) => write!(line, "{quality:?}"),
+            None => write!(line, "none"),
+        };
+        line
     }
-    info!("ENS160 initialized successfully");
+}
 
-    Some(ens160)
+/// Sums the I2C errors of the AHT21 and the ENS160 counted so far
+#[cfg(feature = "health-summary")]
+async fn sensor_i2c_errors() -> u32 {
+    let diagnostics = get_diagnostics().await;
+    diagnostics.i2c_errors(BusDevice::Aht21) + diagnostics.i2c_errors(BusDevice::Ens160)
 }
 
 /// Struct to hold AHT21 sensor readings
@@ -76,42 +458,129 @@ struct Aht21Readings {
     raw_humidity: f32,
     /// Calibrated humidity in percentage
     calibrated_humidity: f32,
+    /// Dew point in degrees Celsius, from the display temperature and the calibrated humidity
+    dew_point: f32,
 }
 
 /// Struct to hold ENS160 sensor readings
 struct Ens160Readings {
     /// eCO2 level in ppm
     co2: f32,
-    /// Ethanol (TVOC) level in ppb
-    etoh: f32,
+    /// Ethanol or TVOC level in ppb, depending on `voc_kind`
+    voc: f32,
+    /// Which figure `voc` holds
+    voc_kind: VocKind,
     /// Air quality index data
     air_quality: AirQualityIndex,
+    /// Whether all readings reported normal operation
+    valid: bool,
+}
+
+/// Coefficient b of the Magnus formula over water, valid from -45 to 60 degrees Celsius (Sonntag 1990)
+const MAGNUS_B: f32 = 17.62;
+
+/// Coefficient c of the Magnus formula over water in degrees Celsius
+const MAGNUS_C: f32 = 243.12;
+
+/// Dew point in degrees Celsius by the Magnus formula, e.g. 9.3 at 20 degrees Celsius and 50%
+/// The humidity is bounded to 0.1%, 0% has no dew point
+// `mul_add` is not available without std
+#[allow(clippy::suboptimal_flops)]
+pub fn dew_point(temp_c: f32, rh: f32) -> f32 {
+    let gamma = ln(rh.clamp(0.1, 100.0) / 100.0) + MAGNUS_B * temp_c / (MAGNUS_C + temp_c);
+    MAGNUS_C * gamma / (MAGNUS_B - gamma)
+}
+
+/// Absolute humidity in g/m³, the water content of the air, from the temperature in degrees Celsius and the relative
+/// humidity in percent, e.g. 8.6 at 20 degrees Celsius and 50%
+/// The saturation vapor pressure follows the Magnus formula, the ideal gas law turns the vapor pressure into a density
+// `mul_add` is not available without std
+#[allow(clippy::suboptimal_flops)]
+pub fn absolute_humidity(temp_c: f32, rh: f32) -> f32 {
+    /// Saturation vapor pressure over water at 0 degrees Celsius in hPa
+    const SATURATION_PRESSURE_0C: f32 = 6.112;
+    /// Molar mass of water over the gas constant (18.015 g/mol / 8.314 J/(mol K)) times 100 Pa per hPa
+    const WATER_DENSITY_FACTOR: f32 = 216.7;
+
+    let saturation_pressure = SATURATION_PRESSURE_0C * exp(MAGNUS_B * temp_c / (MAGNUS_C + temp_c));
+    let vapor_pressure = saturation_pressure * rh.clamp(0.0, 100.0) / 100.0;
+    WATER_DENSITY_FACTOR * vapor_pressure / (273.15 + temp_c)
+}
+
+/// Exponential function, there is no `f32::exp` without std
+/// Powers of 2 are taken from the exponent bits, the remainder within ±ln 2 / 2 from a Taylor series that is accurate
+/// to about 1e-6. Meant for the moderate arguments of the Magnus formula, it does not handle overflow
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss,
+    clippy::suboptimal_flops
+)]
+fn exp(x: f32) -> f32 {
+    let k = (x / core::f32::consts::LN_2 + if x < 0.0 { -0.5 } else { 0.5 }) as i32;
+    let r = x - k as f32 * core::f32::consts::LN_2;
+    let series = 1.0 + r * (1.0 + r * (0.5 + r * (1.0 / 6.0 + r * (1.0 / 24.0 + r * (1.0 / 120.0 + r / 720.0)))));
+    f32::from_bits(((k + 127) as u32) << 23) * series
+}
+
+/// Natural logarithm of a positive normal `x`, there is no `f32::ln` without std
+/// The exponent contributes multiples of ln 2, the mantissa in [1, 2) an atanh series that is accurate to about 1e-6
+#[allow(clippy::cast_possible_wrap, clippy::cast_precision_loss, clippy::suboptimal_flops)]
+fn ln(x: f32) -> f32 {
+    let bits = x.to_bits();
+    let exponent = ((bits >> 23) & 0xFF) as i32 - 127;
+    let mantissa = f32::from_bits((bits & 0x007F_FFFF) | 0x3F80_0000);
+    let s = (mantissa - 1.0) / (mantissa + 1.0);
+    let s2 = s * s;
+    let series = s * (1.0 + s2 * (1.0 / 3.0 + s2 * (1.0 / 5.0 + s2 * (1.0 / 7.0 + s2 / 9.0))));
+    exponent as f32 * core::f32::consts::LN_2 + 2.0 * series
 }
 
 /// Read data from AHT21 sensor
+/// `temperature_offset` is added to the raw temperature for display
+/// `user_humidity_offset` is added to the humidity after the adaptive calibration, limited to `MAX_USER_HUMIDITY_OFFSET`
+/// The calibrator only learns from the reading with `learn`, see `CalibratorWarmupGate`
+#[cfg(target_os = "none")]
 async fn read_aht21(
-    aht21: &mut Aht20<I2cDevice<'static, NoopRawMutex, I2c<'static, I2C0, Async>>, Delay>,
+    aht21: &mut Aht20<I2cBusDevice, Delay>,
     humidity_calibrator: &mut HumidityCalibrator,
+    learn: bool,
+    temperature_offset: f32,
+    user_humidity_offset: f32,
 ) -> Result<Aht21Readings, &'static str> {
     let (hum, temp) = aht21.read().await.map_err(|_| "Failed to read AHT21 sensor")?;
     let raw_temp = temp.celsius();
     let raw_rh = hum.rh();
 
     // Add measurement to calibrator for learning (this also detects rapid changes)
-    humidity_calibrator.add_measurement(raw_temp, raw_rh);
+    if learn {
+        humidity_calibrator.add_measurement(raw_temp, raw_rh);
+    }
 
     // Apply calibration (this preserves rapid changes while applying offset corrections)
     let calibrated_rh = humidity_calibrator.calibrate_humidity(raw_temp, raw_rh);
 
+    // The user offset from a reference hygrometer stacks on top of the adaptive calibration
+    let user_humidity_offset = user_humidity_offset.clamp(-MAX_USER_HUMIDITY_OFFSET, MAX_USER_HUMIDITY_OFFSET);
+    let calibrated_rh = (calibrated_rh + user_humidity_offset).clamp(0.0, 100.0);
+
     let readings = Aht21Readings {
         raw_temperature: raw_temp,
-        display_temperature: raw_temp + AHT21_TEMPERATURE_OFFSET,
+        display_temperature: raw_temp + temperature_offset,
         raw_humidity: raw_rh,
         calibrated_humidity: calibrated_rh,
+        dew_point: dew_point(raw_temp + temperature_offset, calibrated_rh),
     };
 
-    let (is_calibrated, baseline_offset, statistical_offset, sample_count, in_rapid_change, long_term_count) =
-        humidity_calibrator.get_calibration_info();
+    let (
+        is_calibrated,
+        baseline_offset,
+        statistical_offset,
+        sample_count,
+        in_rapid_change,
+        long_term_count,
+        long_term_correction,
+    ) = humidity_calibrator.get_calibration_info();
     let calibration_status = if !is_calibrated {
         "ESTABLISHING_BASELINE"
     } else if in_rapid_change {
@@ -124,132 +593,401 @@ async fn read_aht21(
         "HYBRID_DRIFT_CORRECTION"
     };
 
-    info!(
-        "Temperature: {}°C (raw: {}°C), Humidity: {}% -> {}% (raw->cal), Calibration: {} (baseline offset: {}, statistical offset: {}, samples: {}, long-term count: {})",
-        readings.display_temperature,
-        readings.raw_temperature,
-        readings.raw_humidity,
-        readings.calibrated_humidity,
-        calibration_status,
-        baseline_offset,
-        statistical_offset,
-        sample_count,
-        long_term_count
-    );
+    if should_log_verbose() {
+        info!(
+            "Temperature: {}°C (raw: {}°C), Humidity: {}% -> {}% (raw->cal), Dew point: {}°C, Compensation absolute humidity: {} g/m³, Calibration: {} (baseline offset: {}, statistical offset: {}, user offset: {}, samples: {}, long-term count: {}, long-term correction: {})",
+            readings.display_temperature,
+            readings.raw_temperature,
+            readings.raw_humidity,
+            readings.calibrated_humidity,
+            readings.dew_point,
+            absolute_humidity(readings.raw_temperature, readings.calibrated_humidity),
+            calibration_status,
+            baseline_offset,
+            statistical_offset,
+            user_humidity_offset,
+            sample_count,
+            long_term_count,
+            long_term_correction
+        );
+    }
 
     Ok(readings)
 }
 
+/// Read data from AHT21 sensor, soft-resetting it after repeated failures
+/// After a successful reset the read is retried right away, so the iteration can still succeed
+#[cfg(target_os = "none")]
+async fn read_aht21_with_recovery(
+    i2c_bus: &'static I2cBus,
+    aht21: &mut Aht20<I2cBusDevice, Delay>,
+    humidity_calibrator: &mut HumidityCalibrator,
+    learn: bool,
+    temperature_offset: f32,
+    user_humidity_offset: f32,
+    consecutive_failures: &mut u8,
+) -> Result<Aht21Readings, &'static str> {
+    let mut result = read_aht21(
+        aht21,
+        humidity_calibrator,
+        learn,
+        temperature_offset,
+        user_humidity_offset,
+    )
+    .await;
+
+    if result.is_err() {
+        record_i2c_error(BusDevice::Aht21).await;
+        *consecutive_failures = consecutive_failures.saturating_add(1);
+        // A soft reset cannot reach a disconnected sensor, only one that returns bad data
+        let fault = diagnose_sensor_failure(
+            i2c_bus,
+            AHT21_ADDRESS,
+            SensorFault::Aht21Disconnected,
+            SensorFault::Aht21Error,
+        )
+        .await;
+        if *consecutive_failures >= AHT21_FAILURES_BEFORE_RESET && fault == SensorFault::Aht21Error {
+            info!(
+                "AHT21 failed {} consecutive reads - attempting recovery",
+                *consecutive_failures
+            );
+            match reset_aht21(aht21).await {
+                Ok(()) => {
+                    result = read_aht21(
+                        aht21,
+                        humidity_calibrator,
+                        learn,
+                        temperature_offset,
+                        user_humidity_offset,
+                    )
+                    .await;
+                    if result.is_err() {
+                        record_i2c_error(BusDevice::Aht21).await;
+                    }
+                }
+                Err(e) => {
+                    info!("AHT21 recovery failed: {}", e);
+                    record_i2c_error(BusDevice::Aht21).await;
+                }
+            }
+        }
+    }
+
+    if result.is_ok() {
+        *consecutive_failures = 0;
+    }
+
+    result
+}
+
+/// Poll the ENS160 status register until it reports new data
+#[cfg(target_os = "none")]
+async fn poll_ens160_data_ready(ens160: &mut Ens160<I2cBusDevice, Delay>) -> Result<(), &'static str> {
+    let poll = async {
+        loop {
+            let status = ens160.get_status().await.map_err(|_| "Failed to get ENS160 status")?;
+            if status.new_data_ready() {
+                return Ok(());
+            }
+            Timer::after(ENS160_POLL_INTERVAL).await;
+        }
+    };
+    with_timeout(ENS160_DATA_READY_TIMEOUT, poll)
+        .await
+        .map_err(|_| "Timed out polling ENS160 data ready")?
+}
+
+/// Wait until the ENS160 has new data, via the interrupt pin or by polling
+/// Switches to polling for good if the interrupt does not fire within the timeout
+#[cfg(target_os = "none")]
+async fn wait_for_ens160_data(
+    ens160: &mut Ens160<I2cBusDevice, Delay>,
+    int: &mut Input<'static>,
+    polling: &mut bool,
+) -> Result<(), &'static str> {
+    if !*polling {
+        // Bounded so a stuck or unwired pin cannot hang the iteration
+        if with_timeout(ENS160_DATA_READY_TIMEOUT, int.wait_for_low())
+            .await
+            .is_ok()
+        {
+            if should_log_verbose() {
+                info!("ENS160 interrupt received - data ready");
+            }
+            return Ok(());
+        }
+        warn!("ENS160 interrupt did not fire - is the INT pin wired? Falling back to polling the status register");
+        *polling = true;
+    }
+
+    poll_ens160_data_ready(ens160).await?;
+    if should_log_verbose() {
+        info!("ENS160 status polled - data ready");
+    }
+    Ok(())
+}
+
 /// Read data from ENS160 sensor
-/// Uses moving median of 3 readings taken, using interrupt to ensure complete data
-/// Note: Temperature and humidity compensation should be set separately using `set_ens160_compensation`
+/// Uses moving median of up to 3 readings taken, waiting for new data before each so the data is complete
+/// The number of readings and whether the ethanol or the TVOC figure is read come from the settings
+/// Note: Temperature and humidity compensation should be set separately using `set_ens160_compensation`, with an
+/// `aht21` given it is refreshed from the AHT21 before each further reading
+#[cfg(target_os = "none")]
 async fn read_ens160(
-    ens160: &mut Ens160<I2cDevice<'static, NoopRawMutex, I2c<'static, I2C0, Async>>, Delay>,
+    ens160: &mut Ens160<I2cBusDevice, Delay>,
     int: &mut Input<'static>,
+    loop_state: &mut SensorLoopState,
+    mut aht21: Option<&mut Aht20<I2cBusDevice, Delay>>,
 ) -> Result<Ens160Readings, &'static str> {
+    let samples = ens160_samples(&loop_state.settings);
+    let voc_kind = loop_state.settings.voc_kind;
     let mut co2_median = MovingMedian::<f32, ENS160_MEDIAN_READINGS>::new();
-    let mut etoh_median = MovingMedian::<f32, ENS160_MEDIAN_READINGS>::new();
-    let mut co2_aqi_pairs: Vec<(f32, AirQualityIndex), ENS160_MEDIAN_READINGS> = Vec::new();
+    let mut voc_median = MovingMedian::<f32, ENS160_MEDIAN_READINGS>::new();
+    let mut aqi_readings: Vec<AirQualityIndex, ENS160_MEDIAN_READINGS> = Vec::new();
+    let mut valid = true;
+
+    for i in 0..samples {
+        if should_log_verbose() {
+            info!("ENS160 reading {} of {}", i + 1, samples);
+        }
 
-    for i in 0..ENS160_MEDIAN_READINGS {
-        info!("ENS160 reading {} of {}", i + 1, ENS160_MEDIAN_READINGS);
+        if let Some(aht21) = aht21.as_deref_mut().filter(|_| i > 0) {
+            refresh_ens160_compensation(aht21, ens160, loop_state)
+                .await
+                .unwrap_or_else(|e| warn!("Refreshing ENS160 compensation failed, keeping the previous one: {}", e));
+        }
 
-        // Wait for interrupt to ensure sensor has new data ready
-        int.wait_for_low().await;
-        info!("ENS160 interrupt received - data ready");
+        // Wait for the sensor to have new data ready
+        wait_for_ens160_data(ens160, int, &mut loop_state.ens160_polling).await?;
 
         let status = ens160.get_status().await.map_err(|_| "Failed to get ENS160 status")?;
-        info!("ENS160 status: {}", Debug2Format(&status));
+        if should_log_verbose() {
+            info!("ENS160 status: {}", Debug2Format(&status));
+        }
+        valid &= matches!(status.validity_flag(), Validity::NormalOperation);
 
         let eco2 = ens160.get_eco2().await.map_err(|_| "Failed to get eCO2")?;
-        let etoh = ens160.get_etoh().await.map_err(|_| "Failed to get ethanol")?;
+        let voc = match voc_kind {
+            VocKind::Ethanol => ens160.get_etoh().await.map_err(|_| "Failed to get ethanol")?,
+            VocKind::Tvoc => ens160.get_tvoc().await.map_err(|_| "Failed to get TVOC")?,
+        };
         let aq = ens160
             .get_airquality_index()
             .await
             .map_err(|_| "Failed to get Air Quality Index")?;
 
         let co2_value = f32::from(eco2.get_value());
-        let etoh_value = f32::from(etoh);
+        let voc_value = f32::from(voc);
 
-        info!(
-            "Reading {}: Air Quality Index: {}, eCO2: {} ppm, Ethanol: {} ppb",
-            i + 1,
-            Debug2Format(&aq),
-            co2_value,
-            etoh_value
-        );
+        if should_log_verbose() {
+            info!(
+                "Reading {}: Air Quality Index: {}, eCO2: {} ppm, {}: {} ppb",
+                i + 1,
+                Debug2Format(&aq),
+                co2_value,
+                voc_kind.label(),
+                voc_value
+            );
+        }
 
         co2_median.add_value(co2_value);
-        etoh_median.add_value(etoh_value);
-        let _ = co2_aqi_pairs.push((co2_value, aq));
+        voc_median.add_value(voc_value);
+        let _ = aqi_readings.push(aq);
     }
 
     let median_co2 = co2_median.median();
 
-    // Find the AQI that corresponds to the CO2 value closest to the median
-    let air_quality = co2_aqi_pairs
-        .iter()
-        .min_by(|(co2_a, _), (co2_b, _)| {
-            let diff_a = (co2_a - median_co2).abs();
-            let diff_b = (co2_b - median_co2).abs();
-            diff_a.partial_cmp(&diff_b).unwrap_or(core::cmp::Ordering::Equal)
-        })
-        .map(|(_, aqi)| *aqi)
-        .ok_or("No CO2-AQI pairs available")?;
+    // The AQI the ENS160 reported most often, a CO2 outlier does not drag it to an unrepresentative sample
+    if aqi_readings.is_empty() {
+        return Err("No AQI readings available");
+    }
+    let air_quality = most_frequent_aqi(&aqi_readings);
 
     let readings = Ens160Readings {
         co2: median_co2,
-        etoh: etoh_median.median(),
+        voc: voc_median.median(),
+        voc_kind,
         air_quality,
+        valid,
     };
 
-    info!(
-        "ENS160 median results - Air Quality Index: {}, eCO2: {} ppm, Ethanol: {} ppb",
-        Debug2Format(&readings.air_quality),
-        readings.co2,
-        readings.etoh
-    );
+    if should_log_verbose() {
+        info!(
+            "ENS160 median results - Air Quality Index: {}, eCO2: {} ppm, {}: {} ppb",
+            Debug2Format(&readings.air_quality),
+            readings.co2,
+            voc_kind.label(),
+            readings.voc
+        );
+    }
 
     Ok(readings)
 }
 
+/// Picks the air quality index reported most often, the last reading if several are equally frequent
+/// Returns `AirQualityIndex::Excellent` for no readings
+fn most_frequent_aqi(readings: &[AirQualityIndex]) -> AirQualityIndex {
+    let same = |a: &AirQualityIndex, b: &AirQualityIndex| core::mem::discriminant(a) == core::mem::discriminant(b);
+    let count = |aqi: &AirQualityIndex| readings.iter().filter(|other| same(aqi, other)).count();
+    let Some(last) = readings.last() else {
+        return AirQualityIndex::Excellent;
+    };
+
+    let max_count = readings.iter().map(count).max().unwrap_or(0);
+    let mut most_frequent = readings.iter().filter(|aqi| count(aqi) == max_count);
+    let Some(first) = most_frequent.next() else {
+        return *last;
+    };
+    if most_frequent.any(|aqi| !same(aqi, first)) {
+        *last
+    } else {
+        *first
+    }
+}
+
+/// Snapshot of the key ENS160 registers, used for diagnosing field issues
+/// such as stuck validity flags or a wrong part soldered onto the board
+pub struct Ens160RegisterDump {
+    /// Part ID as reported by the sensor (0x0160 for a genuine ENS160)
+    pub part_id: u16,
+    /// Raw device status register
+    pub status: Status,
+    /// Validity flag decoded from the status register
+    pub validity: Validity,
+    /// Equivalent CO2 in ppm
+    pub eco2: u16,
+    /// TVOC in ppb
+    pub tvoc: u16,
+    /// Ethanol in ppb
+    pub etoh: u16,
+    /// Air quality index
+    pub air_quality: AirQualityIndex,
+}
+
+impl Ens160RegisterDump {
+    /// Logs the register dump as a labeled block
+    pub fn log(&self) {
+        info!("---- ENS160 register dump ----");
+        info!("Part ID:     {=u16:#x}", self.part_id);
+        info!("Status:      {}", Debug2Format(&self.status));
+        info!("Validity:    {}", Debug2Format(&self.validity));
+        info!("eCO2:        {} ppm", self.eco2);
+        info!("TVOC:        {} ppb", self.tvoc);
+        info!("Ethanol:     {} ppb", self.etoh);
+        info!("AQI:         {}", Debug2Format(&self.air_quality));
+        info!("------------------------------");
+    }
+}
+
+/// Reads the key ENS160 registers into an `Ens160RegisterDump`
+///
+/// Only data registers are read, so the operating mode is left untouched. The firmware version is
+/// deliberately not included because the ENS160 only answers that command in Idle mode.
+#[cfg(target_os = "none")]
+pub async fn dump_ens160_registers(
+    ens160: &mut Ens160<I2cBusDevice, Delay>,
+) -> Result<Ens160RegisterDump, &'static str> {
+    let part_id = ens160.get_part_id().await.map_err(|_| "Failed to get ENS160 part ID")?;
+    let status = ens160.get_status().await.map_err(|_| "Failed to get ENS160 status")?;
+    let eco2 = ens160.get_eco2().await.map_err(|_| "Failed to get eCO2")?;
+    let tvoc = ens160.get_tvoc().await.map_err(|_| "Failed to get TVOC")?;
+    let etoh = ens160.get_etoh().await.map_err(|_| "Failed to get ethanol")?;
+    let air_quality = ens160
+        .get_airquality_index()
+        .await
+        .map_err(|_| "Failed to get Air Quality Index")?;
+
+    Ok(Ens160RegisterDump {
+        part_id,
+        validity: status.validity_flag(),
+        status,
+        eco2: eco2.get_value(),
+        tvoc,
+        etoh,
+        air_quality,
+    })
+}
+
+/// Reads and logs the ENS160 register dump, logging the failure instead if the dump cannot be read
+#[cfg(target_os = "none")]
+async fn log_ens160_registers(ens160: &mut Ens160<I2cBusDevice, Delay>) {
+    match dump_ens160_registers(ens160).await {
+        Ok(dump) => dump.log(),
+        Err(e) => info!("ENS160 register dump failed: {}", e),
+    }
+}
+
+/// Logs whether a failed ENS160 is disconnected or faulty, with the register dump of a faulty one
+#[cfg(target_os = "none")]
+async fn log_failed_ens160(ens160: &mut Ens160<I2cBusDevice, Delay>, i2c_bus: &'static I2cBus) {
+    let fault = diagnose_sensor_failure(
+        i2c_bus,
+        ENS160_ADDRESS,
+        SensorFault::Ens160Disconnected,
+        SensorFault::Ens160Error,
+    )
+    .await;
+    if fault == SensorFault::Ens160Error {
+        log_ens160_registers(ens160).await;
+    }
+}
+
+/// Checks an eCO2 value against the atmospheric baseline
+/// Returns the value to report and whether the raw value is suspect
+fn check_eco2(raw_co2: u16) -> (u16, bool) {
+    if raw_co2 >= ECO2_BASELINE {
+        return (raw_co2, false);
+    }
+
+    info!(
+        "ENS160 eCO2 of {} ppm is below the {} ppm baseline - flagging as suspect",
+        raw_co2, ECO2_BASELINE
+    );
+    let co2 = if CLAMP_SUB_BASELINE_ECO2 {
+        ECO2_BASELINE
+    } else {
+        raw_co2
+    };
+    (co2, true)
+}
+
 /// Set temperature and humidity compensation on ENS160 sensor
 /// Uses raw temperature (without offset correction) for accurate sensor compensation
+#[cfg(target_os = "none")]
 async fn set_ens160_compensation(
-    ens160: &mut Ens160<I2cDevice<'static, NoopRawMutex, I2c<'static, I2C0, Async>>, Delay>,
+    ens160: &mut Ens160<I2cBusDevice, Delay>,
     temp: f32,
     rh: f32,
 ) -> Result<(), &'static str> {
-    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let written_rh = COMPENSATION_HUMIDITY_ROUNDING.apply(rh);
     ens160
-        .set_temp_rh_comp(temp, rh as u16)
+        .set_temp_rh_comp(temp, written_rh)
         .await
         .map_err(|_| "Failed to set temperature and humidity compensation")?;
+    record_ens160_compensation(Ens160Compensation {
+        temperature: temp,
+        humidity: rh,
+        written_humidity: written_rh,
+        rounding: COMPENSATION_HUMIDITY_ROUNDING.name(),
+    })
+    .await;
+    if should_log_verbose() {
+        info!(
+            "ENS160 compensation written: {}°C, {}% ({} from {}%)",
+            temp,
+            written_rh,
+            COMPENSATION_HUMIDITY_ROUNDING.name(),
+            rh
+        );
+    }
     Timer::after_millis(100).await;
     Ok(())
 }
 
-/// Initialize both sensors and configure them for operation
-async fn initialize_sensors(
-    aht21_device: I2cDevice<'static, NoopRawMutex, I2c<'static, I2C0, Async>>,
-    ens160_device: I2cDevice<'static, NoopRawMutex, I2c<'static, I2C0, Async>>,
-    _ens160_int: &mut Input<'static>,
-) -> Result<
-    (
-        Aht20<I2cDevice<'static, NoopRawMutex, I2c<'static, I2C0, Async>>, Delay>,
-        Ens160<I2cDevice<'static, NoopRawMutex, I2c<'static, I2C0, Async>>, Delay>,
-    ),
-    &'static str,
-> {
-    let Some(aht21) = initialize_aht21(aht21_device).await else {
-        return Err("Failed to initialize AHT21");
-    };
-
-    let Some(mut ens160) = initialize_ens160(ens160_device).await else {
-        return Err("Failed to initialize ENS160");
-    };
-
-    // Configure ENS160 interrupt pin
+/// Configure the ENS160 interrupt pin to signal new data
+#[cfg(target_os = "none")]
+async fn configure_ens160_interrupt(ens160: &mut Ens160<I2cBusDevice, Delay>) -> Result<(), &'static str> {
     match ens160
         .config_interrupt_pin(
             InterruptPinConfig::builder()
@@ -262,70 +1000,601 @@ async fn initialize_sensors(
     {
         Ok(val) => {
             info!("ENS160 interrupt pin configured successfully to {}", val);
+            Ok(())
         }
         Err(e) => {
             info!("Failed to configure ENS160 interrupt pin: {}", Debug2Format(&e));
-            return Err("Failed to configure ENS160 interrupt pin");
+            record_i2c_error(BusDevice::Ens160).await;
+            Err("Failed to configure ENS160 interrupt pin")
         }
     }
+}
+
+/// Reset the ENS160 so it restarts its baseline in the current air, which should be fresh air (~420 ppm)
+/// The ENS160 does not expose its baseline registers, a reset is the only way to make it re-learn the baseline.
+/// Readings are invalid during the following warmup and unstable for a while after.
+#[cfg(target_os = "none")]
+async fn force_ens160_fresh_air_baseline(ens160: &mut Ens160<I2cBusDevice, Delay>) -> Result<(), &'static str> {
+    info!("Resetting ENS160 to force a fresh air baseline");
+    if ens160.set_operation_mode(OperationMode::Reset).await.is_err() {
+        record_i2c_error(BusDevice::Ens160).await;
+        return Err("Failed to reset ENS160");
+    }
+    Timer::after_millis(10).await;
+    if ens160.initialize().await.is_err() {
+        record_i2c_error(BusDevice::Ens160).await;
+        return Err("Failed to initialize ENS160 after reset");
+    }
+    // The reset cleared the interrupt configuration
+    configure_ens160_interrupt(ens160).await?;
+    record_ens160_validity(false).await;
+    Ok(())
+}
+
+/// Initialize both sensors and configure them for operation
+/// Each attempt gets fresh devices on the shared bus, as a failed initialization consumes them
+#[cfg(target_os = "none")]
+async fn initialize_sensors(
+    i2c_bus: &'static I2cBus,
+    _ens160_int: &mut Input<'static>,
+) -> Result<(Aht20<I2cBusDevice, Delay>, Ens160<I2cBusDevice, Delay>), SensorFault> {
+    let Some(aht21) = initialize_aht21(I2cDevice::new(i2c_bus)).await else {
+        record_i2c_error(BusDevice::Aht21).await;
+        return Err(diagnose_sensor_failure(
+            i2c_bus,
+            AHT21_ADDRESS,
+            SensorFault::Aht21Disconnected,
+            SensorFault::Aht21Error,
+        )
+        .await);
+    };
+
+    let Some(mut ens160) = initialize_ens160(I2cDevice::new(i2c_bus)).await else {
+        record_i2c_error(BusDevice::Ens160).await;
+        return Err(diagnose_sensor_failure(
+            i2c_bus,
+            ENS160_ADDRESS,
+            SensorFault::Ens160Disconnected,
+            SensorFault::Ens160Error,
+        )
+        .await);
+    };
+
+    if let Err(e) = configure_ens160_interrupt(&mut ens160).await {
+        info!("{}", e);
+        return Err(SensorFault::Ens160Error);
+    }
 
     // ENS160 is initialized in Standard mode and remains in continuous operation
     // for reliable measurements and proper calibration
     info!("ENS160 configured for continuous operation in Standard mode");
+    log_ens160_registers(&mut ens160).await;
 
     Ok((aht21, ens160))
 }
 
+/// State carried from one iteration of the sensor reading loop to the next
+#[cfg(target_os = "none")]
+struct SensorLoopState {
+    /// Shared I2C bus, to probe a failing sensor for its presence
+    i2c_bus: &'static I2cBus,
+    /// Previous raw temperature in degrees Celsius, used for ENS160 compensation
+    prev_temp: f32,
+    /// Previous calibrated humidity in percentage, used for ENS160 compensation
+    prev_humidity: f32,
+    /// Adaptive humidity calibration
+    humidity_calibrator: HumidityCalibrator,
+    /// Whether the humidity calibrator learns from the readings, latched once `CALIBRATOR_WARMUP_GATE` opened
+    calibrator_learning: bool,
+    /// Raw temperature of the previous AHT21 reading, to tell when it stabilized
+    last_raw_temperature: Option<f32>,
+    /// Whether the humidity calibrator was in a rapid change period after the previous AHT21 reading
+    in_rapid_change: bool,
+    /// Consecutive AHT21 read failures, used to decide when to soft-reset it
+    aht21_failures: u8,
+    /// Temperature and humidity compensation last written to the ENS160
+    last_compensation: Option<(f32, f32)>,
+    /// Number of upcoming readings to flag as settling
+    settling_readings_left: u8,
+    /// Whether the ENS160 status register is polled instead of waiting for its interrupt
+    ens160_polling: bool,
+    /// Whether only the AHT21 is read while the ENS160 sleeps, see `LOW_BATTERY_SENSING_THRESHOLD`
+    reduced_sensing: bool,
+    /// Whether a reading of both sensors succeeded, reduced sensing only starts after one
+    had_full_reading: bool,
+    /// User settings (temperature offset, CO2 correction), refreshed at the start of each iteration
+    settings: Settings,
+    /// Results of the current cycle for the health summary line
+    #[cfg(feature = "health-summary")]
+    health: CycleHealth,
+}
+
+#[cfg(target_os = "none")]
+impl SensorLoopState {
+    /// Creates the initial loop state with default compensation values
+    const fn new(i2c_bus: &'static I2cBus) -> Self {
+        Self {
+            i2c_bus,
+            prev_temp: 25.0,     // Default raw temperature (without offset)
+            prev_humidity: 50.0, // Default humidity
+            humidity_calibrator: HumidityCalibrator::new(),
+            calibrator_learning: matches!(CALIBRATOR_WARMUP_GATE, CalibratorWarmupGate::Immediate),
+            last_raw_temperature: None,
+            in_rapid_change: false,
+            aht21_failures: 0,
+            last_compensation: None,
+            settling_readings_left: 0,
+            ens160_polling: matches!(DATA_READY_SOURCE, DataReadySource::Polling),
+            reduced_sensing: false,
+            had_full_reading: false,
+            settings: Settings::new(),
+            #[cfg(feature = "health-summary")]
+            health: CycleHealth::new(0),
+        }
+    }
+
+    /// Prepares an iteration, picking up settings changed through the serial console
+    async fn start_iteration(&mut self) {
+        self.settings = SYSTEM_STATE.lock().await.get_settings();
+        self.humidity_calibrator.set_config(self.settings.humidity_calibration);
+        #[cfg(feature = "health-summary")]
+        {
+            self.health = CycleHealth::new(sensor_i2c_errors().await);
+        }
+    }
+
+    /// Records newly written compensation values, starting a settling period if they changed a lot
+    fn record_compensation(&mut self, temp: f32, rh: f32) {
+        if let Some((last_temp, last_rh)) = self.last_compensation {
+            let temp_change = (temp - last_temp).abs();
+            let rh_change = (rh - last_rh).abs();
+            if temp_change >= COMPENSATION_TEMPERATURE_CHANGE_THRESHOLD
+                || rh_change >= COMPENSATION_HUMIDITY_CHANGE_THRESHOLD
+            {
+                info!(
+                    "ENS160 compensation changed by {}°C / {}% - flagging next {} readings as settling",
+                    temp_change, rh_change, SETTLING_READINGS
+                );
+                self.settling_readings_left = SETTLING_READINGS;
+            }
+        }
+        self.last_compensation = Some((temp, rh));
+    }
+
+    /// Opens the humidity calibrator learning once the conditions of `CALIBRATOR_WARMUP_GATE` are met
+    /// The reading that opens it is not learned from yet, the next one is
+    fn update_calibrator_gate(&mut self, raw_temperature: Option<f32>, ens160_valid: bool) {
+        if self.calibrator_learning {
+            return;
+        }
+        self.calibrator_learning = match CALIBRATOR_WARMUP_GATE {
+            CalibratorWarmupGate::Immediate => true,
+            CalibratorWarmupGate::Ens160Valid => ens160_valid,
+            CalibratorWarmupGate::StableTemperature => {
+                let previous = self.last_raw_temperature;
+                if raw_temperature.is_some() {
+                    self.last_raw_temperature = raw_temperature;
+                }
+                previous
+                    .zip(raw_temperature)
+                    .is_some_and(|(previous, current)| (current - previous).abs() < STABLE_TEMPERATURE_CHANGE)
+            }
+        };
+        if self.calibrator_learning {
+            info!("Humidity calibrator warmup gate open, learning from the next reading");
+        }
+    }
+
+    /// Returns whether the current reading is settling, counting down the settling period
+    const fn take_settling(&mut self) -> bool {
+        if self.settling_readings_left > 0 {
+            self.settling_readings_left -= 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Read the AHT21 and keep its values for the next ENS160 compensation
+#[cfg(target_os = "none")]
+async fn read_aht21_for_compensation(
+    aht21: &mut Aht20<I2cBusDevice, Delay>,
+    loop_state: &mut SensorLoopState,
+) -> Result<Aht21Readings, &'static str> {
+    let aht21_result = read_aht21_with_recovery(
+        loop_state.i2c_bus,
+        aht21,
+        &mut loop_state.humidity_calibrator,
+        loop_state.calibrator_learning,
+        loop_state.settings.temperature_offset,
+        loop_state.settings.user_humidity_offset,
+        &mut loop_state.aht21_failures,
+    )
+    .await;
+    #[cfg(feature = "health-summary")]
+    {
+        loop_state.health.aht21_ok = Some(aht21_result.is_ok());
+    }
+    report_substep(aht21_result.is_ok()).await;
+
+    // Tell the orchestrator when a rapid change starts, it can reset the smoothing of the display values
+    let in_rapid_change = loop_state.humidity_calibrator.in_rapid_change();
+    if in_rapid_change && !loop_state.in_rapid_change {
+        send_event(Event::EnvironmentChanged).await;
+    }
+    loop_state.in_rapid_change = in_rapid_change;
+    if let Ok(ref aht21_readings) = aht21_result {
+        loop_state.prev_temp = aht21_readings.raw_temperature; // Use raw temperature for ENS160 compensation
+        loop_state.prev_humidity = aht21_readings.calibrated_humidity; // Use calibrated humidity
+    }
+    aht21_result
+}
+
+/// Write the latest known temperature and humidity to the ENS160 compensation registers
+#[cfg(target_os = "none")]
+async fn apply_ens160_compensation(
+    ens160: &mut Ens160<I2cBusDevice, Delay>,
+    loop_state: &mut SensorLoopState,
+) -> Result<(), &'static str> {
+    if let Err(e) = set_ens160_compensation(ens160, loop_state.prev_temp, loop_state.prev_humidity).await {
+        record_i2c_error(BusDevice::Ens160).await;
+        report_substep(false).await;
+        return Err(e);
+    }
+    report_substep(true).await;
+    loop_state.record_compensation(loop_state.prev_temp, loop_state.prev_humidity);
+    Ok(())
+}
+
+/// Re-reads the AHT21 and writes its values to the ENS160 compensation, in between the ENS160 median samples
+/// The humidity calibrator only corrects the humidity, it learns from one reading per iteration
+#[cfg(target_os = "none")]
+async fn refresh_ens160_compensation(
+    aht21: &mut Aht20<I2cBusDevice, Delay>,
+    ens160: &mut Ens160<I2cBusDevice, Delay>,
+    loop_state: &mut SensorLoopState,
+) -> Result<(), &'static str> {
+    let Ok((hum, temp)) = aht21.read().await else {
+        record_i2c_error(BusDevice::Aht21).await;
+        return Err("Failed to read AHT21 sensor");
+    };
+    let user_humidity_offset = loop_state
+        .settings
+        .user_humidity_offset
+        .clamp(-MAX_USER_HUMIDITY_OFFSET, MAX_USER_HUMIDITY_OFFSET);
+    let calibrated_rh = loop_state
+        .humidity_calibrator
+        .calibrate_humidity(temp.celsius(), hum.rh());
+
+    loop_state.prev_temp = temp.celsius();
+    loop_state.prev_humidity = (calibrated_rh + user_humidity_offset).clamp(0.0, 100.0);
+    apply_ens160_compensation(ens160, loop_state).await
+}
+
+/// Requests the sensor task to reset the ENS160 baseline to the current air, which should be fresh air (~420 ppm)
+pub fn request_fresh_air_baseline() {
+    SENSOR_COMMAND_SIGNAL.signal(SensorCommand::FreshAirBaseline);
+}
+
+/// Requests the sensor task to put the ENS160 into deep sleep and stop reading for storage mode
+pub fn request_storage_mode() {
+    SENSOR_COMMAND_SIGNAL.signal(SensorCommand::StorageMode);
+}
+
+/// Requests the sensor task to read the ENS160 registers and waits for the dump
+/// The sensor task answers in between readings and then waits on for the next one. While the power profile lets the
+/// ENS160 sleep between readings its data registers hold the last reading
+pub async fn request_ens160_register_dump() -> Result<Ens160RegisterDump, &'static str> {
+    ENS160_DUMP_SIGNAL.reset();
+    SENSOR_COMMAND_SIGNAL.signal(SensorCommand::DumpEns160Registers);
+    with_timeout(ENS160_DUMP_TIMEOUT, ENS160_DUMP_SIGNAL.wait())
+        .await
+        .map_err(|_| "No answer from the sensor task")?
+}
+
+/// Updates the reduced sensing trigger from a battery event, `None` while charging
+/// The sensor task switches the sensing mode before its next reading
+pub fn update_battery_for_sensing(level: Option<u8>) {
+    let Some(threshold) = LOW_BATTERY_SENSING_THRESHOLD else {
+        return;
+    };
+    let low = match level {
+        None => false,
+        Some(level) if LOW_BATTERY.load(Ordering::Relaxed) => {
+            level < threshold.saturating_add(LOW_BATTERY_SENSING_HYSTERESIS)
+        }
+        Some(level) => level <= threshold,
+    };
+    LOW_BATTERY.store(low, Ordering::Relaxed);
+}
+
+/// Switches between full and reduced sensing when the battery crossed `LOW_BATTERY_SENSING_THRESHOLD`
+/// Reduced sensing only starts after a full reading, so the display has CO2 values to keep showing. On the way back
+/// the ENS160 is warmed up again before the next reading
+#[cfg(target_os = "none")]
+async fn update_sensing_mode(ens160: &mut Ens160<I2cBusDevice, Delay>, loop_state: &mut SensorLoopState) {
+    let reduced = LOW_BATTERY.load(Ordering::Relaxed) && loop_state.had_full_reading;
+    if reduced == loop_state.reduced_sensing {
+        return;
+    }
+    loop_state.reduced_sensing = reduced;
+
+    if reduced {
+        info!("Battery low, reading only the AHT21 until it recovers");
+        set_ens160_warm(false);
+        if let Err(e) = ens160.set_operation_mode(OperationMode::Sleep).await {
+            info!(
+                "Failed to put ENS160 to sleep for reduced sensing: {}",
+                Debug2Format(&e)
+            );
+            record_i2c_error(BusDevice::Ens160).await;
+        }
+        send_event(Event::ReducedSensing(true)).await;
+    } else {
+        info!(
+            "Battery recovered, waiting for ENS160 warmup period of {} seconds",
+            WARMUP_TIME
+        );
+        if let Err(e) = ens160.set_operation_mode(OperationMode::Standard).await {
+            info!("Failed to wake ENS160: {}", Debug2Format(&e));
+            record_i2c_error(BusDevice::Ens160).await;
+        }
+        Timer::after_secs(WARMUP_TIME).await;
+        set_ens160_warm(true);
+        send_event(Event::ReducedSensing(false)).await;
+    }
+}
+
+/// Marks whether the ENS160 is warmed up and in continuous operation, read back after a reboot
+#[cfg(target_os = "none")]
+fn set_ens160_warm(warm: bool) {
+    let marker = if warm { ENS160_WARM_MARKER } else { 0 };
+    pac::WATCHDOG.scratch(ENS160_WARM_SCRATCH).write_value(marker);
+}
+
+/// Whether the ENS160 was marked warmed up, i.e. this is a reboot without a loss of power since the warmup
+#[cfg(target_os = "none")]
+fn is_ens160_warm() -> bool {
+    pac::WATCHDOG.scratch(ENS160_WARM_SCRATCH).read() == ENS160_WARM_MARKER
+}
+
+/// Waits for the ENS160 warmup at startup, shortened after a warm reboot on USB power
+/// Readings the ENS160 itself still considers warming up are flagged by its validity
+/// The display counts down the seconds left, after a warm reboot on battery it starts over with the rest of the full
+/// warmup once the shortened one has passed
+async fn wait_for_startup_warmup(warm_reboot: bool) {
+    if !(SHORTEN_WARMUP_ON_WARM_REBOOT && warm_reboot) {
+        info!(
+            "Cold start, waiting for ENS160 warmup period of {} seconds",
+            WARMUP_TIME
+        );
+        count_down_warmup(WARMUP_TIME).await;
+        return;
+    }
+
+    // The VSYS task measures right at startup, by now the power source is known
+    count_down_warmup(WARM_REBOOT_WARMUP_TIME).await;
+    if SYSTEM_STATE.lock().await.get_charging_since().is_some() {
+        info!(
+            "Warm reboot on USB power, shortened ENS160 warmup of {} seconds",
+            WARM_REBOOT_WARMUP_TIME
+        );
+    } else {
+        info!(
+            "Warm reboot on battery, waiting for the full ENS160 warmup period of {} seconds",
+            WARMUP_TIME
+        );
+        count_down_warmup(WARMUP_TIME - WARM_REBOOT_WARMUP_TIME).await;
+    }
+}
+
+/// Waits out `secs` of the startup warmup, sending the seconds left to the display every second
+async fn count_down_warmup(secs: u64) {
+    for remaining in (1..=secs).rev() {
+        send_event(Event::WarmupProgress(u16::try_from(remaining).unwrap_or(u16::MAX))).await;
+        Timer::after_secs(1).await;
+    }
+}
+
+/// Xorshift pseudo random number generator for the read interval jitter
+#[cfg(target_os = "none")]
+struct JitterRng {
+    /// Generator state, never zero
+    state: u32,
+}
+
+#[cfg(target_os = "none")]
+impl JitterRng {
+    /// Seeds the generator from the random bit of the ring oscillator, which samples its jitter
+    fn new() -> Self {
+        let mut seed = 0u32;
+        for _ in 0..32 {
+            seed = (seed << 1) | u32::from(pac::ROSC.randombit().read().randombit());
+        }
+        // Xorshift gets stuck on zero
+        Self { state: seed.max(1) }
+    }
+
+    /// Gets the next pseudo random number
+    const fn next(&mut self) -> u32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state
+    }
+
+    /// Gets the read interval in seconds shifted by a random jitter of up to `READ_INTERVAL_JITTER` either way
+    fn jittered_interval(&mut self, read_interval: u64) -> u64 {
+        if READ_INTERVAL_JITTER == 0 {
+            return read_interval;
+        }
+        let shift = u64::from(self.next()) % (2 * READ_INTERVAL_JITTER + 1);
+        (read_interval + shift).saturating_sub(READ_INTERVAL_JITTER)
+    }
+}
+
+/// Put the ENS160 into deep sleep and wake it again a warmup period ahead of the next reading
+/// If the ENS160 cannot be put to sleep it simply keeps running until the next reading
+#[cfg(target_os = "none")]
+async fn sleep_ens160_until_next_reading(ens160: &mut Ens160<I2cBusDevice, Delay>, read_interval: u64) {
+    set_ens160_warm(false);
+    match ens160.set_operation_mode(OperationMode::Sleep).await {
+        Ok(()) => info!("ENS160 sleeping until {} seconds before the next reading", WARMUP_TIME),
+        Err(e) => {
+            info!("Failed to put ENS160 to sleep (continuing): {}", Debug2Format(&e));
+            record_i2c_error(BusDevice::Ens160).await;
+        }
+    }
+    Timer::after_secs(read_interval.saturating_sub(WARMUP_TIME)).await;
+
+    if let Err(e) = ens160.set_operation_mode(OperationMode::Standard).await {
+        info!("Failed to wake ENS160: {}", Debug2Format(&e));
+        record_i2c_error(BusDevice::Ens160).await;
+    }
+    Timer::after_secs(WARMUP_TIME).await;
+    set_ens160_warm(true);
+}
+
+/// Waits for the next reading until `wait` finishes, unless a command comes in first
+/// A register dump is answered without interrupting the wait, so a sleeping ENS160 is not woken or put through its
+/// warmup again. While it sleeps (`ens160` is `None`) the dump is answered with an error instead
+#[cfg(target_os = "none")]
+async fn wait_for_next_reading(
+    wait: impl Future<Output = ()>,
+    mut ens160: Option<&mut Ens160<I2cBusDevice, Delay>>,
+) -> Option<SensorCommand> {
+    let mut wait = pin!(wait);
+    loop {
+        match select(&mut wait, SENSOR_COMMAND_SIGNAL.wait()).await {
+            Either::First(()) => return None,
+            Either::Second(SensorCommand::DumpEns160Registers) => {
+                let dump = match ens160.as_deref_mut() {
+                    Some(ens160) => dump_ens160_registers(ens160).await,
+                    None => Err("ENS160 is asleep until shortly before the next reading"),
+                };
+                ENS160_DUMP_SIGNAL.signal(dump);
+            }
+            Either::Second(command) => return Some(command),
+        }
+    }
+}
+
+/// Reports the outcome of a step of the iteration to the watchdog with `HealthReportGranularity::Substep`
+async fn report_substep(succeeded: bool) {
+    match (HEALTH_REPORT_GRANULARITY, succeeded) {
+        (HealthReportGranularity::Iteration, _) => {}
+        (HealthReportGranularity::Substep, true) => report_task_success(TaskId::Sensor).await,
+        (HealthReportGranularity::Substep, false) => report_task_failure(TaskId::Sensor).await,
+    }
+}
+
 /// Execute one iteration of the sensor reading loop
-/// ENS160 operates continuously in Standard mode for reliable measurements
+/// ENS160 operates in Standard mode for the reading, continuously unless the power profile lets it sleep
+#[cfg(target_os = "none")]
 async fn handle_sensor_iteration(
-    aht21: &mut Aht20<I2cDevice<'static, NoopRawMutex, I2c<'static, I2C0, Async>>, Delay>,
-    ens160: &mut Ens160<I2cDevice<'static, NoopRawMutex, I2c<'static, I2C0, Async>>, Delay>,
+    aht21: &mut Aht20<I2cBusDevice, Delay>,
+    ens160: &mut Ens160<I2cBusDevice, Delay>,
     ens160_int: &mut Input<'static>,
-    prev_temp: &mut f32,
-    prev_humidity: &mut f32,
-    humidity_calibrator: &mut HumidityCalibrator,
+    loop_state: &mut SensorLoopState,
 ) -> bool {
-    // Read AHT21 data first to get current environmental conditions
-    let aht21_result = read_aht21(aht21, humidity_calibrator).await;
-    if let Ok(ref aht21_readings) = aht21_result {
-        *prev_temp = aht21_readings.raw_temperature; // Use raw temperature for ENS160 compensation
-        *prev_humidity = aht21_readings.calibrated_humidity; // Use calibrated humidity
-    }
+    loop_state.start_iteration().await;
 
-    // Set temperature and humidity compensation using latest readings
-    if let Err(e) = set_ens160_compensation(ens160, *prev_temp, *prev_humidity).await {
-        info!("ENS160 compensation setting failed: {}", e);
-        return false; // Indicate failure
-    }
+    let (aht21_result, ens160_result) = match READ_ORDER {
+        ReadOrder::Aht21First => {
+            // Read AHT21 data first to get current environmental conditions
+            let aht21_result = read_aht21_for_compensation(aht21, loop_state).await;
+
+            // Set temperature and humidity compensation using latest readings
+            if let Err(e) = apply_ens160_compensation(ens160, loop_state).await {
+                info!("ENS160 compensation setting failed: {}", e);
+                return false; // Indicate failure
+            }
+
+            let ens160_result = read_ens160(
+                ens160,
+                ens160_int,
+                loop_state,
+                COMPENSATE_EACH_ENS160_SAMPLE.then_some(&mut *aht21),
+            )
+            .await;
+
+            (aht21_result, ens160_result)
+        }
+        ReadOrder::Ens160First => {
+            // Compensate with the previous cycle's readings, then read the ENS160 right away
+            if let Err(e) = apply_ens160_compensation(ens160, loop_state).await {
+                info!("ENS160 compensation setting failed: {}", e);
+                return false; // Indicate failure
+            }
+            let ens160_result = read_ens160(
+                ens160,
+                ens160_int,
+                loop_state,
+                COMPENSATE_EACH_ENS160_SAMPLE.then_some(&mut *aht21),
+            )
+            .await;
 
-    let ens160_result = read_ens160(ens160, ens160_int).await;
+            (read_aht21_for_compensation(aht21, loop_state).await, ens160_result)
+        }
+    };
+    match ens160_result {
+        Ok(ref ens160_readings) => record_ens160_validity(ens160_readings.valid).await,
+        Err(_) => record_i2c_error(BusDevice::Ens160).await,
+    }
+    report_substep(ens160_result.is_ok()).await;
+    loop_state.update_calibrator_gate(
+        aht21_result.as_ref().ok().map(|readings| readings.raw_temperature),
+        ens160_result.as_ref().is_ok_and(|readings| readings.valid),
+    );
+    #[cfg(feature = "health-summary")]
+    {
+        loop_state.health.ens160_ok = Some(ens160_result.is_ok());
+        loop_state.health.ens160_valid = ens160_result.as_ref().is_ok_and(|readings| readings.valid);
+        loop_state.health.samples = ens160_samples(&loop_state.settings);
+    }
 
     // Process readings
     match (ens160_result, aht21_result) {
         (Ok(ens160_readings), Ok(aht21_readings)) => {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let (co2, co2_suspect) = check_eco2(ens160_readings.co2 as u16);
+            let co2 = loop_state.settings.correct_co2(co2);
+            let settling = loop_state.take_settling();
+            #[cfg(feature = "health-summary")]
+            {
+                loop_state.health.quality =
+                    Some(ReadingQuality::classify(co2_suspect, settling, ens160_readings.valid));
+            }
             send_event(Event::SensorData {
                 temperature: aht21_readings.display_temperature, // Use display temperature for UI
                 raw_temperature: aht21_readings.raw_temperature, // Send raw temperature
                 humidity: aht21_readings.calibrated_humidity,    // Use calibrated humidity for UI
                 raw_humidity: aht21_readings.raw_humidity,       // Send raw humidity
+                dew_point: aht21_readings.dew_point,
+                co2,
+                co2_suspect,
                 #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-                co2: ens160_readings.co2 as u16,
-                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-                etoh: ens160_readings.etoh as u16,
+                voc: ens160_readings.voc as u16,
+                voc_kind: ens160_readings.voc_kind,
                 air_quality: ens160_readings.air_quality,
+                settling,
             })
             .await;
 
-            info!("Sensor task: successful");
+            if should_log_verbose() {
+                info!("Sensor task: successful");
+            }
             true // Indicate success
         }
         (Err(ens160_err), Err(aht21_err)) => {
             info!("Both sensors failed - ENS160: {}, AHT21: {}", ens160_err, aht21_err);
+            log_failed_ens160(ens160, loop_state.i2c_bus).await;
             false // Indicate failure
         }
         (Err(ens160_err), Ok(_)) => {
             info!("ENS160 reading failed: {}", ens160_err);
+            log_failed_ens160(ens160, loop_state.i2c_bus).await;
             false // Indicate failure
         }
         (Ok(_), Err(aht21_err)) => {
@@ -335,57 +1604,251 @@ async fn handle_sensor_iteration(
     }
 }
 
+/// Execute one iteration with reduced sensing, only the AHT21 is read while the ENS160 sleeps
+#[cfg(target_os = "none")]
+async fn handle_reduced_sensor_iteration(
+    aht21: &mut Aht20<I2cBusDevice, Delay>,
+    loop_state: &mut SensorLoopState,
+) -> bool {
+    loop_state.start_iteration().await;
+
+    match read_aht21_for_compensation(aht21, loop_state).await {
+        Ok(aht21_readings) => {
+            send_event(Event::ClimateData {
+                temperature: aht21_readings.display_temperature,
+                raw_temperature: aht21_readings.raw_temperature,
+                humidity: aht21_readings.calibrated_humidity,
+                raw_humidity: aht21_readings.raw_humidity,
+                dew_point: aht21_readings.dew_point,
+            })
+            .await;
+            true
+        }
+        Err(e) => {
+            info!("AHT21 reading failed: {}", e);
+            false
+        }
+    }
+}
+
+/// Initialize both sensors, retrying with backoff and then in standby until it succeeds
+/// Rebooting would not help genuinely disconnected sensors, so the standby keeps the display and battery
+/// monitoring running, shows a sensor error and keeps the watchdog fed while retrying
+#[cfg(target_os = "none")]
+async fn initialize_sensors_with_retry(
+    i2c_bus: &'static I2cBus,
+    ens160_int: &mut Input<'static>,
+) -> (Aht20<I2cBusDevice, Delay>, Ens160<I2cBusDevice, Delay>) {
+    let mut attempt: u8 = 0;
+    let mut backoff = SENSOR_INIT_BACKOFF;
+
+    loop {
+        attempt = attempt.saturating_add(1);
+        let fault = match initialize_sensors(i2c_bus, ens160_int).await {
+            Ok(sensors) => {
+                if attempt > SENSOR_INIT_ATTEMPTS {
+                    info!("Sensors recovered, leaving sensor error standby");
+                    send_event(Event::SensorError(None)).await;
+                }
+                return sensors;
+            }
+            Err(fault) => {
+                info!("Sensor initialization attempt {} failed: {}", attempt, fault);
+                fault
+            }
+        };
+
+        if attempt < SENSOR_INIT_ATTEMPTS {
+            Timer::after(backoff).await;
+            backoff = backoff * 2;
+        } else {
+            if attempt == SENSOR_INIT_ATTEMPTS {
+                error!(
+                    "Sensor initialization failed {} times, entering sensor error standby",
+                    SENSOR_INIT_ATTEMPTS
+                );
+            }
+            // The screen follows the fault, e.g. from disconnected to faulty once a connector is pushed back in
+            send_event(Event::SensorError(Some(fault))).await;
+            // There is no ENS160 to put to sleep, storage mode only has to stop the retries
+            storage_mode::park_if_active("Sensor").await;
+            // The standby is a healthy state, a reboot would only end up here again
+            report_task_success(TaskId::Sensor).await;
+            Timer::after(SENSOR_STANDBY_RETRY_INTERVAL).await;
+        }
+    }
+}
+
+#[cfg(target_os = "none")]
 #[embassy_executor::task]
-pub async fn sensor_task(
-    aht21: I2cDevice<'static, NoopRawMutex, I2c<'static, I2C0, Async>>,
-    ens160: I2cDevice<'static, NoopRawMutex, I2c<'static, I2C0, Async>>,
-    mut ens160_int: Input<'static>,
-) {
+pub async fn sensor_task(i2c_bus: &'static I2cBus, mut ens160_int: Input<'static>) {
     let task_id = TaskId::Sensor;
 
+    // Only a reboot that kept the ENS160 powered finds it still marked warm
+    let warm_reboot = is_ens160_warm();
+    set_ens160_warm(false);
+
     // Initialize both sensors
-    let (mut aht21, mut ens160) = match initialize_sensors(aht21, ens160, &mut ens160_int).await {
-        Ok(sensors) => sensors,
-        Err(e) => {
-            info!("Sensor initialization failed: {}", e);
-            report_task_failure(task_id).await;
-            return;
-        }
-    };
+    let (mut aht21, mut ens160) = initialize_sensors_with_retry(i2c_bus, &mut ens160_int).await;
 
-    // Store previous AHT21 readings for ENS160 compensation
-    let mut prev_temp = 25.0; // Default raw temperature (without offset)
-    let mut prev_humidity = 50.0; // Default humidity
+    // A freshly initialized ENS160 starts a new valid period once it reports normal operation
+    record_ens160_validity(false).await;
 
-    // Initialize humidity calibrator
-    let mut humidity_calibrator = HumidityCalibrator::new();
+    // Previous readings for ENS160 compensation, humidity calibrator and recovery bookkeeping
+    let mut loop_state = SensorLoopState::new(i2c_bus);
+    let mut jitter_rng = JitterRng::new();
 
     info!("Sensor task initialized successfully with humidity calibration");
     report_task_success(task_id).await;
 
     // Wait for ENS160 warmup period before starting readings
-    info!("Waiting for ENS160 warmup period of {} seconds", WARMUP_TIME);
-    Timer::after_secs(WARMUP_TIME).await;
+    wait_for_startup_warmup(warm_reboot).await;
+    set_ens160_warm(true);
 
     loop {
+        update_sensing_mode(&mut ens160, &mut loop_state).await;
+
         // Execute one iteration of the sensor reading loop
-        let success = handle_sensor_iteration(
-            &mut aht21,
-            &mut ens160,
-            &mut ens160_int,
-            &mut prev_temp,
-            &mut prev_humidity,
-            &mut humidity_calibrator,
-        )
-        .await;
+        #[cfg(feature = "debug-serial")]
+        let iteration_start = Instant::now();
+        let success = if loop_state.reduced_sensing {
+            handle_reduced_sensor_iteration(&mut aht21, &mut loop_state).await
+        } else {
+            let success = handle_sensor_iteration(&mut aht21, &mut ens160, &mut ens160_int, &mut loop_state).await;
+            loop_state.had_full_reading |= success;
+            success
+        };
+        #[cfg(feature = "debug-serial")]
+        record_loop_time(task_id, iteration_start).await;
+        #[cfg(feature = "health-summary")]
+        send_health_summary(&loop_state.health.summary_line(sensor_i2c_errors().await));
 
         if success {
             report_task_success(task_id).await;
         } else {
             report_task_failure(task_id).await;
+            get_diagnostics().await.log();
+        }
+
+        // Wait for the next reading, the interval is looked up anew every time as it may change at runtime, unless a
+        // command comes in
+        let (params, read_interval) = {
+            let state = SYSTEM_STATE.lock().await;
+            (state.get_settings().power_profile.params(), state.get_read_interval())
+        };
+        let read_interval = jitter_rng.jittered_interval(read_interval);
+        let next_reading = Instant::now() + Duration::from_secs(read_interval);
+        // A sleeping ENS160 is woken a full warmup ahead, a shorter interval keeps it running. With reduced sensing
+        // it is asleep already
+        let ens160_sleep = params.ens160_sleep && read_interval > WARMUP_TIME && !loop_state.reduced_sensing;
+        let command = if ens160_sleep {
+            let remaining = next_reading.saturating_duration_since(Instant::now()).as_secs();
+            wait_for_next_reading(sleep_ens160_until_next_reading(&mut ens160, remaining), None).await
+        } else {
+            wait_for_next_reading(Timer::at(next_reading), Some(&mut ens160)).await
+        };
+        let Some(command) = command else {
+            continue;
+        };
+        match command {
+            SensorCommand::FreshAirBaseline => {
+                set_ens160_warm(false);
+                match force_ens160_fresh_air_baseline(&mut ens160).await {
+                    Ok(()) => loop_state.settling_readings_left = BASELINE_RESET_SETTLING_READINGS,
+                    Err(e) => info!("Forcing a fresh air baseline failed: {}", e),
+                }
+                info!("Waiting for ENS160 warmup period of {} seconds", WARMUP_TIME);
+                Timer::after_secs(WARMUP_TIME).await;
+                set_ens160_warm(true);
+                // The ENS160 is running again, reduced sensing puts it back to sleep if the battery is still low
+                loop_state.reduced_sensing = false;
+            }
+            SensorCommand::StorageMode => {
+                set_ens160_warm(false);
+                if let Err(e) = ens160.set_operation_mode(OperationMode::Sleep).await {
+                    info!("Failed to put ENS160 to sleep for storage mode: {}", Debug2Format(&e));
+                }
+                storage_mode::park("Sensor").await;
+            }
+            // Answered while waiting for the next reading
+            SensorCommand::DumpEns160Registers => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eco2_below_the_baseline_is_suspect() {
+        let reported = |raw_co2| {
+            if CLAMP_SUB_BASELINE_ECO2 {
+                ECO2_BASELINE
+            } else {
+                raw_co2
+            }
+        };
+        assert_eq!(check_eco2(ECO2_BASELINE - 1), (reported(ECO2_BASELINE - 1), true));
+        assert_eq!(check_eco2(0), (reported(0), true));
+        assert_eq!(check_eco2(ECO2_BASELINE), (ECO2_BASELINE, false));
+        assert_eq!(check_eco2(ECO2_BASELINE + 1), (ECO2_BASELINE + 1, false));
+        assert_eq!(check_eco2(u16::MAX), (u16::MAX, false));
+    }
+
+    /// Temperatures in degrees Celsius across the range of the Magnus coefficients, in steps of half a degree
+    fn magnus_temperatures() -> impl Iterator<Item = f32> {
+        (-90_i16..=120).map(|half_degrees| f32::from(half_degrees) / 2.0)
+    }
+
+    #[test]
+    fn dew_point_follows_the_magnus_formula() {
+        assert!((dew_point(20.0, 50.0) - 9.26).abs() < 0.01);
+        // Saturated air is at its dew point
+        assert!((dew_point(20.0, 100.0) - 20.0).abs() < 0.001);
+        // Dry air is bounded to the dew point of 0.1%
+        assert!((dew_point(20.0, 0.0) - dew_point(20.0, 0.1)).abs() < 0.001);
+
+        for temp_c in magnus_temperatures() {
+            for rh in [1.0_f32, 10.0, 30.0, 50.0, 70.0, 90.0] {
+                let gamma = (rh / 100.0).ln() + MAGNUS_B * temp_c / (MAGNUS_C + temp_c);
+                let expected = MAGNUS_C * gamma / (MAGNUS_B - gamma);
+                let error = dew_point(temp_c, rh) - expected;
+                assert!(error.abs() < 0.001, "dew point at {temp_c}°C and {rh}% off by {error}");
+            }
+        }
+    }
+
+    #[test]
+    fn absolute_humidity_matches_the_reference_values() {
+        for (temp_c, rh, expected) in [(20.0, 50.0, 8.6), (25.0, 60.0, 13.8), (0.0, 100.0, 4.85)] {
+            let humidity = absolute_humidity(temp_c, rh);
+            assert!(
+                (humidity - expected).abs() < 0.05,
+                "{humidity} g/m³ at {temp_c}°C and {rh}%, expected {expected} g/m³"
+            );
+        }
+        // Humidity outside 0% to 100% is bounded
+        assert!(absolute_humidity(20.0, -5.0).abs() < f32::EPSILON);
+        assert!((absolute_humidity(20.0, 120.0) - absolute_humidity(20.0, 100.0)).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn ln_is_accurate_over_the_humidity_range() {
+        // The dew point takes the logarithm of the relative humidity from 0.1% to 100%
+        for permille in 1_u16..=1000 {
+            let x = f32::from(permille) / 1000.0;
+            let error = ln(x) - x.ln();
+            assert!(error.abs() < 1e-5, "ln of {x} off by {error}");
         }
+    }
 
-        // Wait for the next reading interval (5 minutes)
-        Timer::after_secs(READ_INTERVAL).await;
+    #[test]
+    fn exp_is_accurate_over_the_magnus_range() {
+        for temp_c in magnus_temperatures() {
+            let x = MAGNUS_B * temp_c / (MAGNUS_C + temp_c);
+            let error = exp(x) / x.exp() - 1.0;
+            assert!(error.abs() < 1e-5, "exp of {x} off by {error}");
+        }
     }
 }
@@ -0,0 +1,1298 @@
+//! Flash-backed storage for data that has to survive a reboot
+//!
+//! Everything is kept in a single blob in the last flash sector, which `memory.x` keeps free of program code.
+//! The blob is framed by a magic number, a layout version and a CRC. A blob that does not validate, e.g. after a
+//! firmware update changed the layout, is replaced by the safe defaults, and the boot screen and the serial console
+//! tell once that the settings were reset.
+//!
+//! Fields are only ever appended to the payload. A blob of an older version is upgraded by a chain of migrations, one
+//! per version, each appending the defaults of the fields its version added, so a firmware update keeps the
+//! configuration.
+//!
+//! User settings (`Settings`) are entered through the guided setup on the serial console on first boot and are
+//! written right away, they change rarely.
+//!
+//! Flash sectors only survive a limited number of erase cycles, so data that changes frequently
+//! (like the extremes or the display mode) is written by `persistence_task` at most once per
+//! `SNAPSHOT_INTERVAL` and only if it actually changed.
+//!
+//! The boot record is written once per boot to count it, and once more right before a watchdog reset to name the
+//! stale task, so the reason of a reset is still known after the power was lost in between.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use defmt::{error, info};
+#[cfg(target_os = "none")]
+use embassy_rp::{
+    Peri,
+    flash::{Blocking, ERASE_SIZE, Flash},
+    peripherals::FLASH,
+};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+use embassy_time::{Duration, Instant, Timer};
+
+use crate::{
+    event::{Event, send_event},
+    humidity_calibrator::{HumidityCalibratorConfig, HumidityClimate},
+    power_profile::PowerProfile,
+    storage_mode,
+    system_state::{DisplayMode, Extremes, SYSTEM_STATE, TemperatureUnit, VocKind},
+    watchdog::TaskId,
+};
+
+/// Total size of the flash, must match the `FLASH` region plus the reserved sector in `memory.x`
+const FLASH_SIZE: usize = 2 * 1024 * 1024;
+
+/// Size of a flash sector, the smallest unit that can be erased
+#[cfg(not(target_os = "none"))]
+const ERASE_SIZE: usize = 4096;
+
+/// The flash holding the storage sector
+#[cfg(target_os = "none")]
+type StorageFlash = Flash<'static, FLASH, Blocking, FLASH_SIZE>;
+
+/// The flash holding the storage sector, a RAM copy of it on the host
+#[cfg(not(target_os = "none"))]
+type StorageFlash = HostFlash;
+
+/// Offset of the storage sector from the start of flash
+#[allow(clippy::cast_possible_truncation)]
+const STORAGE_OFFSET: u32 = (FLASH_SIZE - ERASE_SIZE) as u32;
+
+/// Marks a sector that holds a blob written by this firmware ("AQM1")
+const MAGIC: u32 = 0x4151_4D31;
+
+/// Layout version of the payload, bump whenever the payload layout changes and add a migration to `MIGRATIONS`
+const VERSION: u16 = 9;
+
+/// Size of the header: magic, version and payload length
+const HEADER_SIZE: usize = 8;
+
+/// Size of the CRC trailing the payload
+const CRC_SIZE: usize = 4;
+
+/// Maximum payload size
+const PAYLOAD_CAPACITY: usize = 128;
+
+/// Size of the buffer holding a complete blob
+const BLOB_CAPACITY: usize = HEADER_SIZE + PAYLOAD_CAPACITY + CRC_SIZE;
+
+/// Minimum time between two snapshot writes to protect the flash from wear
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often the persistence task checks whether a snapshot is due
+const SNAPSHOT_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Largest user humidity offset in either direction, in percent RH
+pub const MAX_USER_HUMIDITY_OFFSET: f32 = 20.0;
+
+/// Number of CO2 correction points
+pub const CO2_CORRECTION_POINTS: usize = 2;
+
+/// A CO2 reading paired with the true value from a reference, used to correct the ENS160 eCO2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Co2CorrectionPoint {
+    /// eCO2 reported by the ENS160 in ppm
+    pub measured: u16,
+    /// True CO2 level in ppm, e.g. 420 ppm outdoors
+    pub reference: u16,
+}
+
+/// User configuration, entered through the guided setup on the serial console
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settings {
+    /// Whether the guided setup was completed or skipped
+    pub setup_done: bool,
+    /// Unit temperatures are displayed in
+    pub temperature_unit: TemperatureUnit,
+    /// Offset in degrees Celsius added to the raw AHT21 temperature for display
+    /// The sensor sits next to the heated ENS160 on the combined board and reads high
+    pub temperature_offset: f32,
+    /// Offset in percent RH added to the humidity, on top of the adaptive calibration if that is enabled
+    /// Derived from a reference hygrometer, limited to `MAX_USER_HUMIDITY_OFFSET`
+    pub user_humidity_offset: f32,
+    /// CO2 correction points, one point shifts the readings, two points also correct the slope
+    pub co2_correction: [Option<Co2CorrectionPoint>; CO2_CORRECTION_POINTS],
+    /// Quiet hours (start hour, end hour) during which night mode is on, `None` disables them
+    pub quiet_hours: Option<(u8, u8)>,
+    /// Power profile trading battery runtime against responsiveness
+    pub power_profile: PowerProfile,
+    /// Configuration of the humidity calibration
+    pub humidity_calibration: HumidityCalibratorConfig,
+    /// Which volatile organic compound figure of the ENS160 is displayed and reported
+    pub voc_kind: VocKind,
+}
+
+impl Settings {
+    /// Creates the default settings
+    pub const fn new() -> Self {
+        Self {
+            setup_done: false,
+            temperature_unit: TemperatureUnit::Celsius,
+            temperature_offset: -3.5,
+            user_humidity_offset: 0.0,
+            co2_correction: [None; CO2_CORRECTION_POINTS],
+            quiet_hours: Some((22, 7)),
+            power_profile: PowerProfile::Normal,
+            humidity_calibration: HumidityCalibratorConfig::new(),
+            voc_kind: VocKind::Ethanol,
+        }
+    }
+
+    /// Creates the settings used when the stored ones are corrupt or of an incompatible layout
+    /// These are the first boot defaults every consumer starts from: Celsius, the factory temperature offset, no
+    /// humidity offset and no CO2 correction, quiet hours 22-7, the normal power profile, the temperate adaptive
+    /// humidity calibration and ethanol. The guided setup runs again so the lost configuration can be re-entered.
+    pub const fn safe_defaults() -> Self {
+        Self::new()
+    }
+
+    /// Applies the CO2 correction points to an eCO2 reading
+    // `mul_add` is not available without std
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss, clippy::suboptimal_flops)]
+    pub fn correct_co2(&self, co2: u16) -> u16 {
+        let corrected = match self.co2_correction {
+            [None, None] => return co2,
+            [Some(point), None] | [None, Some(point)] => {
+                f32::from(co2) + f32::from(point.reference) - f32::from(point.measured)
+            }
+            [Some(a), Some(b)] if a.measured == b.measured => {
+                f32::from(co2) + f32::from(b.reference) - f32::from(b.measured)
+            }
+            [Some(a), Some(b)] => {
+                let slope =
+                    (f32::from(b.reference) - f32::from(a.reference)) / (f32::from(b.measured) - f32::from(a.measured));
+                f32::from(a.reference) + (f32::from(co2) - f32::from(a.measured)) * slope
+            }
+        };
+        corrected.clamp(0.0, f32::from(u16::MAX)) as u16
+    }
+
+    /// Sets the user humidity offset so that the given humidity, which includes the current offset, reads as the
+    /// reference humidity, returns the new offset
+    pub const fn calibrate_humidity_offset(&mut self, reference: f32, humidity: f32) -> f32 {
+        let offset = reference - (humidity - self.user_humidity_offset);
+        self.user_humidity_offset = offset.clamp(-MAX_USER_HUMIDITY_OFFSET, MAX_USER_HUMIDITY_OFFSET);
+        self.user_humidity_offset
+    }
+
+    /// Serializes the settings
+    fn write(&self, writer: &mut ByteWriter<'_>) -> Option<()> {
+        writer.put_u8(u8::from(self.setup_done))?;
+        writer.put_u8(temperature_unit_to_byte(self.temperature_unit))?;
+        writer.put_f32(self.temperature_offset)?;
+        write_co2_correction(writer, &self.co2_correction)?;
+        write_quiet_hours(writer, self.quiet_hours)?;
+        writer.put_u8(power_profile_to_byte(self.power_profile))?;
+        writer.put_u8(climate_to_byte(self.humidity_calibration.climate))?;
+        writer.put_u8(u8::from(self.humidity_calibration.long_term_correction))?;
+        writer.put_f32(self.user_humidity_offset)?;
+        writer.put_u8(u8::from(self.humidity_calibration.adaptive))?;
+        writer.put_u8(voc_kind_to_byte(self.voc_kind))
+    }
+
+    /// Deserializes the settings
+    fn read(reader: &mut ByteReader<'_>) -> Option<Self> {
+        let setup_done = reader.get_u8()? != 0;
+        let temperature_unit = temperature_unit_from_byte(reader.get_u8()?)?;
+        let temperature_offset = reader.get_f32()?;
+        let mut co2_correction = [None; CO2_CORRECTION_POINTS];
+        for point in &mut co2_correction {
+            let present = reader.get_u8()? != 0;
+            let measured = reader.get_u16()?;
+            let reference = reader.get_u16()?;
+            *point = present.then_some(Co2CorrectionPoint { measured, reference });
+        }
+        let quiet_hours_present = reader.get_u8()? != 0;
+        let start = reader.get_u8()?;
+        let end = reader.get_u8()?;
+        // An hour beyond 23 is rejected like an unknown enum byte, the console does not accept one either
+        let quiet_hours = match (quiet_hours_present, start, end) {
+            (false, _, _) => None,
+            (true, 0..=23, 0..=23) => Some((start, end)),
+            (true, _, _) => return None,
+        };
+        let power_profile = power_profile_from_byte(reader.get_u8()?)?;
+        let climate = climate_from_byte(reader.get_u8()?)?;
+        let long_term_correction = reader.get_u8()? != 0;
+        let user_humidity_offset = reader.get_f32()?;
+        let adaptive = reader.get_u8()? != 0;
+        let voc_kind = voc_kind_from_byte(reader.get_u8()?)?;
+        Some(Self {
+            setup_done,
+            temperature_unit,
+            temperature_offset,
+            user_humidity_offset,
+            co2_correction,
+            quiet_hours,
+            power_profile,
+            humidity_calibration: HumidityCalibratorConfig {
+                climate,
+                long_term_correction,
+                adaptive,
+            },
+            voc_kind,
+        })
+    }
+}
+
+/// Crash breadcrumb, counting the boots and telling whether the previous one ended in a watchdog reset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootRecord {
+    /// Number of boots since the stored state was first written or last found invalid
+    pub boot_count: u32,
+    /// Whether the previous boot ended in a watchdog reset
+    pub last_reset_was_watchdog: bool,
+    /// First stale task of that watchdog reset
+    pub last_reset_task: Option<TaskId>,
+}
+
+impl BootRecord {
+    /// Creates the record of a first boot
+    pub const fn new() -> Self {
+        Self {
+            boot_count: 0,
+            last_reset_was_watchdog: false,
+            last_reset_task: None,
+        }
+    }
+
+    /// Serializes the boot record, the task as a presence flag followed by the task
+    fn write(self, writer: &mut ByteWriter<'_>) -> Option<()> {
+        writer.put_u32(self.boot_count)?;
+        writer.put_u8(u8::from(self.last_reset_was_watchdog))?;
+        let task = self.last_reset_task.map_or((0, 0), |task| (1, task_id_to_byte(task)));
+        writer.put_u8(task.0)?;
+        writer.put_u8(task.1)
+    }
+
+    /// Deserializes the boot record
+    fn read(reader: &mut ByteReader<'_>) -> Option<Self> {
+        let boot_count = reader.get_u32()?;
+        let last_reset_was_watchdog = reader.get_u8()? != 0;
+        let task_present = reader.get_u8()? != 0;
+        let task = task_id_from_byte(reader.get_u8()?)?;
+        Some(Self {
+            boot_count,
+            last_reset_was_watchdog,
+            last_reset_task: task_present.then_some(task),
+        })
+    }
+}
+
+/// Data persisted across reboots
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StoredState {
+    /// Minimum and maximum readings seen
+    pub extremes: Extremes,
+    /// Display mode that was active when the snapshot was taken
+    pub display_mode: DisplayMode,
+    /// User configuration
+    pub settings: Settings,
+    /// Boot count and how the previous boot ended
+    pub boot_record: BootRecord,
+}
+
+impl StoredState {
+    /// Creates the state used when nothing valid is stored
+    pub const fn new() -> Self {
+        Self {
+            extremes: Extremes::new(),
+            display_mode: DisplayMode::RawData,
+            settings: Settings::new(),
+            boot_record: BootRecord::new(),
+        }
+    }
+
+    /// Serializes the state into `buf`, returns the number of bytes written
+    fn to_bytes(self, buf: &mut [u8]) -> Option<usize> {
+        let mut writer = ByteWriter::new(buf);
+        writer.put_u16(self.extremes.co2_min)?;
+        writer.put_u16(self.extremes.co2_max)?;
+        writer.put_f32(self.extremes.temperature_min)?;
+        writer.put_f32(self.extremes.temperature_max)?;
+        writer.put_f32(self.extremes.humidity_min)?;
+        writer.put_f32(self.extremes.humidity_max)?;
+        writer.put_u8(display_mode_to_byte(self.display_mode))?;
+        self.settings.write(&mut writer)?;
+        self.boot_record.write(&mut writer)?;
+        Some(writer.position())
+    }
+
+    /// Deserializes the state from `buf`
+    fn from_bytes(buf: &[u8]) -> Option<Self> {
+        let mut reader = ByteReader::new(buf);
+        let extremes = Extremes {
+            co2_min: reader.get_u16()?,
+            co2_max: reader.get_u16()?,
+            temperature_min: reader.get_f32()?,
+            temperature_max: reader.get_f32()?,
+            humidity_min: reader.get_f32()?,
+            humidity_max: reader.get_f32()?,
+        };
+        let display_mode = display_mode_from_byte(reader.get_u8()?)?;
+        let settings = Settings::read(&mut reader)?;
+        let boot_record = BootRecord::read(&mut reader)?;
+        Some(Self {
+            extremes,
+            display_mode,
+            settings,
+            boot_record,
+        })
+    }
+}
+
+/// Encodes a display mode for storage
+const fn display_mode_to_byte(mode: DisplayMode) -> u8 {
+    match mode {
+        DisplayMode::RawData => 0,
+        DisplayMode::Co2History => 1,
+        DisplayMode::Diagnostics => 2,
+        DisplayMode::Compact => 3,
+        DisplayMode::Stats => 4,
+        DisplayMode::Focus => 5,
+        DisplayMode::HumidityHistory => 6,
+        DisplayMode::Co2Large => 7,
+    }
+}
+
+/// Decodes a stored display mode
+const fn display_mode_from_byte(byte: u8) -> Option<DisplayMode> {
+    match byte {
+        0 => Some(DisplayMode::RawData),
+        1 => Some(DisplayMode::Co2History),
+        2 => Some(DisplayMode::Diagnostics),
+        3 => Some(DisplayMode::Compact),
+        4 => Some(DisplayMode::Stats),
+        5 => Some(DisplayMode::Focus),
+        6 => Some(DisplayMode::HumidityHistory),
+        7 => Some(DisplayMode::Co2Large),
+        _ => None,
+    }
+}
+
+/// Encodes a temperature unit for storage
+const fn temperature_unit_to_byte(unit: TemperatureUnit) -> u8 {
+    match unit {
+        TemperatureUnit::Celsius => 0,
+        TemperatureUnit::Fahrenheit => 1,
+    }
+}
+
+/// Decodes a stored temperature unit
+const fn temperature_unit_from_byte(byte: u8) -> Option<TemperatureUnit> {
+    match byte {
+        0 => Some(TemperatureUnit::Celsius),
+        1 => Some(TemperatureUnit::Fahrenheit),
+        _ => None,
+    }
+}
+
+/// Encodes a power profile for storage
+const fn power_profile_to_byte(profile: PowerProfile) -> u8 {
+    match profile {
+        PowerProfile::Eco => 0,
+        PowerProfile::Normal => 1,
+        PowerProfile::Performance => 2,
+    }
+}
+
+/// Decodes a stored power profile
+const fn power_profile_from_byte(byte: u8) -> Option<PowerProfile> {
+    match byte {
+        0 => Some(PowerProfile::Eco),
+        1 => Some(PowerProfile::Normal),
+        2 => Some(PowerProfile::Performance),
+        _ => None,
+    }
+}
+
+/// Encodes a humidity climate for storage
+const fn climate_to_byte(climate: HumidityClimate) -> u8 {
+    match climate {
+        HumidityClimate::Arid => 0,
+        HumidityClimate::Temperate => 1,
+        HumidityClimate::Humid => 2,
+    }
+}
+
+/// Decodes a stored humidity climate
+const fn climate_from_byte(byte: u8) -> Option<HumidityClimate> {
+    match byte {
+        0 => Some(HumidityClimate::Arid),
+        1 => Some(HumidityClimate::Temperate),
+        2 => Some(HumidityClimate::Humid),
+        _ => None,
+    }
+}
+
+/// Encodes a VOC kind for storage
+const fn voc_kind_to_byte(kind: VocKind) -> u8 {
+    match kind {
+        VocKind::Ethanol => 0,
+        VocKind::Tvoc => 1,
+    }
+}
+
+/// Decodes a stored VOC kind
+const fn voc_kind_from_byte(byte: u8) -> Option<VocKind> {
+    match byte {
+        0 => Some(VocKind::Ethanol),
+        1 => Some(VocKind::Tvoc),
+        _ => None,
+    }
+}
+
+/// Encodes a task for storage
+const fn task_id_to_byte(task_id: TaskId) -> u8 {
+    match task_id {
+        TaskId::Sensor => 0,
+        TaskId::Display => 1,
+        TaskId::Vsys => 2,
+        TaskId::Orchestrator => 3,
+        TaskId::ModeSwitch => 4,
+    }
+}
+
+/// Decodes a stored task
+const fn task_id_from_byte(byte: u8) -> Option<TaskId> {
+    match byte {
+        0 => Some(TaskId::Sensor),
+        1 => Some(TaskId::Display),
+        2 => Some(TaskId::Vsys),
+        3 => Some(TaskId::Orchestrator),
+        4 => Some(TaskId::ModeSwitch),
+        _ => None,
+    }
+}
+
+/// Serializes the CO2 correction points, each as a presence flag followed by the measured and reference values
+fn write_co2_correction(
+    writer: &mut ByteWriter<'_>,
+    co2_correction: &[Option<Co2CorrectionPoint>; CO2_CORRECTION_POINTS],
+) -> Option<()> {
+    for point in co2_correction {
+        let point = point.map_or((0, 0, 0), |point| (1, point.measured, point.reference));
+        writer.put_u8(point.0)?;
+        writer.put_u16(point.1)?;
+        writer.put_u16(point.2)?;
+    }
+    Some(())
+}
+
+/// Serializes the quiet hours as a presence flag followed by the start and end hour
+fn write_quiet_hours(writer: &mut ByteWriter<'_>, quiet_hours: Option<(u8, u8)>) -> Option<()> {
+    let quiet_hours = quiet_hours.map_or((0, 0, 0), |(start, end)| (1, start, end));
+    writer.put_u8(quiet_hours.0)?;
+    writer.put_u8(quiet_hours.1)?;
+    writer.put_u8(quiet_hours.2)
+}
+
+/// Upgrades a payload by one version, appending the defaults of the fields the next version added
+type Migration = fn(&mut ByteWriter<'_>) -> Option<()>;
+
+/// Migrations from every older version, `MIGRATIONS[n]` upgrades a payload of version `n + 1`
+/// The length is tied to `VERSION`, a version bump without a migration does not compile
+const MIGRATIONS: [Migration; VERSION as usize - 1] = [
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+    migrate_v5_to_v6,
+    migrate_v6_to_v7,
+    migrate_v7_to_v8,
+    migrate_v8_to_v9,
+];
+
+/// Version 2 added the display mode
+fn migrate_v1_to_v2(writer: &mut ByteWriter<'_>) -> Option<()> {
+    writer.put_u8(display_mode_to_byte(StoredState::new().display_mode))
+}
+
+/// Version 3 added the user settings of the guided setup
+fn migrate_v2_to_v3(writer: &mut ByteWriter<'_>) -> Option<()> {
+    let defaults = Settings::new();
+    writer.put_u8(u8::from(defaults.setup_done))?;
+    writer.put_u8(temperature_unit_to_byte(defaults.temperature_unit))?;
+    writer.put_f32(defaults.temperature_offset)?;
+    write_co2_correction(writer, &defaults.co2_correction)?;
+    write_quiet_hours(writer, defaults.quiet_hours)
+}
+
+/// Version 4 added the power profile
+fn migrate_v3_to_v4(writer: &mut ByteWriter<'_>) -> Option<()> {
+    writer.put_u8(power_profile_to_byte(Settings::new().power_profile))
+}
+
+/// Version 5 added the climate of the humidity calibration
+fn migrate_v4_to_v5(writer: &mut ByteWriter<'_>) -> Option<()> {
+    writer.put_u8(climate_to_byte(Settings::new().humidity_calibration.climate))
+}
+
+/// Version 6 added the switch of the long-term humidity correction
+fn migrate_v5_to_v6(writer: &mut ByteWriter<'_>) -> Option<()> {
+    writer.put_u8(u8::from(Settings::new().humidity_calibration.long_term_correction))
+}
+
+/// Version 7 added the user humidity offset and the switch of the adaptive humidity calibration
+fn migrate_v6_to_v7(writer: &mut ByteWriter<'_>) -> Option<()> {
+    let defaults = Settings::new();
+    writer.put_f32(defaults.user_humidity_offset)?;
+    writer.put_u8(u8::from(defaults.humidity_calibration.adaptive))
+}
+
+/// Version 8 added the VOC kind
+fn migrate_v7_to_v8(writer: &mut ByteWriter<'_>) -> Option<()> {
+    writer.put_u8(voc_kind_to_byte(Settings::new().voc_kind))
+}
+
+/// Version 9 added the boot record
+fn migrate_v8_to_v9(writer: &mut ByteWriter<'_>) -> Option<()> {
+    BootRecord::new().write(writer)
+}
+
+/// Upgrades the payload of an older version in place to the current version, returns the new payload length
+fn migrate(payload: &mut [u8], payload_len: usize, version: u16) -> Option<usize> {
+    let mut writer = ByteWriter {
+        buf: payload,
+        position: payload_len,
+    };
+    for migration in MIGRATIONS.get(usize::from(version).checked_sub(1)?..)? {
+        migration(&mut writer)?;
+    }
+    Some(writer.position())
+}
+
+/// Owns the flash and a cached copy of what is stored in it
+struct Store {
+    /// The flash driver, `None` until `init` was called
+    flash: Option<StorageFlash>,
+    /// The state as last read from or written to flash
+    state: StoredState,
+}
+
+/// Global store, shared by everything that persists data
+static STORE: Mutex<CriticalSectionRawMutex, Store> = Mutex::new(Store {
+    flash: None,
+    state: StoredState::new(),
+});
+
+/// Set when the stored state could not be loaded and the settings were reset to the safe defaults
+static SETTINGS_RESET: AtomicBool = AtomicBool::new(false);
+
+/// Takes ownership of the flash and loads the stored state
+/// Returns the defaults if nothing is stored, and the safe defaults if the stored state is invalid
+#[cfg(target_os = "none")]
+pub async fn init(flash: Peri<'static, FLASH>) -> StoredState {
+    init_with(Flash::<_, Blocking, FLASH_SIZE>::new_blocking(flash)).await
+}
+
+/// Takes ownership of the flash stand-in of the host tests and loads the stored state, see `init`
+#[cfg(not(target_os = "none"))]
+pub async fn init(flash: HostFlash) -> StoredState {
+    init_with(flash).await
+}
+
+/// Loads the stored state from `flash` and keeps both for later updates
+async fn init_with(mut flash: StorageFlash) -> StoredState {
+    let state = match load(&mut flash) {
+        Ok(Some(state)) => state,
+        Ok(None) => {
+            info!("No stored state found - using defaults");
+            StoredState::new()
+        }
+        Err(e) => {
+            error!("{} - settings reset to defaults", e);
+            SETTINGS_RESET.store(true, Ordering::Relaxed);
+            StoredState {
+                settings: Settings::safe_defaults(),
+                ..StoredState::new()
+            }
+        }
+    };
+
+    let mut store = STORE.lock().await;
+    store.flash = Some(flash);
+    store.state = state;
+    state
+}
+
+/// Applies `f` to the stored state and writes the result to flash if it changed
+pub async fn update<F>(f: F) -> Result<(), &'static str>
+where
+    F: FnOnce(&mut StoredState),
+{
+    let mut store = STORE.lock().await;
+    let mut state = store.state;
+    f(&mut state);
+    if state == store.state {
+        return Ok(());
+    }
+
+    let flash = store.flash.as_mut().ok_or("Settings store not initialized")?;
+    save(flash, &state)?;
+    store.state = state;
+    drop(store);
+    Ok(())
+}
+
+/// Persists new user settings and applies them to the running system
+/// Repaints the screen when a setting it is drawn with changed
+pub async fn apply_settings(settings: Settings) -> Result<(), &'static str> {
+    let unit_changed = {
+        let mut state = SYSTEM_STATE.lock().await;
+        let unit_changed = state.get_settings().temperature_unit != settings.temperature_unit;
+        state.set_settings(settings);
+        unit_changed
+    };
+    if unit_changed {
+        send_event(Event::ForceRedraw).await;
+    }
+    update(|stored| stored.settings = settings).await
+}
+
+/// Resets everything stored to the defaults, the guided setup runs again on the next serial connection
+/// The boot record is kept, it is no configuration
+pub async fn factory_reset() -> Result<(), &'static str> {
+    {
+        let mut state = SYSTEM_STATE.lock().await;
+        state.set_settings(Settings::new());
+        state.restore_extremes(Extremes::new());
+    }
+    update(|stored| {
+        *stored = StoredState {
+            boot_record: stored.boot_record,
+            ..StoredState::new()
+        };
+    })
+    .await
+}
+
+/// Logs how the previous boot ended and counts this boot, called once early at boot after `init`
+/// A boot starts out as not ended by the watchdog, `record_watchdog_reset` marks it right before a reset
+pub async fn record_boot() {
+    let previous = STORE.lock().await.state.boot_record;
+    let boot_count = previous.boot_count.wrapping_add(1);
+    match (previous.last_reset_was_watchdog, previous.last_reset_task) {
+        (true, Some(task)) => info!(
+            "Boot {}, the previous boot was reset by the watchdog, stale task {}",
+            boot_count, task
+        ),
+        (true, None) => info!("Boot {}, the previous boot was reset by the watchdog", boot_count),
+        (false, _) => info!("Boot {}, the previous boot was not reset by the watchdog", boot_count),
+    }
+
+    let record = BootRecord {
+        boot_count,
+        ..BootRecord::new()
+    };
+    if let Err(e) = update(|stored| stored.boot_record = record).await {
+        error!("Failed to record boot: {}", e);
+    }
+}
+
+/// Marks the current boot as ended by a watchdog reset with the first stale task, right before the reset
+pub async fn record_watchdog_reset(task: Option<TaskId>) {
+    if let Err(e) = update(|stored| {
+        stored.boot_record.last_reset_was_watchdog = true;
+        stored.boot_record.last_reset_task = task;
+    })
+    .await
+    {
+        error!("Failed to record watchdog reset: {}", e);
+    }
+}
+
+/// Whether the settings were reset to the safe defaults at boot because the stored state was invalid
+pub fn were_settings_reset() -> bool {
+    SETTINGS_RESET.load(Ordering::Relaxed)
+}
+
+/// Whether the settings were reset at boot, only returns `true` once so the reset is reported a single time
+pub fn take_settings_reset() -> bool {
+    SETTINGS_RESET.swap(false, Ordering::Relaxed)
+}
+
+/// Reads and validates the blob from flash
+/// Returns `None` if nothing is stored (no magic number, e.g. an erased sector) and an error if the blob is invalid
+fn load(flash: &mut StorageFlash) -> Result<Option<StoredState>, &'static str> {
+    let mut blob = [0u8; BLOB_CAPACITY];
+    if flash.blocking_read(STORAGE_OFFSET, &mut blob).is_err() {
+        return Err("Failed to read storage sector");
+    }
+
+    let mut header = ByteReader::new(&blob);
+    let magic = header.get_u32().ok_or("Blob too small")?;
+    let version = header.get_u16().ok_or("Blob too small")?;
+    let payload_len = usize::from(header.get_u16().ok_or("Blob too small")?);
+    if magic != MAGIC {
+        return Ok(None);
+    }
+    if payload_len > PAYLOAD_CAPACITY {
+        return Err("Stored state exceeds payload capacity");
+    }
+    if version == 0 || version > VERSION {
+        info!("Stored state has version {}, expected at most {}", version, VERSION);
+        return Err("Stored state has an incompatible version");
+    }
+
+    let crc_offset = HEADER_SIZE + payload_len;
+    let stored_crc = ByteReader::new(blob.get(crc_offset..).ok_or("Blob too small")?)
+        .get_u32()
+        .ok_or("Blob too small")?;
+    if crc32(blob.get(..crc_offset).ok_or("Blob too small")?) != stored_crc {
+        return Err("Stored state failed CRC check");
+    }
+
+    // Older payloads are upgraded in a copy, the blob is rewritten in the current version with the next change
+    let mut payload = [0u8; PAYLOAD_CAPACITY];
+    payload
+        .get_mut(..payload_len)
+        .ok_or("Blob too small")?
+        .copy_from_slice(blob.get(HEADER_SIZE..crc_offset).ok_or("Blob too small")?);
+    let payload_len = if version < VERSION {
+        let migrated_len = migrate(&mut payload, payload_len, version).ok_or("Failed to migrate stored state")?;
+        info!("Migrated stored state from version {} to {}", version, VERSION);
+        migrated_len
+    } else {
+        payload_len
+    };
+
+    let state = StoredState::from_bytes(payload.get(..payload_len).ok_or("Blob too small")?)
+        .ok_or("Stored state has invalid values")?;
+    info!("Loaded stored state (version {}, {} bytes)", version, payload_len);
+    Ok(Some(state))
+}
+
+/// Erases the storage sector and writes the blob for `state`
+fn save(flash: &mut StorageFlash, state: &StoredState) -> Result<(), &'static str> {
+    let mut blob = [0xFFu8; BLOB_CAPACITY];
+    let payload_len = state
+        .to_bytes(
+            blob.get_mut(HEADER_SIZE..HEADER_SIZE + PAYLOAD_CAPACITY)
+                .ok_or("Blob too small")?,
+        )
+        .ok_or("Stored state exceeds payload capacity")?;
+
+    let mut header = ByteWriter::new(&mut blob);
+    header.put_u32(MAGIC).ok_or("Blob too small")?;
+    header.put_u16(VERSION).ok_or("Blob too small")?;
+    #[allow(clippy::cast_possible_truncation)]
+    header.put_u16(payload_len as u16).ok_or("Blob too small")?;
+
+    let crc_offset = HEADER_SIZE + payload_len;
+    let crc = crc32(blob.get(..crc_offset).ok_or("Blob too small")?);
+    ByteWriter::new(blob.get_mut(crc_offset..).ok_or("Blob too small")?)
+        .put_u32(crc)
+        .ok_or("Blob too small")?;
+
+    let blob = blob.get(..crc_offset + CRC_SIZE).ok_or("Blob too small")?;
+    #[allow(clippy::cast_possible_truncation)]
+    flash
+        .blocking_erase(STORAGE_OFFSET, STORAGE_OFFSET + ERASE_SIZE as u32)
+        .map_err(|_| "Failed to erase storage sector")?;
+    flash
+        .blocking_write(STORAGE_OFFSET, blob)
+        .map_err(|_| "Failed to write storage sector")?;
+
+    info!("Stored state written to flash ({} bytes)", blob.len());
+    Ok(())
+}
+
+/// Flash stand-in for the host tests, the storage sector in RAM, counting how often it was written
+#[cfg(not(target_os = "none"))]
+pub struct HostFlash {
+    /// Contents of the storage sector
+    sector: [u8; ERASE_SIZE],
+    /// Number of writes to the sector
+    writes: u32,
+}
+
+#[cfg(not(target_os = "none"))]
+impl HostFlash {
+    /// Creates an erased storage sector
+    pub const fn new() -> Self {
+        Self {
+            sector: [0xFF; ERASE_SIZE],
+            writes: 0,
+        }
+    }
+
+    /// Creates a storage sector holding `blob`, as written by an earlier firmware
+    pub fn with_blob(blob: &[u8]) -> Self {
+        let mut flash = Self::new();
+        if let Some(sector) = flash.sector.get_mut(..blob.len()) {
+            sector.copy_from_slice(blob);
+        }
+        flash
+    }
+
+    /// Number of writes to the storage sector so far
+    pub const fn writes(&self) -> u32 {
+        self.writes
+    }
+
+    /// Part of the storage sector at `offset` from the start of flash
+    fn range(offset: u32, len: usize) -> Result<core::ops::Range<usize>, &'static str> {
+        let start = offset.checked_sub(STORAGE_OFFSET).ok_or("Outside the storage sector")? as usize;
+        let end = start + len;
+        if end > ERASE_SIZE {
+            return Err("Outside the storage sector");
+        }
+        Ok(start..end)
+    }
+
+    /// Reads from the storage sector, like `Flash::blocking_read`, which takes the flash mutably as well
+    #[allow(clippy::needless_pass_by_ref_mut)]
+    fn blocking_read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), &'static str> {
+        let range = Self::range(offset, bytes.len())?;
+        bytes.copy_from_slice(self.sector.get(range).ok_or("Outside the storage sector")?);
+        Ok(())
+    }
+
+    /// Erases the storage sector, like `Flash::blocking_erase`
+    fn blocking_erase(&mut self, from: u32, to: u32) -> Result<(), &'static str> {
+        let range = Self::range(from, to.saturating_sub(from) as usize)?;
+        self.sector
+            .get_mut(range)
+            .ok_or("Outside the storage sector")?
+            .fill(0xFF);
+        Ok(())
+    }
+
+    /// Writes to the storage sector, like `Flash::blocking_write`, counting the write
+    fn blocking_write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), &'static str> {
+        let range = Self::range(offset, bytes.len())?;
+        self.sector
+            .get_mut(range)
+            .ok_or("Outside the storage sector")?
+            .copy_from_slice(bytes);
+        self.writes += 1;
+        Ok(())
+    }
+}
+
+/// Decides when a dirty snapshot may be written, limiting flash writes to one per interval
+struct SnapshotLimiter {
+    /// Minimum time between two writes
+    interval: Duration,
+    /// Time of the last write, `None` if nothing was written yet
+    last_write: Option<Instant>,
+}
+
+impl SnapshotLimiter {
+    /// Creates a limiter allowing one write per `interval`
+    const fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_write: None,
+        }
+    }
+
+    /// Whether a write is allowed at `now`
+    fn is_write_allowed(&self, now: Instant) -> bool {
+        self.last_write
+            .is_none_or(|last_write| now.saturating_duration_since(last_write) >= self.interval)
+    }
+
+    /// Records that a write happened at `now`
+    const fn record_write(&mut self, now: Instant) {
+        self.last_write = Some(now);
+    }
+}
+
+/// Periodically writes changed extremes and display mode to flash, at most once per `SNAPSHOT_INTERVAL`
+#[embassy_executor::task]
+pub async fn persistence_task() {
+    let mut limiter = SnapshotLimiter::new(SNAPSHOT_INTERVAL);
+
+    loop {
+        Timer::after(SNAPSHOT_CHECK_INTERVAL).await;
+        storage_mode::park_if_active("Persistence").await;
+        persist_snapshot(&mut limiter).await;
+    }
+}
+
+/// Writes the extremes and display mode to flash if they changed and `limiter` allows a write
+async fn persist_snapshot(limiter: &mut SnapshotLimiter) {
+    let now = Instant::now();
+    if !limiter.is_write_allowed(now) {
+        return;
+    }
+
+    // Only take the dirty flag once a write is allowed, so changes keep accumulating in between
+    let (extremes, display_mode) = {
+        let mut state = SYSTEM_STATE.lock().await;
+        if !state.take_snapshot_dirty() {
+            return;
+        }
+        (state.get_extremes(), state.get_display_mode())
+    };
+
+    match update(|stored| {
+        stored.extremes = extremes;
+        stored.display_mode = display_mode;
+    })
+    .await
+    {
+        Ok(()) => limiter.record_write(now),
+        Err(e) => error!("Failed to persist snapshot: {}", e),
+    }
+}
+
+/// Calculates the CRC-32 (IEEE) checksum of `bytes`
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Writes little-endian values into a byte buffer
+struct ByteWriter<'a> {
+    /// The buffer being written
+    buf: &'a mut [u8],
+    /// Current write position
+    position: usize,
+}
+
+impl<'a> ByteWriter<'a> {
+    /// Creates a writer starting at the beginning of `buf`
+    const fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, position: 0 }
+    }
+
+    /// Number of bytes written so far
+    const fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Writes raw bytes, returns `None` if they do not fit
+    fn put_bytes(&mut self, bytes: &[u8]) -> Option<()> {
+        let end = self.position + bytes.len();
+        self.buf.get_mut(self.position..end)?.copy_from_slice(bytes);
+        self.position = end;
+        Some(())
+    }
+
+    /// Writes a `u8`
+    fn put_u8(&mut self, value: u8) -> Option<()> {
+        self.put_bytes(&[value])
+    }
+
+    /// Writes a `u16`
+    fn put_u16(&mut self, value: u16) -> Option<()> {
+        self.put_bytes(&value.to_le_bytes())
+    }
+
+    /// Writes a `u32`
+    fn put_u32(&mut self, value: u32) -> Option<()> {
+        self.put_bytes(&value.to_le_bytes())
+    }
+
+    /// Writes an `f32`
+    fn put_f32(&mut self, value: f32) -> Option<()> {
+        self.put_bytes(&value.to_le_bytes())
+    }
+}
+
+/// Reads little-endian values from a byte buffer
+struct ByteReader<'a> {
+    /// The buffer being read
+    buf: &'a [u8],
+    /// Current read position
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    /// Creates a reader starting at the beginning of `buf`
+    const fn new(buf: &'a [u8]) -> Self {
+        Self { buf, position: 0 }
+    }
+
+    /// Reads `N` raw bytes, returns `None` if the buffer is exhausted
+    fn get_bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.buf.get(self.position..self.position + N)?.try_into().ok()?;
+        self.position += N;
+        Some(bytes)
+    }
+
+    /// Reads a `u8`
+    fn get_u8(&mut self) -> Option<u8> {
+        self.get_bytes::<1>().map(|[value]| value)
+    }
+
+    /// Reads a `u16`
+    fn get_u16(&mut self) -> Option<u16> {
+        self.get_bytes().map(u16::from_le_bytes)
+    }
+
+    /// Reads a `u32`
+    fn get_u32(&mut self) -> Option<u32> {
+        self.get_bytes().map(u32::from_le_bytes)
+    }
+
+    /// Reads an `f32`
+    fn get_f32(&mut self) -> Option<f32> {
+        self.get_bytes().map(f32::from_le_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+    use embassy_time::MockDriver;
+    use ens160_aq::data::AirQualityIndex;
+
+    use super::*;
+    use crate::{
+        HOST_TEST_LOCK,
+        system_state::{ReadingQuality, SensorData, SystemState},
+    };
+
+    /// A reading with the given CO2 level
+    const fn reading(co2: u16) -> SensorData {
+        SensorData {
+            temperature: 21.0,
+            raw_temperature: 23.5,
+            humidity: 45.0,
+            raw_humidity: 41.0,
+            dew_point: 8.6,
+            co2,
+            co2_suspect: false,
+            voc: 50,
+            voc_kind: VocKind::Ethanol,
+            air_quality: AirQualityIndex::Good,
+            settling: false,
+            quality: ReadingQuality::Good,
+        }
+    }
+
+    /// Number of writes to the flash of the store
+    async fn flash_writes() -> u32 {
+        STORE.lock().await.flash.as_ref().map_or(0, HostFlash::writes)
+    }
+
+    #[test]
+    fn snapshots_are_written_at_most_once_per_interval() {
+        let _guard = HOST_TEST_LOCK.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        block_on(async {
+            init(HostFlash::new()).await;
+            *SYSTEM_STATE.lock().await = SystemState::new();
+            let mut limiter = SnapshotLimiter::new(SNAPSHOT_INTERVAL);
+
+            // A new CO2 maximum every minute marks the snapshot dirty on every check within the interval
+            let checks = u16::try_from(SNAPSHOT_INTERVAL.as_secs() / SNAPSHOT_CHECK_INTERVAL.as_secs()).unwrap();
+            for co2 in 1000..1000 + checks {
+                SYSTEM_STATE.lock().await.update_extremes(&reading(co2));
+                MockDriver::get().advance(SNAPSHOT_CHECK_INTERVAL);
+                persist_snapshot(&mut limiter).await;
+            }
+            assert_eq!(flash_writes().await, 1);
+
+            // The changes held back are written once the interval passed
+            MockDriver::get().advance(SNAPSHOT_CHECK_INTERVAL);
+            persist_snapshot(&mut limiter).await;
+            assert_eq!(flash_writes().await, 2);
+            assert_eq!(STORE.lock().await.state.extremes.co2_max, 1000 + checks - 1);
+        });
+    }
+
+    /// Bytes of the fields a layout version appended to the payload, with values that differ from the defaults
+    fn fields_added_in(version: u16) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match version {
+            1 => {
+                // Extremes
+                bytes.extend(420u16.to_le_bytes());
+                bytes.extend(1800u16.to_le_bytes());
+                for value in [18.5f32, 26.0, 30.0, 60.0] {
+                    bytes.extend(value.to_le_bytes());
+                }
+            }
+            2 => bytes.push(display_mode_to_byte(DisplayMode::Co2History)),
+            3 => {
+                // Setup done, Fahrenheit and the temperature offset
+                bytes.extend([1, 1]);
+                bytes.extend((-2.0f32).to_le_bytes());
+                // One CO2 correction point
+                bytes.push(1);
+                bytes.extend(500u16.to_le_bytes());
+                bytes.extend(420u16.to_le_bytes());
+                bytes.extend([0; 5]);
+                // Quiet hours
+                bytes.extend([1, 23, 6]);
+            }
+            4 => bytes.push(power_profile_to_byte(PowerProfile::Performance)),
+            5 => bytes.push(climate_to_byte(HumidityClimate::Arid)),
+            // Long-term correction off
+            6 => bytes.push(u8::from(false)),
+            7 => {
+                // User humidity offset, adaptive calibration off
+                bytes.extend(5.0f32.to_le_bytes());
+                bytes.push(0);
+            }
+            8 => bytes.push(voc_kind_to_byte(VocKind::Tvoc)),
+            9 => {
+                // Boot count, watchdog reset of the VSYS task
+                bytes.extend(12u32.to_le_bytes());
+                bytes.extend([1, 1, 2]);
+            }
+            _ => panic!("no layout version {version}"),
+        }
+        bytes
+    }
+
+    /// State held by a payload of `version` built from `fields_added_in`, later fields at their defaults
+    fn state_of(version: u16) -> StoredState {
+        let mut state = StoredState::new();
+        state.extremes = Extremes {
+            co2_min: 420,
+            co2_max: 1800,
+            temperature_min: 18.5,
+            temperature_max: 26.0,
+            humidity_min: 30.0,
+            humidity_max: 60.0,
+        };
+        let settings = &mut state.settings;
+        if version >= 2 {
+            state.display_mode = DisplayMode::Co2History;
+        }
+        if version >= 3 {
+            settings.setup_done = true;
+            settings.temperature_unit = TemperatureUnit::Fahrenheit;
+            settings.temperature_offset = -2.0;
+            settings.co2_correction = [
+                Some(Co2CorrectionPoint {
+                    measured: 500,
+                    reference: 420,
+                }),
+                None,
+            ];
+            settings.quiet_hours = Some((23, 6));
+        }
+        if version >= 4 {
+            settings.power_profile = PowerProfile::Performance;
+        }
+        if version >= 5 {
+            settings.humidity_calibration.climate = HumidityClimate::Arid;
+        }
+        if version >= 6 {
+            settings.humidity_calibration.long_term_correction = false;
+        }
+        if version >= 7 {
+            settings.user_humidity_offset = 5.0;
+            settings.humidity_calibration.adaptive = false;
+        }
+        if version >= 8 {
+            settings.voc_kind = VocKind::Tvoc;
+        }
+        if version >= 9 {
+            state.boot_record = BootRecord {
+                boot_count: 12,
+                last_reset_was_watchdog: true,
+                last_reset_task: Some(TaskId::Vsys),
+            };
+        }
+        state
+    }
+
+    /// Frames a payload with the header and the CRC
+    fn blob(version: u16, payload: &[u8]) -> Vec<u8> {
+        let mut blob = Vec::new();
+        blob.extend(MAGIC.to_le_bytes());
+        blob.extend(version.to_le_bytes());
+        blob.extend(u16::try_from(payload.len()).unwrap().to_le_bytes());
+        blob.extend(payload);
+        blob.extend(crc32(&blob).to_le_bytes());
+        blob
+    }
+
+    /// Loads a payload of `version` holding the fields of that version
+    fn load_version(version: u16) -> Result<Option<StoredState>, &'static str> {
+        let payload: Vec<u8> = (1..=version).flat_map(fields_added_in).collect();
+        load(&mut HostFlash::with_blob(&blob(version, &payload)))
+    }
+
+    #[test]
+    fn v1_is_migrated() {
+        assert_eq!(load_version(1), Ok(Some(state_of(1))));
+    }
+
+    #[test]
+    fn v2_is_migrated() {
+        assert_eq!(load_version(2), Ok(Some(state_of(2))));
+    }
+
+    #[test]
+    fn v3_is_migrated() {
+        assert_eq!(load_version(3), Ok(Some(state_of(3))));
+    }
+
+    #[test]
+    fn v4_is_migrated() {
+        assert_eq!(load_version(4), Ok(Some(state_of(4))));
+    }
+
+    #[test]
+    fn v5_is_migrated() {
+        assert_eq!(load_version(5), Ok(Some(state_of(5))));
+    }
+
+    #[test]
+    fn v6_is_migrated() {
+        assert_eq!(load_version(6), Ok(Some(state_of(6))));
+    }
+
+    #[test]
+    fn v7_is_migrated() {
+        assert_eq!(load_version(7), Ok(Some(state_of(7))));
+    }
+
+    #[test]
+    fn v8_is_migrated() {
+        assert_eq!(load_version(8), Ok(Some(state_of(8))));
+    }
+
+    #[test]
+    fn v9_is_loaded() {
+        assert_eq!(VERSION, 9, "add the fields of the new version to the migration tests");
+        assert_eq!(load_version(9), Ok(Some(state_of(9))));
+    }
+
+    #[test]
+    fn saved_state_is_loaded_back() {
+        let mut flash = HostFlash::new();
+        save(&mut flash, &state_of(VERSION)).unwrap();
+
+        assert_eq!(load(&mut flash), Ok(Some(state_of(VERSION))));
+    }
+
+    #[test]
+    fn quiet_hours_beyond_the_day_are_rejected() {
+        for quiet_hours in [(24, 6), (22, 24), (255, 255)] {
+            let mut state = state_of(VERSION);
+            state.settings.quiet_hours = Some(quiet_hours);
+            let mut flash = HostFlash::new();
+            save(&mut flash, &state).unwrap();
+
+            assert!(load(&mut flash).is_err(), "quiet hours {quiet_hours:?}");
+        }
+    }
+
+    #[test]
+    fn newer_and_corrupt_blobs_are_rejected() {
+        let payload: Vec<u8> = (1..=VERSION).flat_map(fields_added_in).collect();
+        let mut corrupt = blob(VERSION, &payload);
+        corrupt[HEADER_SIZE] ^= 1;
+
+        assert!(load(&mut HostFlash::with_blob(&blob(VERSION + 1, &payload))).is_err());
+        assert!(load(&mut HostFlash::with_blob(&corrupt)).is_err());
+        assert_eq!(load(&mut HostFlash::new()), Ok(None));
+    }
+}
@@ -0,0 +1,35 @@
+//! User initiated storage mode, for keeping the device in a drawer or shipping it without draining the battery
+//!
+//! Entering it is coordinated by the orchestrator: the display shows a notice and switches off, the ENS160 is put
+//! to deep sleep and every other task parks itself at its next iteration. The MCU then idles in its sleep state with
+//! no timers left running. The USB stack stays up, opening the serial console again resets the system through the
+//! watchdog. The RP2350 dormant mode is not used, as a USB connection could not wake it.
+
+use defmt::info;
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+
+/// Whether storage mode was entered, it is only left by a reset
+static ACTIVE: Mutex<CriticalSectionRawMutex, bool> = Mutex::new(false);
+
+/// Marks storage mode as entered, tasks park at their next check
+pub async fn enter() {
+    *ACTIVE.lock().await = true;
+}
+
+/// Whether storage mode was entered
+pub async fn is_active() -> bool {
+    *ACTIVE.lock().await
+}
+
+/// Parks the calling task for good if storage mode was entered
+pub async fn park_if_active(task_name: &str) {
+    if is_active().await {
+        park(task_name).await;
+    }
+}
+
+/// Parks the calling task for good
+pub async fn park(task_name: &str) -> ! {
+    info!("{} task parked for storage mode", task_name);
+    core::future::pending().await
+}
@@ -1,9 +1,13 @@
 //! System state management for the Air Quality Monitor
 
+use defmt::Format;
 use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+use embassy_time::{Duration, Instant};
 use ens160_aq::data::AirQualityIndex;
 use heapless::Vec;
 
+use crate::{sensor::CHARGING_READ_INTERVAL, settings_store::Settings};
+
 /// Global system state - initialized with default values
 pub static SYSTEM_STATE: Mutex<CriticalSectionRawMutex, SystemState> = Mutex::new(SystemState::new());
 
@@ -14,20 +18,398 @@ pub enum DisplayMode {
     RawData,
     /// Show CO2 history bar chart
     Co2History,
+    /// Show humidity history bar chart
+    HumidityHistory,
+    /// Show the CO2 level in large seven-segment digits with the air quality below, to be read across a room
+    Co2Large,
+    /// Show diagnostics counters
+    Diagnostics,
+    /// Show all metrics on two dense lines with a CO2 sparkline
+    Compact,
+    /// Show the session statistics
+    Stats,
+    /// Show the metric that changed most notably in large digits, with the others below
+    Focus,
+}
+
+/// Display modes the automatic mode switching cycles through, in order
+/// Add `DisplayMode::Compact`, `DisplayMode::Diagnostics`, `DisplayMode::Stats` or `DisplayMode::Focus` to show those
+/// screens as well
+const DISPLAY_ROTATION: &[DisplayMode] = &[
+    DisplayMode::RawData,
+    DisplayMode::Co2History,
+    DisplayMode::HumidityHistory,
+    DisplayMode::Co2Large,
+];
+
+/// Metric shown in large digits on the focus screen
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Format)]
+pub enum FocusMetric {
+    /// CO2 level
+    Co2,
+    /// Temperature
+    Temperature,
+    /// Humidity
+    Humidity,
+}
+
+/// Metric the focus screen returns to once no metric changed notably for a while
+const FOCUS_DEFAULT_METRIC: FocusMetric = FocusMetric::Co2;
+
+/// Unit temperatures are displayed in
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum TemperatureUnit {
+    /// Degrees Celsius
+    Celsius,
+    /// Degrees Fahrenheit
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    /// Converts a temperature in degrees Celsius to this unit
+    pub fn from_celsius(self, celsius: f32) -> f32 {
+        match self {
+            Self::Celsius => celsius,
+            Self::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    /// Converts a temperature in this unit to degrees Celsius
+    pub fn to_celsius(self, value: f32) -> f32 {
+        match self {
+            Self::Celsius => value,
+            Self::Fahrenheit => (value - 32.0) * 5.0 / 9.0,
+        }
+    }
+
+    /// Unit symbol, "C" or "F"
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::Celsius => "C",
+            Self::Fahrenheit => "F",
+        }
+    }
+
+    /// The other unit
+    pub const fn toggled(self) -> Self {
+        match self {
+            Self::Celsius => Self::Fahrenheit,
+            Self::Fahrenheit => Self::Celsius,
+        }
+    }
+}
+
+/// Why the sensors cannot be used, shown on the sensor error screen
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Format)]
+pub enum SensorFault {
+    /// The AHT21 does not acknowledge its address, e.g. a loose connector
+    Aht21Disconnected,
+    /// The AHT21 acknowledges its address but fails
+    Aht21Error,
+    /// The ENS160 does not acknowledge its address, e.g. a loose connector
+    Ens160Disconnected,
+    /// The ENS160 acknowledges its address but fails
+    Ens160Error,
+}
+
+impl SensorFault {
+    /// Short description for the display
+    pub const fn message(self) -> &'static str {
+        match self {
+            Self::Aht21Disconnected => "AHT21 disconnected",
+            Self::Aht21Error => "AHT21 error",
+            Self::Ens160Disconnected => "ENS160 disconnected",
+            Self::Ens160Error => "ENS160 error",
+        }
+    }
+}
+
+/// Volatile organic compound figure of the ENS160 that is displayed and reported
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum VocKind {
+    /// Ethanol equivalent concentration
+    Ethanol,
+    /// Total volatile organic compounds
+    Tvoc,
+}
+
+impl VocKind {
+    /// Short label for the display, the unit is ppb for both
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Ethanol => "EtOH",
+            Self::Tvoc => "TVOC",
+        }
+    }
+
+    /// Name of the figure as used on the serial console
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Ethanol => "ethanol",
+            Self::Tvoc => "tvoc",
+        }
+    }
+
+    /// Parses a figure name as used on the serial console
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Ethanol, Self::Tvoc].into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+/// Which display mode the device starts in after a reboot
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[allow(dead_code)]
+pub enum StartupDisplayMode {
+    /// Resume the display mode that was active before the reboot
+    ResumeLast,
+    /// Always start in the given display mode
+    Fixed(DisplayMode),
+}
+
+/// Selected startup display mode behavior
+pub const STARTUP_DISPLAY_MODE: StartupDisplayMode = StartupDisplayMode::Fixed(DisplayMode::RawData);
+
+/// Half width of the band around the smoothed CO2 level in ppm within which the trend shows flat
+const CO2_TREND_DEAD_BAND: f32 = 20.0;
+
+/// Weight of a new reading in the smoothed CO2 level the trend is compared against (0-1, lower is smoother)
+const CO2_TREND_SMOOTHING: f32 = 0.5;
+
+/// Number of consecutive readings a new trend direction has to persist before the arrow flips
+/// A change of more than twice the dead band flips it right away
+const CO2_TREND_CONFIRMATIONS: u8 = 2;
+
+/// How the CO2 trend is derived from the readings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+enum Co2TrendMode {
+    /// The latest reading against a smoothed level, with a dead band and confirmations, see `Co2TrendTracker`
+    Smoothed,
+    /// The latest reading against the previous one in the CO2 history, a trend from a change of `CO2_TREND_STEP`
+    PreviousReading,
+}
+
+/// Derivation of the CO2 trend shown behind the CO2 value
+const CO2_TREND_MODE: Co2TrendMode = Co2TrendMode::Smoothed;
+
+/// Change in ppm between the last two readings from which `Co2TrendMode::PreviousReading` shows a trend
+const CO2_TREND_STEP: u16 = 50;
+
+/// Direction the CO2 level is heading
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Co2Trend {
+    /// CO2 is rising beyond the dead band
+    Rising,
+    /// CO2 is falling beyond the dead band
+    Falling,
+    /// CO2 is steady within the dead band
+    Flat,
+}
+
+/// Derives a stable CO2 trend, so a level oscillating by a few ppm does not flicker the arrow
+struct Co2TrendTracker {
+    /// Exponentially smoothed CO2 level, `None` until the first reading
+    smoothed: Option<f32>,
+    /// Currently shown trend
+    trend: Co2Trend,
+    /// Direction seen in the latest readings that differs from the shown trend
+    candidate: Co2Trend,
+    /// Number of consecutive readings the candidate direction has been seen
+    confirmations: u8,
+}
+
+impl Co2TrendTracker {
+    /// Creates a tracker showing a flat trend
+    const fn new() -> Self {
+        Self {
+            smoothed: None,
+            trend: Co2Trend::Flat,
+            candidate: Co2Trend::Flat,
+            confirmations: 0,
+        }
+    }
+
+    /// Adds a CO2 reading and returns the resulting trend
+    fn update(&mut self, co2: u16) -> Co2Trend {
+        let co2 = f32::from(co2);
+        let Some(smoothed) = self.smoothed else {
+            self.smoothed = Some(co2);
+            return self.trend;
+        };
+
+        let delta = co2 - smoothed;
+        let direction = if delta > CO2_TREND_DEAD_BAND {
+            Co2Trend::Rising
+        } else if delta < -CO2_TREND_DEAD_BAND {
+            Co2Trend::Falling
+        } else {
+            Co2Trend::Flat
+        };
+
+        if direction == self.trend {
+            self.confirmations = 0;
+        } else {
+            if direction == self.candidate {
+                self.confirmations = self.confirmations.saturating_add(1);
+            } else {
+                self.candidate = direction;
+                self.confirmations = 1;
+            }
+            if self.confirmations >= CO2_TREND_CONFIRMATIONS || delta.abs() > 2.0 * CO2_TREND_DEAD_BAND {
+                self.trend = direction;
+                self.confirmations = 0;
+            }
+        }
+
+        self.smoothed = Some(smoothed + CO2_TREND_SMOOTHING * delta);
+        self.trend
+    }
+}
+
+/// CO2 trend from the change between the last two readings of the history, flat until there are two
+const fn previous_reading_trend(co2_history: &[u16]) -> Co2Trend {
+    match *co2_history {
+        [.., previous, latest] if latest >= previous.saturating_add(CO2_TREND_STEP) => Co2Trend::Rising,
+        [.., previous, latest] if latest.saturating_add(CO2_TREND_STEP) <= previous => Co2Trend::Falling,
+        _ => Co2Trend::Flat,
+    }
 }
 
 /// Holds the current state of the system, including battery level and sensor data
+// The flags are independent pieces of state, not a mode that would fit an enum
+#[allow(clippy::struct_excessive_bools)]
 pub struct SystemState {
     /// Current battery level
     battery_percent: u8,
-    /// Whether the battery is charging
-    is_charging: bool,
+    /// When charging started, `None` while on battery
+    charging_since: Option<Instant>,
     /// Last sensor data for redrawing
     pub last_sensor_data: Option<SensorData>,
+    /// When the last sensor data arrived
+    last_sensor_data_at: Option<Instant>,
     /// CO2 history buffer (last 10 measurements)
     co2_history: Vec<u16, 10>,
+    /// Humidity history buffer in whole percent (last 10 measurements)
+    humidity_history: Vec<u8, 10>,
+    /// Quality of the most recent readings, oldest first
+    quality_history: Vec<ReadingQuality, QUALITY_WINDOW_READINGS>,
     /// Current display mode
     display_mode: DisplayMode,
+    /// Minimum and maximum readings seen so far, across reboots
+    extremes: Extremes,
+    /// Minimum and maximum readings seen in the current session
+    session_extremes: Extremes,
+    /// Whether persisted data (extremes, display mode) changed since it was last written to flash
+    snapshot_dirty: bool,
+    /// Whether night mode (minimum brightness, dithered rendering) is active
+    night_mode: bool,
+    /// Why the sensors failed to initialize while the sensor task is in standby, retrying periodically
+    sensor_error: Option<SensorFault>,
+    /// Whether only temperature and humidity are read on a low battery, the CO2 and VOC values are from before
+    reduced_sensing: bool,
+    /// Whether the low battery warning is shown, cleared by charging or a level above the clear threshold
+    low_battery_warning: bool,
+    /// Seconds left of the ENS160 warmup at startup, `None` before it started and once the first reading arrived
+    warmup_remaining: Option<u16>,
+    /// Until when the display is frozen on the current screen, `None` while it is not frozen
+    display_frozen_until: Option<Instant>,
+    /// Whether the CO2 level crossed `CO2_ALARM_THRESHOLD` and has not dropped back below it yet
+    co2_alarm: bool,
+    /// CO2 trend derived from the readings
+    co2_trend: Co2TrendTracker,
+    /// Whether a rapid environmental change was detected since the last reading was processed
+    environment_changed: bool,
+    /// How often the CO2 level rose above `CO2_EXCEEDANCE_THRESHOLD` this session
+    co2_exceedances: u16,
+    /// Whether the CO2 level is above `CO2_EXCEEDANCE_THRESHOLD` and has not dropped back below it yet
+    co2_exceeding: bool,
+    /// When the session statistics were last reset, `None` if they run since boot
+    session_start: Option<Instant>,
+    /// Metric shown in large digits on the focus screen
+    focus_metric: FocusMetric,
+    /// Readings the focus metric stays in focus for without a further notable change
+    focus_readings_left: u8,
+    /// User configuration, persisted by `settings_store`
+    settings: Settings,
+    /// Seconds between two readings set on the serial console, `None` follows the power profile
+    read_interval_override: Option<u64>,
+}
+
+/// Which minimum and maximum readings are meant, those of the current session or those of all time
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Format)]
+#[allow(dead_code)]
+pub enum ExtremesScope {
+    /// Since power-on or the last `clear-history`, kept in RAM only
+    Session,
+    /// Across reboots, persisted to flash
+    AllTime,
+}
+
+impl ExtremesScope {
+    /// Name of the scope as used on the serial console
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Session => "session",
+            Self::AllTime => "all",
+        }
+    }
+
+    /// Parses a scope name as used on the serial console
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "session" => Some(Self::Session),
+            "all" => Some(Self::AllTime),
+            _ => None,
+        }
+    }
+}
+
+/// Minimum and maximum sensor readings, persisted to flash
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Extremes {
+    /// Lowest CO2 level in ppm
+    pub co2_min: u16,
+    /// Highest CO2 level in ppm
+    pub co2_max: u16,
+    /// Lowest temperature in degrees Celsius (display value with offset)
+    pub temperature_min: f32,
+    /// Highest temperature in degrees Celsius (display value with offset)
+    pub temperature_max: f32,
+    /// Lowest humidity in percentage (calibrated)
+    pub humidity_min: f32,
+    /// Highest humidity in percentage (calibrated)
+    pub humidity_max: f32,
+}
+
+impl Extremes {
+    /// Creates an empty set of extremes that any reading will replace
+    pub const fn new() -> Self {
+        Self {
+            co2_min: u16::MAX,
+            co2_max: u16::MIN,
+            temperature_min: f32::MAX,
+            temperature_max: f32::MIN,
+            humidity_min: f32::MAX,
+            humidity_max: f32::MIN,
+        }
+    }
+
+    /// Widens the extremes to include the given sensor data, returns whether anything changed
+    pub fn update(&mut self, data: &SensorData) -> bool {
+        let previous = *self;
+        self.co2_min = self.co2_min.min(data.co2);
+        self.co2_max = self.co2_max.max(data.co2);
+        self.temperature_min = self.temperature_min.min(data.temperature);
+        self.temperature_max = self.temperature_max.max(data.temperature);
+        self.humidity_min = self.humidity_min.min(data.humidity);
+        self.humidity_max = self.humidity_max.max(data.humidity);
+        *self != previous
+    }
+
+    /// Returns whether no reading was seen yet
+    pub const fn is_empty(&self) -> bool {
+        self.co2_min > self.co2_max
+    }
 }
 
 /// Holds the sensor data to be displayed
@@ -41,12 +423,63 @@ pub struct SensorData {
     pub humidity: f32,
     /// Raw humidity in percentage (uncalibrated)
     pub raw_humidity: f32,
+    /// Dew point in degrees Celsius, from the display temperature and the calibrated humidity
+    pub dew_point: f32,
     /// CO2 level in ppm
     pub co2: u16,
-    /// Ethanol level in ppb
-    pub etoh: u16,
+    /// Whether the raw CO2 level was implausibly low (below the atmospheric baseline)
+    pub co2_suspect: bool,
+    /// Ethanol or TVOC level in ppb, depending on `voc_kind`
+    pub voc: u16,
+    /// Which figure `voc` holds
+    pub voc_kind: VocKind,
     /// Air quality index
     pub air_quality: AirQualityIndex,
+    /// Whether the ENS160 is still settling after a large compensation change
+    pub settling: bool,
+    /// How trustworthy the reading is
+    pub quality: ReadingQuality,
+}
+
+/// CO2 level in ppm at or above which the CO2 alarm is raised
+pub const CO2_ALARM_THRESHOLD: u16 = 1500;
+
+/// How far in ppm the CO2 level has to drop below the threshold to clear the alarm, so readings hovering around the
+/// threshold do not raise it again and again
+const CO2_ALARM_HYSTERESIS: u16 = 100;
+
+/// CO2 level in ppm above which a reading counts as an exceedance in the session statistics
+pub const CO2_EXCEEDANCE_THRESHOLD: u16 = 1000;
+
+/// How far in ppm the CO2 level has to drop below the exceedance threshold before the next exceedance is counted,
+/// so a sustained exceedance hovering around the threshold counts once
+const CO2_EXCEEDANCE_HYSTERESIS: u16 = 100;
+
+/// Number of readings the worst recent quality is taken over (an hour at the normal read interval)
+const QUALITY_WINDOW_READINGS: usize = 12;
+
+/// Trustworthiness of a reading, ordered from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Format)]
+pub enum ReadingQuality {
+    /// The ENS160 is in normal operation and the reading is plausible
+    Good,
+    /// The ENS160 is warming up, or settling after a compensation change or baseline reset
+    Warmup,
+    /// The CO2 reading was implausibly low
+    Suspect,
+}
+
+impl ReadingQuality {
+    /// Composes the quality of a reading from its flags and the ENS160 validity
+    pub const fn classify(co2_suspect: bool, settling: bool, ens160_valid: bool) -> Self {
+        if co2_suspect {
+            Self::Suspect
+        } else if settling || !ens160_valid {
+            Self::Warmup
+        } else {
+            Self::Good
+        }
+    }
 }
 
 /// The Charge Level of the battery
@@ -74,16 +507,44 @@ impl SystemState {
     pub const fn new() -> Self {
         Self {
             battery_percent: 100,
-            is_charging: false,
+            charging_since: None,
             last_sensor_data: None,
+            last_sensor_data_at: None,
             co2_history: Vec::new(),
+            humidity_history: Vec::new(),
+            quality_history: Vec::new(),
             display_mode: DisplayMode::RawData,
+            extremes: Extremes::new(),
+            session_extremes: Extremes::new(),
+            snapshot_dirty: false,
+            night_mode: false,
+            sensor_error: None,
+            reduced_sensing: false,
+            low_battery_warning: false,
+            warmup_remaining: None,
+            display_frozen_until: None,
+            co2_alarm: false,
+            co2_trend: Co2TrendTracker::new(),
+            environment_changed: false,
+            co2_exceedances: 0,
+            co2_exceeding: false,
+            session_start: None,
+            focus_metric: FOCUS_DEFAULT_METRIC,
+            focus_readings_left: 0,
+            settings: Settings::new(),
+            read_interval_override: None,
         }
     }
 
     /// Sets the last sensor data
-    pub const fn set_last_sensor_data(&mut self, data: SensorData) {
+    pub fn set_last_sensor_data(&mut self, data: SensorData) {
         self.last_sensor_data = Some(data);
+        self.last_sensor_data_at = Some(Instant::now());
+    }
+
+    /// Gets how long ago the last sensor data arrived
+    pub fn get_last_sensor_data_age(&self) -> Option<Duration> {
+        self.last_sensor_data_at.map(|at| at.elapsed())
     }
 
     /// Sets the battery percentage
@@ -91,9 +552,23 @@ impl SystemState {
         self.battery_percent = percent;
     }
 
+    /// Gets the battery percentage
+    pub const fn get_battery_percent(&self) -> u8 {
+        self.battery_percent
+    }
+
     /// Sets the charging state
-    pub const fn set_charging(&mut self, is_charging: bool) {
-        self.is_charging = is_charging;
+    pub fn set_charging(&mut self, is_charging: bool) {
+        if !is_charging {
+            self.charging_since = None;
+        } else if self.charging_since.is_none() {
+            self.charging_since = Some(Instant::now());
+        }
+    }
+
+    /// Gets when charging started, `None` while on battery
+    pub const fn get_charging_since(&self) -> Option<Instant> {
+        self.charging_since
     }
 
     /// Adds a CO2 measurement to the history buffer
@@ -104,14 +579,117 @@ impl SystemState {
         }
         // Add the new measurement (ignore if push fails - shouldn't happen due to above check)
         let _ = self.co2_history.push(co2);
+        self.co2_trend.update(co2);
     }
 
-    /// Toggles the display mode between raw data and CO2 history
-    pub const fn toggle_display_mode(&mut self) {
-        self.display_mode = match self.display_mode {
-            DisplayMode::RawData => DisplayMode::Co2History,
-            DisplayMode::Co2History => DisplayMode::RawData,
-        };
+    /// Adds a humidity measurement to the history buffer, rounded to whole percent
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn add_humidity_measurement(&mut self, humidity: f32) {
+        if self.humidity_history.is_full() {
+            self.humidity_history.remove(0);
+        }
+        let _ = self.humidity_history.push((humidity + 0.5).clamp(0.0, 100.0) as u8);
+    }
+
+    /// Clears the CO2 and humidity history, the CO2 trend and the reading quality window, e.g. at the start of an
+    /// experiment. The all-time extremes are kept, see `reset_extremes`
+    pub fn clear_co2_history(&mut self) {
+        self.co2_history.clear();
+        self.humidity_history.clear();
+        self.quality_history.clear();
+        self.co2_trend = Co2TrendTracker::new();
+    }
+
+    /// Restarts the CO2 trend from the next reading, dropping the smoothed level
+    pub const fn reset_co2_trend(&mut self) {
+        self.co2_trend = Co2TrendTracker::new();
+    }
+
+    /// Marks that a rapid environmental change was detected, for the next reading to pick up
+    pub const fn mark_environment_changed(&mut self) {
+        self.environment_changed = true;
+    }
+
+    /// Returns whether a rapid environmental change was detected since the last call, clearing the mark
+    pub const fn take_environment_changed(&mut self) -> bool {
+        let changed = self.environment_changed;
+        self.environment_changed = false;
+        changed
+    }
+
+    /// Gets the current CO2 trend, derived according to `CO2_TREND_MODE`
+    pub fn get_co2_trend(&self) -> Co2Trend {
+        match CO2_TREND_MODE {
+            Co2TrendMode::Smoothed => self.co2_trend.trend,
+            Co2TrendMode::PreviousReading => previous_reading_trend(&self.co2_history),
+        }
+    }
+
+    /// Updates the session and all-time extremes with new sensor data, marks the all-time ones for persisting if
+    /// they changed
+    pub fn update_extremes(&mut self, data: &SensorData) {
+        self.session_extremes.update(data);
+        if self.extremes.update(data) {
+            self.snapshot_dirty = true;
+        }
+    }
+
+    /// Resets the extremes of the given scope, the all-time ones are persisted with the next snapshot
+    pub const fn reset_extremes(&mut self, scope: ExtremesScope) {
+        match scope {
+            ExtremesScope::Session => self.session_extremes = Extremes::new(),
+            ExtremesScope::AllTime => {
+                self.extremes = Extremes::new();
+                self.snapshot_dirty = true;
+            }
+        }
+    }
+
+    /// Restores previously persisted extremes, e.g. after a reboot
+    pub const fn restore_extremes(&mut self, extremes: Extremes) {
+        self.extremes = extremes;
+    }
+
+    /// Gets the current all-time extremes
+    pub const fn get_extremes(&self) -> Extremes {
+        self.extremes
+    }
+
+    /// Gets the extremes of the given scope
+    pub const fn get_extremes_of(&self, scope: ExtremesScope) -> Extremes {
+        match scope {
+            ExtremesScope::Session => self.session_extremes,
+            ExtremesScope::AllTime => self.extremes,
+        }
+    }
+
+    /// Returns whether persisted data changed since the last call, clearing the dirty flag
+    pub const fn take_snapshot_dirty(&mut self) -> bool {
+        let dirty = self.snapshot_dirty;
+        self.snapshot_dirty = false;
+        dirty
+    }
+
+    /// Advances the display mode to the next one in `DISPLAY_ROTATION`
+    /// A mode that is not part of the rotation continues with the first mode of the rotation
+    pub fn toggle_display_mode(&mut self) {
+        let next_index = DISPLAY_ROTATION
+            .iter()
+            .position(|&mode| mode == self.display_mode)
+            .map_or(0, |index| (index + 1) % DISPLAY_ROTATION.len());
+        self.display_mode = DISPLAY_ROTATION
+            .get(next_index)
+            .copied()
+            .unwrap_or(DisplayMode::RawData);
+        // The mode only needs persisting if it is resumed after a reboot, this spares the flash otherwise
+        if matches!(STARTUP_DISPLAY_MODE, StartupDisplayMode::ResumeLast) {
+            self.snapshot_dirty = true;
+        }
+    }
+
+    /// Sets the display mode, e.g. when restoring it after a reboot
+    pub const fn set_display_mode(&mut self, mode: DisplayMode) {
+        self.display_mode = mode;
     }
 
     /// Gets the current display mode
@@ -119,15 +697,208 @@ impl SystemState {
         self.display_mode
     }
 
+    /// Sets whether night mode is active
+    pub const fn set_night_mode(&mut self, night_mode: bool) {
+        self.night_mode = night_mode;
+    }
+
+    /// Gets whether night mode is active
+    pub const fn is_night_mode(&self) -> bool {
+        self.night_mode
+    }
+
+    /// Sets the seconds left of the ENS160 warmup at startup, `None` once it is over
+    pub const fn set_warmup_remaining(&mut self, warmup_remaining: Option<u16>) {
+        self.warmup_remaining = warmup_remaining;
+    }
+
+    /// Gets the seconds left of the ENS160 warmup at startup
+    pub const fn get_warmup_remaining(&self) -> Option<u16> {
+        self.warmup_remaining
+    }
+
+    /// Sets why the sensors are in the error standby, `None` when they are not
+    pub const fn set_sensor_error(&mut self, sensor_error: Option<SensorFault>) {
+        self.sensor_error = sensor_error;
+    }
+
+    /// Gets why the sensors are in the error standby, `None` when they are not
+    pub const fn get_sensor_error(&self) -> Option<SensorFault> {
+        self.sensor_error
+    }
+
+    /// Sets whether only temperature and humidity are read on a low battery
+    pub const fn set_reduced_sensing(&mut self, reduced: bool) {
+        self.reduced_sensing = reduced;
+    }
+
+    /// Returns whether only temperature and humidity are read on a low battery
+    pub const fn is_reduced_sensing(&self) -> bool {
+        self.reduced_sensing
+    }
+
+    /// Sets whether the low battery warning is shown
+    pub const fn set_low_battery_warning(&mut self, warning: bool) {
+        self.low_battery_warning = warning;
+    }
+
+    /// Returns whether the low battery warning is shown
+    pub const fn is_low_battery_warning(&self) -> bool {
+        self.low_battery_warning
+    }
+
+    /// Freezes the display on the current screen until the given time, or unfreezes it with `None`
+    pub const fn set_display_frozen_until(&mut self, until: Option<Instant>) {
+        self.display_frozen_until = until;
+    }
+
+    /// Gets until when the display is frozen, `None` while it is not frozen
+    pub const fn get_display_frozen_until(&self) -> Option<Instant> {
+        self.display_frozen_until
+    }
+
+    /// Gets whether the display is frozen on the current screen
+    pub const fn is_display_frozen(&self) -> bool {
+        self.display_frozen_until.is_some()
+    }
+
+    /// Updates the CO2 alarm with a new reading, returns whether the alarm was just raised
+    pub const fn update_co2_alarm(&mut self, co2: u16) -> bool {
+        if self.co2_alarm {
+            if co2 < CO2_ALARM_THRESHOLD.saturating_sub(CO2_ALARM_HYSTERESIS) {
+                self.co2_alarm = false;
+            }
+            false
+        } else {
+            self.co2_alarm = co2 >= CO2_ALARM_THRESHOLD;
+            self.co2_alarm
+        }
+    }
+
+    /// Counts a reading towards the session CO2 exceedances, returns whether a new exceedance started
+    pub const fn update_co2_exceedances(&mut self, co2: u16) -> bool {
+        if self.co2_exceeding {
+            if co2 < CO2_EXCEEDANCE_THRESHOLD.saturating_sub(CO2_EXCEEDANCE_HYSTERESIS) {
+                self.co2_exceeding = false;
+            }
+            false
+        } else {
+            self.co2_exceeding = co2 > CO2_EXCEEDANCE_THRESHOLD;
+            if self.co2_exceeding {
+                self.co2_exceedances = self.co2_exceedances.saturating_add(1);
+            }
+            self.co2_exceeding
+        }
+    }
+
+    /// Gets how often the CO2 level rose above `CO2_EXCEEDANCE_THRESHOLD` this session
+    pub const fn get_co2_exceedances(&self) -> u16 {
+        self.co2_exceedances
+    }
+
+    /// Gets how long the session statistics have been running
+    pub fn get_session_duration(&self) -> Duration {
+        self.session_start.unwrap_or(Instant::MIN).elapsed()
+    }
+
+    /// Resets the session statistics and extremes, an ongoing exceedance counts again once it dropped below the
+    /// threshold
+    pub fn reset_session_stats(&mut self) {
+        self.co2_exceedances = 0;
+        self.session_extremes = Extremes::new();
+        self.session_start = Some(Instant::now());
+    }
+
+    /// Puts a metric in focus for the given number of readings, including the current one
+    pub const fn set_focus(&mut self, metric: FocusMetric, readings: u8) {
+        self.focus_metric = metric;
+        self.focus_readings_left = readings;
+    }
+
+    /// Counts a reading without a notable change, the focus returns to `FOCUS_DEFAULT_METRIC` once its readings ran out
+    pub const fn age_focus(&mut self) {
+        self.focus_readings_left = self.focus_readings_left.saturating_sub(1);
+        if self.focus_readings_left == 0 {
+            self.focus_metric = FOCUS_DEFAULT_METRIC;
+        }
+    }
+
+    /// Gets the metric shown in large digits on the focus screen
+    pub const fn get_focus_metric(&self) -> FocusMetric {
+        self.focus_metric
+    }
+
+    /// Sets the unit temperatures are displayed in, the raw values and the ENS160 compensation stay in Celsius
+    pub const fn set_temperature_unit(&mut self, unit: TemperatureUnit) {
+        self.settings.temperature_unit = unit;
+    }
+
+    /// Gets the unit temperatures are displayed in
+    pub const fn get_temperature_unit(&self) -> TemperatureUnit {
+        self.settings.temperature_unit
+    }
+
+    /// Sets the user configuration
+    pub const fn set_settings(&mut self, settings: Settings) {
+        self.settings = settings;
+    }
+
+    /// Gets the user configuration
+    pub const fn get_settings(&self) -> Settings {
+        self.settings
+    }
+
+    /// Sets the read interval in seconds set on the serial console, `None` follows the power profile again
+    /// It is not persisted, after a reboot the power profile applies
+    pub const fn set_read_interval_override(&mut self, read_interval: Option<u64>) {
+        self.read_interval_override = read_interval;
+    }
+
+    /// Gets the read interval in seconds set on the serial console, `None` while the power profile applies
+    pub const fn get_read_interval_override(&self) -> Option<u64> {
+        self.read_interval_override
+    }
+
+    /// Gets the seconds between two sensor readings in effect: the one set on the serial console, else
+    /// `CHARGING_READ_INTERVAL` while charging, else that of the power profile
+    pub fn get_read_interval(&self) -> u64 {
+        let charging_interval = CHARGING_READ_INTERVAL.filter(|_| self.charging_since.is_some());
+        self.read_interval_override
+            .or(charging_interval)
+            .unwrap_or_else(|| self.settings.power_profile.params().read_interval)
+    }
+
+    /// Adds the quality of a reading to the recent window
+    pub fn add_reading_quality(&mut self, quality: ReadingQuality) {
+        if self.quality_history.is_full() {
+            self.quality_history.remove(0);
+        }
+        let _ = self.quality_history.push(quality);
+    }
+
+    /// Gets the worst quality among the recent readings, `Good` without readings
+    pub fn get_worst_recent_quality(&self) -> ReadingQuality {
+        self.quality_history
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(ReadingQuality::Good)
+    }
+
     /// Gets the CO2 history for drawing charts
     pub fn get_co2_history(&self) -> &[u16] {
         &self.co2_history
     }
 
+    /// Gets the humidity history for drawing charts
+    pub fn get_humidity_history(&self) -> &[u8] {
+        &self.humidity_history
+    }
+
     /// Returns the current battery level based on the battery percentage and charging state
     /// Attempts to compensate for the fact that the voltage of the battery does not change linearly but drops way steeper at the end
     pub const fn get_battery_level(&self) -> BatteryLevel {
-        if self.is_charging {
+        if self.charging_since.is_some() {
             BatteryLevel::Charging
         } else {
             match self.battery_percent {
@@ -141,3 +912,55 @@ impl SystemState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oscillating_co2_stays_flat() {
+        let mut tracker = Co2TrendTracker::new();
+        for reading in 0..50 {
+            let co2 = if reading % 2 == 0 { 785 } else { 815 };
+            assert_eq!(tracker.update(co2), Co2Trend::Flat, "reading {reading} of {co2} ppm");
+        }
+    }
+
+    #[test]
+    fn sustained_rise_flips_after_the_confirmations() {
+        let mut tracker = Co2TrendTracker::new();
+        tracker.update(800);
+        // Every reading is 30 ppm above the smoothed level, beyond the dead band but no jump
+        for reading in 1..=u16::from(CO2_TREND_CONFIRMATIONS) {
+            let expected = if reading < u16::from(CO2_TREND_CONFIRMATIONS) {
+                Co2Trend::Flat
+            } else {
+                Co2Trend::Rising
+            };
+            assert_eq!(tracker.update(815 + 15 * reading), expected, "reading {reading}");
+        }
+    }
+
+    #[test]
+    fn previous_reading_trend_needs_a_step() {
+        assert_eq!(previous_reading_trend(&[]), Co2Trend::Flat);
+        assert_eq!(previous_reading_trend(&[800]), Co2Trend::Flat);
+        assert_eq!(previous_reading_trend(&[800, 849]), Co2Trend::Flat);
+        assert_eq!(previous_reading_trend(&[800, 751]), Co2Trend::Flat);
+        assert_eq!(previous_reading_trend(&[800, 850]), Co2Trend::Rising);
+        assert_eq!(previous_reading_trend(&[800, 750]), Co2Trend::Falling);
+        // Only the last two readings count
+        assert_eq!(previous_reading_trend(&[400, 1200, 1210]), Co2Trend::Flat);
+    }
+
+    #[test]
+    fn jump_flips_right_away() {
+        let mut tracker = Co2TrendTracker::new();
+        tracker.update(800);
+        assert_eq!(tracker.update(850), Co2Trend::Rising);
+
+        let mut tracker = Co2TrendTracker::new();
+        tracker.update(800);
+        assert_eq!(tracker.update(750), Co2Trend::Falling);
+    }
+}
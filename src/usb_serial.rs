@@ -0,0 +1,1397 @@
+//! USB CDC ACM serial console accepting line based commands from a connected computer
+//!
+//! Commands are terminated by a newline, each is answered with one or more lines of text. Type `help` for a list.
+//!
+//! `stream on` turns the console into a simple data logger, each reading is written as a JSON line until `stream off`
+//! or a disconnect.
+//!
+//! Until the user settings have been set up, connecting starts a guided setup instead, which asks for each setting
+//! in turn and persists the answers. Pressing Escape skips it and keeps the defaults.
+
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use defmt::info;
+#[cfg(feature = "debug-serial")]
+use defmt::warn;
+use embassy_executor::Spawner;
+#[cfg(not(any(feature = "trace-events", feature = "health-summary")))]
+use embassy_futures::select::{Either3, select3};
+#[cfg(any(feature = "trace-events", feature = "health-summary"))]
+use embassy_futures::select::{Either4, select4};
+#[cfg(target_os = "none")]
+use embassy_rp::{peripherals::USB, usb::Driver};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel, signal::Signal};
+use embassy_time::Instant;
+#[cfg(target_os = "none")]
+use embassy_usb::{
+    Builder, Config as UsbConfig, UsbDevice,
+    class::cdc_acm::{CdcAcmClass, State},
+    driver::EndpointError,
+};
+#[cfg(feature = "debug-serial")]
+use ens160_aq::data::AirQualityIndex;
+use heapless::String;
+#[cfg(target_os = "none")]
+use static_cell::StaticCell;
+
+#[cfg(feature = "rtc")]
+use crate::rtc::{self, DateTime};
+use crate::{
+    FIRMWARE_VERSION,
+    datalog::{self, Granularity},
+    diagnostics::{BusDevice, get_diagnostics},
+    display::{DISPLAY_FREEZE_TIMEOUT, aqi_to_index},
+    event::{Event, send_event},
+    humidity_calibrator::HumidityClimate,
+    i2c_speed,
+    power_profile::{MAX_READ_INTERVAL, PowerProfile},
+    sensor::{Ens160RegisterDump, MIN_READ_INTERVAL, request_ens160_register_dump, request_fresh_air_baseline},
+    settings_store::{self, Co2CorrectionPoint, Settings},
+    storage_mode,
+    system_state::{
+        CO2_EXCEEDANCE_THRESHOLD, Extremes, ExtremesScope, SYSTEM_STATE, SensorData, TemperatureUnit, VocKind,
+    },
+    verbosity::{set_verbose, should_log_verbose},
+    watchdog,
+};
+#[cfg(feature = "debug-serial")]
+use crate::{diagnostics::get_loop_timing, display::render_screenshot, sensor::dew_point, watchdog::TaskId};
+
+/// The USB driver type used by the serial console
+#[cfg(target_os = "none")]
+pub type UsbDriver = Driver<'static, USB>;
+
+/// USB vendor ID (test VID, this device is not meant for distribution)
+const USB_VID: u16 = 0xc0de;
+
+/// USB product ID
+const USB_PID: u16 = 0xcafe;
+
+/// Maximum USB packet size for the CDC ACM endpoints
+const MAX_PACKET_SIZE: u16 = 64;
+
+/// Maximum length of a command line, longer lines are discarded
+const LINE_CAPACITY: usize = 64;
+
+/// Maximum length of a command response
+const RESPONSE_CAPACITY: usize = 1536;
+
+/// Key that skips the guided setup (Escape)
+const SKIP_SETUP_KEY: u8 = 0x1B;
+
+/// Maximum length of an alert line
+const ALERT_LINE_CAPACITY: usize = 128;
+
+/// Latest alert waiting to be written to the serial console
+static ALERT_SIGNAL: Signal<CriticalSectionRawMutex, String<ALERT_LINE_CAPACITY>> = Signal::new();
+
+/// Maximum length of a streamed reading
+const READING_LINE_CAPACITY: usize = 96;
+
+/// Number of streamed readings buffered for the serial console, further readings are dropped
+const READING_CHANNEL_CAPACITY: usize = 4;
+
+/// Streamed readings waiting to be written to the serial console
+static READING_CHANNEL: Channel<CriticalSectionRawMutex, String<READING_LINE_CAPACITY>, READING_CHANNEL_CAPACITY> =
+    Channel::new();
+
+/// Whether the connected console asked for the readings with `stream on`, cleared on every connection
+static STREAM_READINGS: AtomicBool = AtomicBool::new(false);
+
+/// Maximum length of an event trace or health summary line, sensor data is the longest event
+#[cfg(any(feature = "trace-events", feature = "health-summary"))]
+const TRACE_LINE_CAPACITY: usize = 256;
+
+/// Number of event trace and health summary lines buffered for the serial console, further lines are dropped
+#[cfg(any(feature = "trace-events", feature = "health-summary"))]
+const TRACE_CHANNEL_CAPACITY: usize = 8;
+
+/// Event trace and health summary lines waiting to be written to the serial console
+#[cfg(any(feature = "trace-events", feature = "health-summary"))]
+static TRACE_CHANNEL: Channel<CriticalSectionRawMutex, String<TRACE_LINE_CAPACITY>, TRACE_CHANNEL_CAPACITY> =
+    Channel::new();
+
+/// Tasks whose loop timing the timing command prints, with their names
+#[cfg(feature = "debug-serial")]
+const TIMED_TASKS: [(&str, TaskId); 3] = [
+    ("sensor", TaskId::Sensor),
+    ("display", TaskId::Display),
+    ("vsys", TaskId::Vsys),
+];
+
+/// Queues an event for the trace on the serial console, prefixed with the uptime in seconds
+/// Never waits, when the console does not keep up the event is left out of the trace, so tracing cannot change the
+/// timing it is meant to show
+#[cfg(feature = "trace-events")]
+pub fn trace_event(event: &crate::event::Event) {
+    let now = Instant::now();
+    let mut trace_line: String<TRACE_LINE_CAPACITY> = String::new();
+    let _ = write!(
+        trace_line,
+        "[{}.{:03}] {:?}\r\n",
+        now.as_secs(),
+        now.as_millis() % 1000,
+        event
+    );
+    // Also fills up while no console is connected, the stale lines are cleared on connection
+    let _ = TRACE_CHANNEL.try_send(trace_line);
+}
+
+/// Queues the health summary of a sensor cycle for the serial console, prefixed with the uptime in seconds
+/// Never waits, like the event trace it shares the queue with
+#[cfg(feature = "health-summary")]
+pub fn send_health_summary(summary: &str) {
+    let now = Instant::now();
+    let mut summary_line: String<TRACE_LINE_CAPACITY> = String::new();
+    let _ = write!(
+        summary_line,
+        "[{}.{:03}] {summary}\r\n",
+        now.as_secs(),
+        now.as_millis() % 1000
+    );
+    let _ = TRACE_CHANNEL.try_send(summary_line);
+}
+
+/// Queues a reading for the serial console as a JSON line, if the console asked for them
+/// Never waits, a host that does not read them (or went away) only loses readings, the sensor pipeline goes on
+pub fn stream_reading(sensor_data: &SensorData) {
+    if !STREAM_READINGS.load(Ordering::Relaxed) {
+        return;
+    }
+    let voc_key = match sensor_data.voc_kind {
+        VocKind::Ethanol => "etoh",
+        VocKind::Tvoc => "tvoc",
+    };
+    let mut reading_line: String<READING_LINE_CAPACITY> = String::new();
+    let _ = write!(
+        reading_line,
+        "{{\"t\":{:.1},\"rh\":{:.1},\"co2\":{},\"{voc_key}\":{},\"aqi\":{}}}\r\n",
+        sensor_data.temperature,
+        sensor_data.humidity,
+        sensor_data.co2,
+        sensor_data.voc,
+        aqi_to_index(sensor_data.air_quality)
+    );
+    let _ = READING_CHANNEL.try_send(reading_line);
+}
+
+/// Queues an alert for the serial console, prefixed with the uptime in seconds
+/// Only the latest alert is kept, and one raised while no console is connected is dropped on the next connection
+pub fn send_alert(message: &str) {
+    let now = Instant::now();
+    let mut alert_line: String<ALERT_LINE_CAPACITY> = String::new();
+    let _ = write!(
+        alert_line,
+        "[{}.{:03}] alert: {message}\r\n",
+        now.as_secs(),
+        now.as_millis() % 1000
+    );
+    ALERT_SIGNAL.signal(alert_line);
+}
+
+/// Sets up the USB device with a CDC ACM class and spawns the tasks running it
+#[cfg(target_os = "none")]
+pub fn init(spawner: Spawner, driver: UsbDriver) {
+    static CONFIG_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+    static BOS_DESCRIPTOR: StaticCell<[u8; 256]> = StaticCell::new();
+    static CONTROL_BUF: StaticCell<[u8; 64]> = StaticCell::new();
+    static CDC_STATE: StaticCell<State<'static>> = StaticCell::new();
+
+    let mut config = UsbConfig::new(USB_VID, USB_PID);
+    config.manufacturer = Some("1-rafael-1");
+    config.product = Some("Air Quality Monitor");
+    config.max_power = 100;
+    config.max_packet_size_0 = 64;
+
+    let mut builder = Builder::new(
+        driver,
+        config,
+        CONFIG_DESCRIPTOR.init([0; 256]),
+        BOS_DESCRIPTOR.init([0; 256]),
+        &mut [],
+        CONTROL_BUF.init([0; 64]),
+    );
+    let class = CdcAcmClass::new(&mut builder, CDC_STATE.init(State::new()), MAX_PACKET_SIZE);
+    let usb = builder.build();
+
+    #[allow(clippy::unwrap_used)]
+    spawner.spawn(usb_task(usb)).unwrap();
+    #[allow(clippy::unwrap_used)]
+    spawner.spawn(serial_task(class)).unwrap();
+}
+
+/// Runs the USB device stack
+#[cfg(target_os = "none")]
+#[embassy_executor::task]
+async fn usb_task(mut usb: UsbDevice<'static, UsbDriver>) -> ! {
+    usb.run().await
+}
+
+/// Serial console task, reads command lines from the host and answers them
+#[cfg(target_os = "none")]
+#[embassy_executor::task]
+async fn serial_task(mut class: CdcAcmClass<'static, UsbDriver>) {
+    loop {
+        class.wait_connection().await;
+        info!("USB serial connected");
+        // Connecting again is the way out of storage mode, the console stays usable until the reset
+        if storage_mode::is_active().await {
+            watchdog::request_reset();
+        }
+        // Only returns on an endpoint error, which means the host went away
+        let _ = handle_connection(&mut class).await;
+        info!("USB serial disconnected");
+    }
+}
+
+/// Reads bytes into a line buffer and runs each completed line as a command, or as an answer while the guided
+/// setup is running
+#[cfg(target_os = "none")]
+async fn handle_connection(class: &mut CdcAcmClass<'static, UsbDriver>) -> Result<(), EndpointError> {
+    let mut packet = [0u8; MAX_PACKET_SIZE as usize];
+    let mut line: String<LINE_CAPACITY> = String::new();
+    let mut overflow = false;
+    let mut previous_byte = 0u8;
+
+    let settings = SYSTEM_STATE.lock().await.get_settings();
+    let mut setup = (!settings.setup_done).then(|| Setup::new(settings));
+    if let Some(ref setup) = setup {
+        let mut response: String<RESPONSE_CAPACITY> = String::new();
+        if settings_store::take_settings_reset() {
+            let _ = writeln!(
+                response,
+                "The stored settings were invalid (corrupt or from an incompatible firmware) and were reset\r"
+            );
+        }
+        let _ = writeln!(response, "air-quality-monitor {FIRMWARE_VERSION} first boot setup\r");
+        let _ = writeln!(response, "Press Esc to skip and keep the defaults\r");
+        setup.prompt(&mut response);
+        write_response(class, &response).await?;
+    }
+
+    // Events and alerts sent while nobody was listening are stale, a new connection has to ask for readings again
+    #[cfg(any(feature = "trace-events", feature = "health-summary"))]
+    TRACE_CHANNEL.clear();
+    ALERT_SIGNAL.reset();
+    STREAM_READINGS.store(false, Ordering::Relaxed);
+    READING_CHANNEL.clear();
+
+    loop {
+        #[cfg(any(feature = "trace-events", feature = "health-summary"))]
+        let len = match select4(
+            class.read_packet(&mut packet),
+            TRACE_CHANNEL.receive(),
+            ALERT_SIGNAL.wait(),
+            READING_CHANNEL.receive(),
+        )
+        .await
+        {
+            Either4::First(len) => len?,
+            Either4::Second(trace_line) => {
+                write_response(class, &trace_line).await?;
+                continue;
+            }
+            Either4::Third(alert_line) => {
+                write_response(class, &alert_line).await?;
+                continue;
+            }
+            Either4::Fourth(reading_line) => {
+                write_response(class, &reading_line).await?;
+                continue;
+            }
+        };
+        #[cfg(not(any(feature = "trace-events", feature = "health-summary")))]
+        let len = match select3(
+            class.read_packet(&mut packet),
+            ALERT_SIGNAL.wait(),
+            READING_CHANNEL.receive(),
+        )
+        .await
+        {
+            Either3::First(len) => len?,
+            Either3::Second(alert_line) => {
+                write_response(class, &alert_line).await?;
+                continue;
+            }
+            Either3::Third(reading_line) => {
+                write_response(class, &reading_line).await?;
+                continue;
+            }
+        };
+
+        for &byte in &packet[..len] {
+            if byte == SKIP_SETUP_KEY && setup.take().is_some() {
+                let response = finish_setup(Settings::new(), "Setup skipped, using defaults").await;
+                write_response(class, &response).await?;
+                line.clear();
+            } else if byte == b'\n' && previous_byte == b'\r' {
+                // Second half of a CRLF line ending
+            } else if byte == b'\r' || byte == b'\n' {
+                if overflow {
+                    write_response(class, "error: line too long\r\n").await?;
+                } else if let Some(ref mut active_setup) = setup {
+                    // Empty answers are meaningful during setup, they keep the default
+                    let mut response: String<RESPONSE_CAPACITY> = String::new();
+                    if let Some(settings) = active_setup.answer(line.trim(), &mut response).await {
+                        setup = None;
+                        response = finish_setup(settings, "Setup complete").await;
+                    }
+                    write_response(class, &response).await?;
+                } else if !line.trim().is_empty() {
+                    run_line(class, line.trim()).await?;
+                }
+                line.clear();
+                overflow = false;
+            } else if line.push(char::from(byte)).is_err() {
+                overflow = true;
+            }
+            previous_byte = byte;
+        }
+    }
+}
+
+/// Runs a command line and writes its response
+#[cfg(target_os = "none")]
+async fn run_line(class: &mut CdcAcmClass<'static, UsbDriver>, line: &str) -> Result<(), EndpointError> {
+    // A screenshot does not fit a response, it is streamed line by line instead
+    #[cfg(feature = "debug-serial")]
+    if line == "screenshot" {
+        return write_screenshot(class).await;
+    }
+
+    // An export does not fit a response either, it is streamed in batches
+    if let Ok(Command::Export(granularity)) = parse_command(line) {
+        return write_export(class, granularity).await;
+    }
+
+    let response = run_command(line).await;
+    write_response(class, &response).await
+}
+
+/// Writes the datalog as CSV, one row per reading or per averaged bucket, framed by a header and an end line
+/// Rows are stamped with the uptime, with the RTC the current time is given in the header to convert it
+#[cfg(target_os = "none")]
+async fn write_export(
+    class: &mut CdcAcmClass<'static, UsbDriver>,
+    granularity: Granularity,
+) -> Result<(), EndpointError> {
+    /// Maximum length of a CSV row
+    const ROW_CAPACITY: usize = 64;
+
+    let mut header: String<RESPONSE_CAPACITY> = String::new();
+    let _ = writeln!(
+        header,
+        "export {}, uptime now {}s\r",
+        granularity.name(),
+        Instant::now().as_secs()
+    );
+    #[cfg(feature = "rtc")]
+    if let Some(date_time) = rtc::now().await {
+        let _ = writeln!(header, "time now {date_time}\r");
+    }
+    let _ = writeln!(header, "uptime_s,co2_ppm,temperature_c,humidity_pct,samples\r");
+    write_response(class, &header).await?;
+
+    let mut after_secs = None;
+    loop {
+        let rows = datalog::export_batch(granularity, after_secs).await;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        after_secs = Some(last.start_secs);
+
+        for row in &rows {
+            let mut csv_row: String<ROW_CAPACITY> = String::new();
+            let _ = write!(
+                csv_row,
+                "{},{},{:.1},{:.1},{}\r\n",
+                row.start_secs, row.co2, row.temperature, row.humidity, row.samples
+            );
+            write_response(class, &csv_row).await?;
+        }
+    }
+    write_response(class, "end\r\n").await
+}
+
+/// Writes the current screen as hex, 32 bytes per line in the SSD1306 memory layout (8 pages of 128 columns,
+/// each byte a column of 8 pixels with the top pixel in bit 0), framed by a header and an end line
+#[cfg(all(feature = "debug-serial", target_os = "none"))]
+async fn write_screenshot(class: &mut CdcAcmClass<'static, UsbDriver>) -> Result<(), EndpointError> {
+    /// Bytes per hex line
+    const BYTES_PER_LINE: usize = 32;
+
+    let screenshot = match render_screenshot().await {
+        Ok(screenshot) => screenshot,
+        Err(e) => {
+            let mut response: String<RESPONSE_CAPACITY> = String::new();
+            let _ = writeln!(response, "error: {e}\r");
+            return write_response(class, &response).await;
+        }
+    };
+
+    write_response(class, "screenshot 128x64 ssd1306\r\n").await?;
+    for bytes in screenshot.chunks(BYTES_PER_LINE) {
+        let mut hex_line: String<{ BYTES_PER_LINE * 2 + 2 }> = String::new();
+        for byte in bytes {
+            let _ = write!(hex_line, "{byte:02x}");
+        }
+        let _ = write!(hex_line, "\r\n");
+        write_response(class, &hex_line).await?;
+    }
+    write_response(class, "end\r\n").await
+}
+
+/// Steps of the guided setup, in order
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SetupStep {
+    /// Ask for the temperature unit
+    TemperatureUnit,
+    /// Ask for a reference temperature to derive the temperature offset from
+    TemperatureReference,
+    /// Ask for a reference humidity to derive the user humidity offset from
+    HumidityReference,
+    /// Ask for a reference CO2 level to derive a correction point from
+    Co2Reference,
+    /// Ask for the quiet hours, only with an RTC to schedule them
+    #[cfg(feature = "rtc")]
+    QuietHours,
+}
+
+impl SetupStep {
+    /// The step following this one, `None` after the last step
+    const fn next(self) -> Option<Self> {
+        match self {
+            Self::TemperatureUnit => Some(Self::TemperatureReference),
+            Self::TemperatureReference => Some(Self::HumidityReference),
+            Self::HumidityReference => Some(Self::Co2Reference),
+            #[cfg(feature = "rtc")]
+            Self::Co2Reference => Some(Self::QuietHours),
+            #[cfg(not(feature = "rtc"))]
+            Self::Co2Reference => None,
+            #[cfg(feature = "rtc")]
+            Self::QuietHours => None,
+        }
+    }
+}
+
+/// Guided first boot setup, collects the settings one answer at a time
+struct Setup {
+    /// Step waiting for an answer
+    step: SetupStep,
+    /// Settings collected so far
+    settings: Settings,
+}
+
+impl Setup {
+    /// Starts the setup with the current settings as defaults
+    const fn new(settings: Settings) -> Self {
+        Self {
+            step: SetupStep::TemperatureUnit,
+            settings,
+        }
+    }
+
+    /// Writes the question of the current step
+    fn prompt(&self, response: &mut String<RESPONSE_CAPACITY>) {
+        let unit = self.settings.temperature_unit;
+        let _ = match self.step {
+            SetupStep::TemperatureUnit => write!(response, "Temperature unit, c or f [{}]: ", unit.symbol()),
+            SetupStep::TemperatureReference => write!(
+                response,
+                "Temperature in {} from a reference thermometer, empty keeps the offset of {:.1} C: ",
+                unit.symbol(),
+                self.settings.temperature_offset
+            ),
+            SetupStep::HumidityReference => write!(
+                response,
+                "Humidity in % from a reference hygrometer, empty keeps the offset of {:.1}%: ",
+                self.settings.user_humidity_offset
+            ),
+            SetupStep::Co2Reference => write!(
+                response,
+                "CO2 in ppm from a reference (e.g. 420 by an open window), empty skips: "
+            ),
+            #[cfg(feature = "rtc")]
+            SetupStep::QuietHours => match self.settings.quiet_hours {
+                Some((start, end)) => write!(
+                    response,
+                    "Quiet hours as start-end (e.g. 22-7) or off [{start}-{end}]: "
+                ),
+                None => write!(response, "Quiet hours as start-end (e.g. 22-7) or off [off]: "),
+            },
+        };
+    }
+
+    /// Takes the answer for the current step and writes the next question
+    /// Returns the collected settings once the last step was answered
+    async fn answer(&mut self, answer: &str, response: &mut String<RESPONSE_CAPACITY>) -> Option<Settings> {
+        let _ = write!(response, "\r\n");
+        // An empty answer keeps the default
+        let result = if answer.is_empty() {
+            Ok(())
+        } else {
+            self.apply(answer, response).await
+        };
+        if let Err(e) = result {
+            let _ = writeln!(response, "error: {e}\r");
+            self.prompt(response);
+            return None;
+        }
+
+        if let Some(step) = self.step.next() {
+            self.step = step;
+            self.prompt(response);
+            None
+        } else {
+            Some(self.settings)
+        }
+    }
+
+    /// Applies a non-empty answer to the settings
+    async fn apply(&mut self, answer: &str, response: &mut String<RESPONSE_CAPACITY>) -> Result<(), &'static str> {
+        let last_sensor_data = SYSTEM_STATE.lock().await.last_sensor_data.clone();
+        match self.step {
+            SetupStep::TemperatureUnit => {
+                self.settings.temperature_unit = match answer {
+                    "c" | "C" => TemperatureUnit::Celsius,
+                    "f" | "F" => TemperatureUnit::Fahrenheit,
+                    _ => return Err("expected c or f"),
+                };
+            }
+            SetupStep::TemperatureReference => {
+                let reference: f32 = answer.parse().map_err(|_| "expected a temperature, e.g. 21.5")?;
+                let reference = self.settings.temperature_unit.to_celsius(reference);
+                // Without a reading yet there is nothing to compare against, the offset stays as is
+                if let Some(data) = last_sensor_data {
+                    self.settings.temperature_offset = reference - data.raw_temperature;
+                    let _ = writeln!(response, "offset set to {:.1} C\r", self.settings.temperature_offset);
+                } else {
+                    let _ = writeln!(response, "no reading yet, keeping the offset\r");
+                }
+            }
+            SetupStep::HumidityReference => {
+                let reference = parse_humidity(answer)?;
+                if let Some(data) = last_sensor_data {
+                    let offset = self.settings.calibrate_humidity_offset(reference, data.humidity);
+                    let _ = writeln!(response, "offset set to {offset:.1}%\r");
+                } else {
+                    let _ = writeln!(response, "no reading yet, keeping the offset\r");
+                }
+            }
+            SetupStep::Co2Reference => {
+                let reference: u16 = answer.parse().map_err(|_| "expected a CO2 level in ppm, e.g. 420")?;
+                if let Some(data) = last_sensor_data {
+                    let point = Co2CorrectionPoint {
+                        measured: data.co2,
+                        reference,
+                    };
+                    self.settings.co2_correction = [Some(point), None];
+                    let _ = writeln!(response, "correction {} -> {} ppm\r", point.measured, point.reference);
+                } else {
+                    let _ = writeln!(response, "no reading yet, skipping CO2 correction\r");
+                }
+            }
+            #[cfg(feature = "rtc")]
+            SetupStep::QuietHours => {
+                self.settings.quiet_hours = if answer == "off" {
+                    None
+                } else {
+                    let (start, end) = answer.split_once('-').ok_or("expected start-end or off")?;
+                    let start: u8 = start.parse().map_err(|_| "expected start-end or off")?;
+                    let end: u8 = end.parse().map_err(|_| "expected start-end or off")?;
+                    if start > 23 || end > 23 {
+                        return Err("hours must be 0-23");
+                    }
+                    Some((start, end))
+                };
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Marks the setup as done, persists the settings and returns the closing message
+async fn finish_setup(mut settings: Settings, message: &str) -> String<RESPONSE_CAPACITY> {
+    settings.setup_done = true;
+    let mut response: String<RESPONSE_CAPACITY> = String::new();
+    match settings_store::apply_settings(settings).await {
+        Ok(()) => {
+            info!("Guided setup finished");
+            let _ = writeln!(response, "\r\n{message}, type help for commands\r");
+        }
+        Err(e) => {
+            let _ = writeln!(response, "\r\nerror: saving settings failed: {e}\r");
+        }
+    }
+    response
+}
+
+/// Writes a line with the minimum and maximum readings of a scope, or that there are none yet
+fn write_extremes(response: &mut String<RESPONSE_CAPACITY>, scope: ExtremesScope, extremes: &Extremes) {
+    if extremes.is_empty() {
+        let _ = writeln!(response, "{}: no readings yet\r", scope.name());
+        return;
+    }
+    let _ = writeln!(
+        response,
+        "{}: co2 {}-{} ppm, {:.1}-{:.1} C, {:.0}-{:.0} %\r",
+        scope.name(),
+        extremes.co2_min,
+        extremes.co2_max,
+        extremes.temperature_min,
+        extremes.temperature_max,
+        extremes.humidity_min,
+        extremes.humidity_max
+    );
+}
+
+/// Writes the ENS160 register dump, one register per line
+fn write_ens160_registers(response: &mut String<RESPONSE_CAPACITY>, dump: &Ens160RegisterDump) {
+    let _ = writeln!(response, "part id: {:#06x}\r", dump.part_id);
+    let _ = writeln!(response, "status: {:?}\r", dump.status);
+    let _ = writeln!(response, "validity: {:?}\r", dump.validity);
+    let _ = writeln!(response, "eco2: {} ppm\r", dump.eco2);
+    let _ = writeln!(response, "tvoc: {} ppb\r", dump.tvoc);
+    let _ = writeln!(response, "ethanol: {} ppb\r", dump.etoh);
+    let _ = writeln!(response, "aqi: {:?}\r", dump.air_quality);
+}
+
+/// Writes a response to the host, split into packets
+#[cfg(target_os = "none")]
+async fn write_response(class: &mut CdcAcmClass<'static, UsbDriver>, response: &str) -> Result<(), EndpointError> {
+    let bytes = response.as_bytes();
+    for chunk in bytes.chunks(MAX_PACKET_SIZE as usize) {
+        class.write_packet(chunk).await?;
+    }
+    // A transfer ending on a full packet needs a zero length packet to be completed on the host
+    if !bytes.is_empty() && bytes.len() % MAX_PACKET_SIZE as usize == 0 {
+        class.write_packet(&[]).await?;
+    }
+    Ok(())
+}
+
+/// A command accepted on the serial console
+enum Command {
+    /// List the available commands
+    Help,
+    /// Print the diagnostics counters
+    Diagnostics,
+    /// Switch night mode on or off
+    NightMode(bool),
+    /// Freeze the display on the current screen, or unfreeze it if it is frozen
+    Freeze,
+    /// Clear the display and repaint the current screen
+    Redraw,
+    /// Switch the displayed temperature unit between Celsius and Fahrenheit
+    ToggleUnit,
+    /// Print the power profile, or select a new one
+    Profile(Option<PowerProfile>),
+    /// Print the read interval, or set one in seconds (0 follows the power profile again)
+    Interval(Option<u32>),
+    /// Print the climate of the humidity model, or select a new one
+    Climate(Option<HumidityClimate>),
+    /// Print the volatile organic compound figure shown, or select a new one
+    Voc(Option<VocKind>),
+    /// Switch the long-term statistical humidity correction on or off
+    LongTermCorrection(bool),
+    /// Switch the adaptive humidity calibration on or off
+    AdaptiveHumidity(bool),
+    /// Print the user humidity offset, or derive it from a reference humidity
+    HumidityOffset(Option<f32>),
+    /// Remove the user humidity offset
+    ClearHumidityOffset,
+    /// Print whether the per-cycle logs are on, or switch them on or off
+    Verbose(Option<bool>),
+    /// Reset the ENS160 baseline to the current air, which should be fresh air
+    FreshAirBaseline,
+    /// Print the key ENS160 registers
+    Ens160Registers,
+    /// Shut down into storage mode until the serial console is connected again
+    StorageMode,
+    /// Reset all stored data and settings to the defaults
+    FactoryReset,
+    /// Export the datalog as CSV, streamed by `run_line` as it does not fit a response
+    Export(Granularity),
+    /// Start or stop writing each reading as a JSON line
+    Stream(bool),
+    /// Clear the CO2 history, the session statistics and the datalog
+    ClearHistory,
+    /// Print the session statistics
+    Stats,
+    /// Print the session and all-time minimum and maximum readings
+    Extremes,
+    /// Reset the minimum and maximum readings of the given scope
+    ResetExtremes(ExtremesScope),
+    /// Print the current time
+    Time,
+    /// Set the real time clock
+    #[cfg(feature = "rtc")]
+    SetTime(DateTime),
+    /// Print the loop iteration timing of the sensor, display and VSYS tasks
+    #[cfg(feature = "debug-serial")]
+    Timing,
+    /// Inject synthetic sensor data as if it came from the sensors
+    #[cfg(feature = "debug-serial")]
+    Simulate {
+        /// CO2 level in ppm
+        co2: u16,
+        /// Temperature in degrees Celsius
+        temperature: f32,
+        /// Humidity in percentage
+        humidity: f32,
+        /// Ethanol or TVOC level in ppb, whichever is selected
+        voc: u16,
+        /// Air quality index
+        air_quality: AirQualityIndex,
+    },
+}
+
+/// Parses a command line into a command
+fn parse_command(line: &str) -> Result<Command, &'static str> {
+    let mut args = line.split_whitespace();
+    let command = match args.next() {
+        Some("help") => Command::Help,
+        Some("diag") => Command::Diagnostics,
+        Some("factory-reset") => Command::FactoryReset,
+        Some("baseline") => Command::FreshAirBaseline,
+        Some("ens160") => Command::Ens160Registers,
+        Some("storage") => Command::StorageMode,
+        Some("export") => match args.next() {
+            None => Command::Export(Granularity::Raw),
+            Some(name) => Command::Export(Granularity::from_name(name).ok_or("usage: export [raw|5m|1h]")?),
+        },
+        Some("stream") => match args.next() {
+            Some("on") => Command::Stream(true),
+            Some("off") => Command::Stream(false),
+            _ => return Err("usage: stream on|off"),
+        },
+        Some("clear-history") => Command::ClearHistory,
+        Some("stats") => Command::Stats,
+        Some("extremes") => match (args.next(), args.next().and_then(ExtremesScope::from_name)) {
+            (None, _) => Command::Extremes,
+            (Some("reset"), Some(scope)) => Command::ResetExtremes(scope),
+            _ => return Err("usage: extremes [reset session|all]"),
+        },
+        Some("freeze") => Command::Freeze,
+        Some("redraw") => Command::Redraw,
+        Some("unit") => Command::ToggleUnit,
+        Some("night") => match args.next() {
+            Some("on") => Command::NightMode(true),
+            Some("off") => Command::NightMode(false),
+            _ => return Err("usage: night on|off"),
+        },
+        Some("profile") => match args.next() {
+            None => Command::Profile(None),
+            Some(name) => Command::Profile(Some(
+                PowerProfile::from_name(name).ok_or("usage: profile [eco|normal|performance]")?,
+            )),
+        },
+        Some("interval") => match args.next() {
+            None => Command::Interval(None),
+            Some("off") => Command::Interval(Some(0)),
+            Some(secs) => Command::Interval(Some(parse_read_interval(secs)?)),
+        },
+        Some("climate") => match args.next() {
+            None => Command::Climate(None),
+            Some(name) => Command::Climate(Some(
+                HumidityClimate::from_name(name).ok_or("usage: climate [arid|temperate|humid]")?,
+            )),
+        },
+        Some("voc") => match args.next() {
+            None => Command::Voc(None),
+            Some(name) => Command::Voc(Some(VocKind::from_name(name).ok_or("usage: voc [ethanol|tvoc]")?)),
+        },
+        Some("longterm") => match args.next() {
+            Some("on") => Command::LongTermCorrection(true),
+            Some("off") => Command::LongTermCorrection(false),
+            _ => return Err("usage: longterm on|off"),
+        },
+        Some("adaptive") => match args.next() {
+            Some("on") => Command::AdaptiveHumidity(true),
+            Some("off") => Command::AdaptiveHumidity(false),
+            _ => return Err("usage: adaptive on|off"),
+        },
+        Some("humidity") => match args.next() {
+            None => Command::HumidityOffset(None),
+            Some("off") => Command::ClearHumidityOffset,
+            Some(reference) => Command::HumidityOffset(Some(parse_humidity(reference)?)),
+        },
+        Some("verbose") => match args.next() {
+            None => Command::Verbose(None),
+            Some("on") => Command::Verbose(Some(true)),
+            Some("off") => Command::Verbose(Some(false)),
+            _ => return Err("usage: verbose [on|off]"),
+        },
+        #[cfg(feature = "rtc")]
+        Some("time") => match (args.next(), args.next()) {
+            (None, _) => Command::Time,
+            (Some(date), Some(time)) => Command::SetTime(parse_date_time(date, time)?),
+            _ => return Err("usage: time [YYYY-MM-DD HH:MM:SS]"),
+        },
+        #[cfg(not(feature = "rtc"))]
+        Some("time") => Command::Time,
+        #[cfg(feature = "debug-serial")]
+        Some("sim") => parse_simulate(&mut args)?,
+        #[cfg(feature = "debug-serial")]
+        Some("timing") => Command::Timing,
+        _ => return Err("unknown command, type help"),
+    };
+
+    if args.next().is_some() {
+        return Err("too many arguments");
+    }
+    Ok(command)
+}
+
+/// Parses a relative humidity in percent
+fn parse_humidity(humidity: &str) -> Result<f32, &'static str> {
+    match humidity.parse::<f32>() {
+        Ok(humidity) if (0.0..=100.0).contains(&humidity) => Ok(humidity),
+        _ => Err("expected a humidity in %, e.g. 45"),
+    }
+}
+
+/// Text before, between and after the bounds in `READ_INTERVAL_ERROR`
+const READ_INTERVAL_ERROR_PARTS: [&str; 3] = ["expected seconds from ", " to ", ", or off"];
+
+/// Length of `READ_INTERVAL_ERROR`
+const READ_INTERVAL_ERROR_LEN: usize = READ_INTERVAL_ERROR_PARTS[0].len()
+    + decimal_len(MIN_READ_INTERVAL)
+    + READ_INTERVAL_ERROR_PARTS[1].len()
+    + decimal_len(MAX_READ_INTERVAL)
+    + READ_INTERVAL_ERROR_PARTS[2].len();
+
+/// Error of `parse_read_interval`, built from `MIN_READ_INTERVAL` and `MAX_READ_INTERVAL` so it follows them
+const READ_INTERVAL_ERROR: &str = {
+    /// The message as bytes
+    const BYTES: [u8; READ_INTERVAL_ERROR_LEN] = read_interval_error();
+    match core::str::from_utf8(&BYTES) {
+        Ok(message) => message,
+        Err(_) => "expected seconds, or off",
+    }
+};
+
+/// Writes the message of `READ_INTERVAL_ERROR`
+const fn read_interval_error() -> [u8; READ_INTERVAL_ERROR_LEN] {
+    let mut message = [0; READ_INTERVAL_ERROR_LEN];
+    let at = write_bytes(&mut message, 0, READ_INTERVAL_ERROR_PARTS[0].as_bytes());
+    let at = write_decimal(&mut message, at, MIN_READ_INTERVAL);
+    let at = write_bytes(&mut message, at, READ_INTERVAL_ERROR_PARTS[1].as_bytes());
+    let at = write_decimal(&mut message, at, MAX_READ_INTERVAL);
+    write_bytes(&mut message, at, READ_INTERVAL_ERROR_PARTS[2].as_bytes());
+    message
+}
+
+/// Number of decimal digits of `value`
+const fn decimal_len(value: u64) -> usize {
+    match value.checked_ilog10() {
+        Some(log) => log as usize + 1,
+        None => 1,
+    }
+}
+
+/// Copies `bytes` into `buffer` from `at`, returns the position behind them
+const fn write_bytes(buffer: &mut [u8], at: usize, bytes: &[u8]) -> usize {
+    let mut i = 0;
+    while i < bytes.len() {
+        buffer[at + i] = bytes[i];
+        i += 1;
+    }
+    at + bytes.len()
+}
+
+/// Writes `value` in decimal into `buffer` from `at`, returns the position behind it
+#[allow(clippy::cast_possible_truncation)]
+const fn write_decimal(buffer: &mut [u8], at: usize, mut value: u64) -> usize {
+    let end = at + decimal_len(value);
+    let mut i = end;
+    while i > at {
+        i -= 1;
+        buffer[i] = b'0' + (value % 10) as u8;
+        value /= 10;
+    }
+    end
+}
+
+/// Parses a read interval in seconds within the bounds the watchdog is sized for
+fn parse_read_interval(secs: &str) -> Result<u32, &'static str> {
+    match secs.parse::<u32>() {
+        Ok(secs) if (MIN_READ_INTERVAL..=MAX_READ_INTERVAL).contains(&u64::from(secs)) => Ok(secs),
+        _ => Err(READ_INTERVAL_ERROR),
+    }
+}
+
+/// Parses a date and time given as `YYYY-MM-DD HH:MM:SS`
+#[cfg(feature = "rtc")]
+fn parse_date_time(date: &str, time: &str) -> Result<DateTime, &'static str> {
+    /// Error returned on any parse error
+    const INVALID: &str = "invalid time, expected YYYY-MM-DD HH:MM:SS";
+
+    let mut date_parts = date.split('-');
+    let mut time_parts = time.split(':');
+    let mut next_date = || date_parts.next().ok_or(INVALID)?.parse::<u16>().map_err(|_| INVALID);
+    let year = next_date()?;
+    let month = u8::try_from(next_date()?).map_err(|_| INVALID)?;
+    let day = u8::try_from(next_date()?).map_err(|_| INVALID)?;
+    let mut next_time = || time_parts.next().ok_or(INVALID)?.parse::<u8>().map_err(|_| INVALID);
+    let hour = next_time()?;
+    let minute = next_time()?;
+    let second = next_time()?;
+
+    DateTime::new(year, month, day, hour, minute, second).ok_or(INVALID)
+}
+
+/// Parses the arguments of the simulate command: `sim <co2> <temp> <rh> <voc> <aqi>`
+#[cfg(feature = "debug-serial")]
+fn parse_simulate<'a>(args: &mut impl Iterator<Item = &'a str>) -> Result<Command, &'static str> {
+    /// Usage hint returned on any parse error
+    const USAGE: &str = "usage: sim <co2 ppm> <temp C> <rh %> <voc ppb> <aqi 1-5>";
+
+    let mut next = || args.next().ok_or(USAGE);
+    let co2 = next()?.parse().map_err(|_| USAGE)?;
+    let temperature = next()?.parse().map_err(|_| USAGE)?;
+    let humidity = next()?.parse().map_err(|_| USAGE)?;
+    let voc = next()?.parse().map_err(|_| USAGE)?;
+    let air_quality = match next()? {
+        "1" => AirQualityIndex::Excellent,
+        "2" => AirQualityIndex::Good,
+        "3" => AirQualityIndex::Moderate,
+        "4" => AirQualityIndex::Poor,
+        "5" => AirQualityIndex::Unhealthy,
+        _ => return Err(USAGE),
+    };
+
+    Ok(Command::Simulate {
+        co2,
+        temperature,
+        humidity,
+        voc,
+        air_quality,
+    })
+}
+
+/// Runs a command line and returns the response text
+#[allow(clippy::too_many_lines)]
+async fn run_command(line: &str) -> String<RESPONSE_CAPACITY> {
+    let mut response: String<RESPONSE_CAPACITY> = String::new();
+
+    let command = match parse_command(line) {
+        Ok(command) => command,
+        Err(e) => {
+            let _ = writeln!(response, "error: {e}\r");
+            return response;
+        }
+    };
+
+    match command {
+        Command::Help => {
+            let _ = writeln!(response, "air-quality-monitor {FIRMWARE_VERSION}\r");
+            let _ = writeln!(response, "diag            print diagnostics counters\r");
+            let _ = writeln!(response, "night on|off    switch night mode\r");
+            let _ = writeln!(response, "freeze          hold the current screen, again to release\r");
+            let _ = writeln!(response, "redraw          repaint the whole screen\r");
+            let _ = writeln!(response, "unit            switch between Celsius and Fahrenheit\r");
+            let _ = writeln!(response, "profile [eco|normal|performance]  power profile\r");
+            let _ = writeln!(response, "interval [<secs>|off]  read interval in seconds\r");
+            let _ = writeln!(response, "climate [arid|temperate|humid]  humidity model\r");
+            let _ = writeln!(response, "voc [ethanol|tvoc]  VOC figure shown\r");
+            let _ = writeln!(response, "longterm on|off long-term humidity correction\r");
+            let _ = writeln!(response, "adaptive on|off adaptive humidity calibration\r");
+            let _ = writeln!(response, "humidity [<rh>|off]  humidity offset from a reference\r");
+            let _ = writeln!(response, "verbose [on|off]  per-reading defmt logs\r");
+            let _ = writeln!(response, "time            print the current time\r");
+            let _ = writeln!(response, "baseline        reset the ENS160 baseline in fresh air\r");
+            let _ = writeln!(response, "ens160          dump the ENS160 registers\r");
+            let _ = writeln!(response, "storage         shut down until reconnected\r");
+            let _ = writeln!(response, "export [raw|5m|1h]  readings as CSV, raw or averaged\r");
+            let _ = writeln!(response, "stream on|off   each reading as a JSON line\r");
+            let _ = writeln!(response, "stats           print the session statistics\r");
+            let _ = writeln!(response, "extremes [reset session|all]  print or reset min/max\r");
+            let _ = writeln!(response, "clear-history   start the chart, stats and export over\r");
+            let _ = writeln!(response, "factory-reset   restore defaults, setup runs again\r");
+            #[cfg(feature = "rtc")]
+            let _ = writeln!(response, "time YYYY-MM-DD HH:MM:SS  set the clock\r");
+            #[cfg(feature = "debug-serial")]
+            let _ = writeln!(response, "sim <co2> <temp> <rh> <voc> <aqi>  inject sensor data\r");
+            #[cfg(feature = "debug-serial")]
+            let _ = writeln!(response, "screenshot      dump the screen as hex\r");
+            #[cfg(feature = "debug-serial")]
+            let _ = writeln!(response, "timing          task loop timing\r");
+        }
+        Command::Diagnostics => {
+            let diagnostics = get_diagnostics().await;
+            let _ = write!(
+                response,
+                "i2c errors: aht21={} ens160={} display={}",
+                diagnostics.i2c_errors(BusDevice::Aht21),
+                diagnostics.i2c_errors(BusDevice::Ens160),
+                diagnostics.i2c_errors(BusDevice::Display)
+            );
+            #[cfg(feature = "rtc")]
+            let _ = write!(response, " rtc={}", diagnostics.i2c_errors(BusDevice::Rtc));
+            let _ = writeln!(response, "\r");
+            let _ = writeln!(response, "i2c clock: {} kHz\r", i2c_speed::frequency().await / 1000);
+            let _ = writeln!(
+                response,
+                "display redraws: {} flushed, {} coalesced\r",
+                diagnostics.redraws(),
+                diagnostics.coalesced_redraws()
+            );
+            match diagnostics.ens160_valid_for() {
+                Some(valid_for) => {
+                    let _ = writeln!(response, "ens160 valid for {}s\r", valid_for.as_secs());
+                }
+                None => {
+                    let _ = writeln!(response, "ens160 not valid\r");
+                }
+            }
+            // Raw temperature and calibrated humidity, a mismatch with the display can explain eCO2 discrepancies
+            if let Some(compensation) = diagnostics.ens160_compensation() {
+                let _ = writeln!(
+                    response,
+                    "ens160 compensation: {:.2} C, {}% ({} from {:.2}%)\r",
+                    compensation.temperature,
+                    compensation.written_humidity,
+                    compensation.rounding,
+                    compensation.humidity
+                );
+            }
+        }
+        Command::Verbose(None) => {
+            let _ = writeln!(
+                response,
+                "verbose {}\r",
+                if should_log_verbose() { "on" } else { "off" }
+            );
+        }
+        Command::Verbose(Some(verbose)) => {
+            set_verbose(verbose);
+            info!("Verbose logging {}", if verbose { "on" } else { "off" });
+            let _ = writeln!(response, "ok\r");
+        }
+        Command::FreshAirBaseline => {
+            request_fresh_air_baseline();
+            let _ = writeln!(
+                response,
+                "ok, keep the device in fresh air (~420 ppm) for the next minutes\r"
+            );
+            let _ = writeln!(
+                response,
+                "the ENS160 restarts, readings are unstable for about an hour\r"
+            );
+        }
+        Command::Ens160Registers => match request_ens160_register_dump().await {
+            Ok(dump) => write_ens160_registers(&mut response, &dump),
+            Err(e) => {
+                let _ = writeln!(response, "error: {e}\r");
+            }
+        },
+        Command::StorageMode => {
+            send_event(Event::EnterStorageMode).await;
+            let _ = writeln!(response, "ok, entering storage mode, disconnect now\r");
+            let _ = writeln!(response, "connect the serial console again to wake the device\r");
+        }
+        Command::Export(_) => {
+            // Streamed by `run_line`
+        }
+        Command::Stream(stream) => {
+            // Readings queued before were not asked for
+            READING_CHANNEL.clear();
+            STREAM_READINGS.store(stream, Ordering::Relaxed);
+            let _ = writeln!(response, "ok\r");
+        }
+        Command::Stats => {
+            let (exceedances, duration) = {
+                let state = SYSTEM_STATE.lock().await;
+                (state.get_co2_exceedances(), state.get_session_duration())
+            };
+            let _ = writeln!(
+                response,
+                "co2 above {CO2_EXCEEDANCE_THRESHOLD} ppm: {exceedances} times in {}m\r",
+                duration.as_secs() / 60
+            );
+        }
+        Command::Extremes => {
+            let (session, all_time) = {
+                let state = SYSTEM_STATE.lock().await;
+                (state.get_extremes_of(ExtremesScope::Session), state.get_extremes())
+            };
+            write_extremes(&mut response, ExtremesScope::Session, &session);
+            write_extremes(&mut response, ExtremesScope::AllTime, &all_time);
+        }
+        Command::ResetExtremes(scope) => {
+            send_event(Event::ResetExtremes(scope)).await;
+            let _ = writeln!(response, "ok\r");
+        }
+        Command::ClearHistory => {
+            send_event(Event::ClearHistory).await;
+            let _ = writeln!(response, "ok\r");
+        }
+        Command::Redraw => {
+            send_event(Event::ForceRedraw).await;
+            let _ = writeln!(response, "ok\r");
+        }
+        Command::ToggleUnit => {
+            send_event(Event::ToggleTemperatureUnit).await;
+            let _ = writeln!(response, "ok\r");
+        }
+        Command::FactoryReset => match settings_store::factory_reset().await {
+            Ok(()) => {
+                let _ = writeln!(response, "ok, reconnect to run the setup\r");
+            }
+            Err(e) => {
+                let _ = writeln!(response, "error: {e}\r");
+            }
+        },
+        Command::Time => {
+            #[cfg(feature = "rtc")]
+            match rtc::now().await {
+                Some(date_time) => {
+                    let _ = writeln!(response, "{date_time}\r");
+                }
+                None => {
+                    let _ = writeln!(response, "no RTC time\r");
+                }
+            }
+            let _ = writeln!(response, "uptime {}s\r", Instant::now().as_secs());
+        }
+        #[cfg(feature = "rtc")]
+        Command::SetTime(date_time) => {
+            rtc::set_time(date_time);
+            let _ = writeln!(response, "ok\r");
+        }
+        Command::NightMode(night_mode) => {
+            send_event(Event::SetNightMode(night_mode)).await;
+            let _ = writeln!(response, "ok\r");
+        }
+        Command::Freeze => {
+            let frozen = SYSTEM_STATE.lock().await.is_display_frozen();
+            send_event(Event::SetDisplayFreeze(!frozen)).await;
+            if frozen {
+                let _ = writeln!(response, "ok, display unfrozen\r");
+            } else {
+                let _ = writeln!(
+                    response,
+                    "ok, display frozen for {} min, freeze again to release\r",
+                    DISPLAY_FREEZE_TIMEOUT.as_secs() / 60
+                );
+            }
+        }
+        Command::Interval(None) => {
+            let (read_interval, read_interval_override) = {
+                let state = SYSTEM_STATE.lock().await;
+                (state.get_read_interval(), state.get_read_interval_override())
+            };
+            let source = if read_interval_override.is_some() {
+                "set"
+            } else {
+                "from the profile or charging"
+            };
+            let _ = writeln!(response, "read every {read_interval}s ({source})\r");
+        }
+        Command::Interval(Some(secs)) => {
+            send_event(Event::SetReadInterval(secs)).await;
+            let _ = writeln!(response, "ok, applies from the next reading\r");
+        }
+        Command::Profile(None) => {
+            let profile = SYSTEM_STATE.lock().await.get_settings().power_profile;
+            let params = profile.params();
+            let _ = write!(
+                response,
+                "{}: read every {}s, {} ens160 samples, ens160 sleep {}",
+                profile.name(),
+                params.read_interval,
+                params.ens160_samples,
+                if params.ens160_sleep { "on" } else { "off" }
+            );
+            match params.display_timeout {
+                Some(timeout) => {
+                    let _ = writeln!(response, ", display off after {}s\r", timeout.as_secs());
+                }
+                None => {
+                    let _ = writeln!(response, ", display always on\r");
+                }
+            }
+        }
+        Command::Climate(None) => {
+            let climate = SYSTEM_STATE.lock().await.get_settings().humidity_calibration.climate;
+            let _ = writeln!(response, "{}\r", climate.name());
+        }
+        Command::Climate(Some(climate)) => {
+            let mut settings = SYSTEM_STATE.lock().await.get_settings();
+            settings.humidity_calibration.climate = climate;
+            match settings_store::apply_settings(settings).await {
+                Ok(()) => {
+                    let _ = writeln!(response, "ok\r");
+                }
+                Err(e) => {
+                    let _ = writeln!(response, "error: {e}\r");
+                }
+            }
+        }
+        Command::Voc(None) => {
+            let voc_kind = SYSTEM_STATE.lock().await.get_settings().voc_kind;
+            let _ = writeln!(response, "{}\r", voc_kind.name());
+        }
+        Command::Voc(Some(voc_kind)) => {
+            let mut settings = SYSTEM_STATE.lock().await.get_settings();
+            settings.voc_kind = voc_kind;
+            match settings_store::apply_settings(settings).await {
+                Ok(()) => {
+                    let _ = writeln!(response, "ok, shown from the next reading\r");
+                }
+                Err(e) => {
+                    let _ = writeln!(response, "error: {e}\r");
+                }
+            }
+        }
+        Command::LongTermCorrection(long_term_correction) => {
+            let mut settings = SYSTEM_STATE.lock().await.get_settings();
+            settings.humidity_calibration.long_term_correction = long_term_correction;
+            match settings_store::apply_settings(settings).await {
+                Ok(()) => {
+                    let _ = writeln!(response, "ok\r");
+                }
+                Err(e) => {
+                    let _ = writeln!(response, "error: {e}\r");
+                }
+            }
+        }
+        Command::AdaptiveHumidity(adaptive) => {
+            let mut settings = SYSTEM_STATE.lock().await.get_settings();
+            settings.humidity_calibration.adaptive = adaptive;
+            match settings_store::apply_settings(settings).await {
+                Ok(()) => {
+                    let _ = writeln!(response, "ok\r");
+                }
+                Err(e) => {
+                    let _ = writeln!(response, "error: {e}\r");
+                }
+            }
+        }
+        Command::HumidityOffset(None) => {
+            let settings = SYSTEM_STATE.lock().await.get_settings();
+            let _ = writeln!(
+                response,
+                "offset {:.1}%, adaptive calibration {}\r",
+                settings.user_humidity_offset,
+                if settings.humidity_calibration.adaptive {
+                    "on"
+                } else {
+                    "off"
+                }
+            );
+        }
+        Command::HumidityOffset(Some(reference)) => {
+            let (mut settings, last_sensor_data) = {
+                let state = SYSTEM_STATE.lock().await;
+                (state.get_settings(), state.last_sensor_data.clone())
+            };
+            let Some(data) = last_sensor_data else {
+                let _ = writeln!(response, "error: no reading yet\r");
+                return response;
+            };
+            let offset = settings.calibrate_humidity_offset(reference, data.humidity);
+            match settings_store::apply_settings(settings).await {
+                Ok(()) => {
+                    let _ = writeln!(
+                        response,
+                        "ok, offset set to {offset:.1}%, takes effect with the next reading\r"
+                    );
+                }
+                Err(e) => {
+                    let _ = writeln!(response, "error: {e}\r");
+                }
+            }
+        }
+        Command::ClearHumidityOffset => {
+            let mut settings = SYSTEM_STATE.lock().await.get_settings();
+            settings.user_humidity_offset = 0.0;
+            match settings_store::apply_settings(settings).await {
+                Ok(()) => {
+                    let _ = writeln!(response, "ok\r");
+                }
+                Err(e) => {
+                    let _ = writeln!(response, "error: {e}\r");
+                }
+            }
+        }
+        Command::Profile(Some(power_profile)) => {
+            let mut settings = SYSTEM_STATE.lock().await.get_settings();
+            settings.power_profile = power_profile;
+            match settings_store::apply_settings(settings).await {
+                Ok(()) => {
+                    let _ = writeln!(response, "ok, takes effect after the next reading\r");
+                }
+                Err(e) => {
+                    let _ = writeln!(response, "error: {e}\r");
+                }
+            }
+        }
+        #[cfg(feature = "debug-serial")]
+        Command::Timing => {
+            for (name, task_id) in TIMED_TASKS {
+                let timing = get_loop_timing(task_id).await;
+                if timing.count == 0 {
+                    let _ = writeln!(response, "{name}: no iterations yet\r");
+                } else {
+                    let _ = writeln!(
+                        response,
+                        "{name}: n={} min={}ms avg={}ms max={}ms\r",
+                        timing.count,
+                        timing.min.as_millis(),
+                        timing.average().as_millis(),
+                        timing.max.as_millis()
+                    );
+                }
+            }
+        }
+        #[cfg(feature = "debug-serial")]
+        Command::Simulate {
+            co2,
+            temperature,
+            humidity,
+            voc,
+            air_quality,
+        } => {
+            warn!("Injecting simulated sensor data: CO2 {} ppm", co2);
+            let voc_kind = SYSTEM_STATE.lock().await.get_settings().voc_kind;
+            // Simulated values stand in for both raw and corrected readings, they are shown until the next real read
+            send_event(Event::SensorData {
+                temperature,
+                raw_temperature: temperature,
+                humidity,
+                raw_humidity: humidity,
+                dew_point: dew_point(temperature, humidity),
+                co2,
+                co2_suspect: false,
+                voc,
+                voc_kind,
+                air_quality,
+                settling: false,
+            })
+            .await;
+            let _ = writeln!(response, "ok\r");
+        }
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_interval_error_names_the_bounds() {
+        assert_eq!(
+            READ_INTERVAL_ERROR,
+            format!("expected seconds from {MIN_READ_INTERVAL} to {MAX_READ_INTERVAL}, or off")
+        );
+        assert_eq!(parse_read_interval("10"), Err(READ_INTERVAL_ERROR));
+        assert_eq!(parse_read_interval("60"), Ok(60));
+    }
+}
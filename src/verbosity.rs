@@ -0,0 +1,23 @@
+//! Runtime switch for the chatty per-cycle defmt logs
+//!
+//! The per-reading lines of the sensor task and the humidity calibrator flood the log of a healthy unit, but are the
+//! first thing needed when a unit misbehaves in the field. They are only logged in verbose mode, which can be turned
+//! on and off over the serial console without reflashing. Warnings, errors and state changes are always logged.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the per-cycle logs are on after boot
+const VERBOSE_BY_DEFAULT: bool = false;
+
+/// Current log verbosity, not persisted so a forgotten debugging session ends with the next reboot
+static VERBOSE: AtomicBool = AtomicBool::new(VERBOSE_BY_DEFAULT);
+
+/// Whether the chatty per-cycle lines should be logged
+pub fn should_log_verbose() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
+/// Turns the chatty per-cycle lines on or off
+pub fn set_verbose(verbose: bool) {
+    VERBOSE.store(verbose, Ordering::Relaxed);
+}
@@ -1,127 +1,187 @@
 //! VSYS voltage measurement task
+//!
+//! The ADC and its channel are set up once and reused for every measurement. The measurement cadence is the
+//! `vsys_interval` of the power profile, which can be overridden at build time in `power_profile::OVERRIDES`.
+//!
+//! This task is the only source of the charging state as well as the battery percentage. VBUS is not read, its
+//! detection is unreliable due to the RP2350 E9 erratum, so charging is told from VSYS rising above
+//! `CHARGING_VOLTAGE_THRESHOLD` instead.
 
 use defmt::{error, info};
+#[cfg(target_os = "none")]
 use embassy_rp::{
     Peri,
-    adc::{Adc, Async, Channel, Config, Error},
+    adc::{Adc, Async, Channel, Error},
     gpio::Pull,
-    peripherals::{ADC, PIN_29},
+    peripherals::PIN_29,
 };
+#[cfg(feature = "debug-serial")]
+use embassy_time::Instant;
 use embassy_time::{Duration, Timer, with_timeout};
+#[cfg(target_os = "none")]
 use moving_median::MovingMedian;
 
+#[cfg(feature = "debug-serial")]
+use crate::diagnostics::record_loop_time;
 use crate::{
-    Irqs,
     event::{Event, send_event},
+    storage_mode,
+    system_state::SYSTEM_STATE,
     watchdog::{TaskId, report_task_failure, report_task_success},
 };
 
-/// Interval for periodic voltage measurements
-static INTERVAL: Duration = Duration::from_secs(4);
-
 /// Voltage threshold for determining charging state (above this = charging)
 const CHARGING_VOLTAGE_THRESHOLD: f32 = 4.4;
 
+/// Battery percentage below which a low battery warning is raised once while on battery
+pub const LOW_BATTERY_WARNING_PERCENT: u8 = 10;
+
+/// Battery percentage above which the low battery warning clears again, above `LOW_BATTERY_WARNING_PERCENT` so a
+/// level wavering around the threshold does not raise it over and over
+pub const LOW_BATTERY_WARNING_CLEAR_PERCENT: u8 = 15;
+
 /// Median window size for voltage measurements when on battery power
 const MEDIAN_WINDOW_SIZE: usize = 5;
 
+// Measurement chain from the VSYS pin to the ADC code, board specific, check these when retargeting to another board.
+// VSYS goes through a divider onto GPIO29, which the ADC samples against its reference voltage. The offset is added
+// to the converted voltage afterwards. An ADC code of 0 is treated as a failed read, see `read_voltage`.
+
+/// Reference voltage of the ADC, the voltage a full scale code stands for
+const ADC_REF_VOLTAGE: f32 = 3.3;
+
+/// Ratio of the divider between VSYS and GPIO29, the pin sees a third of VSYS on the Pico layout
+const VOLTAGE_DIVIDER: f32 = 3.0;
+
+/// Number of ADC codes, the RP2350 ADC has a resolution of 12 bits
+const ADC_RESOLUTION: f32 = 4096.0;
+
 /// Vsys voltage offset - calibrated by measuring actual voltage supplied as opposed to what we can measure on the VSYS pin
 /// For whatever reason the waveshare boards have a considerably lower voltage on the VSYS pin than what is actually supplied,
 /// this is true for powering from USB or battery both.
 const VSYS_VOLTAGE_OFFSET: f32 = 0.27;
 
+/// Time given to the ADC after setting it up, before the first measurement
+const ADC_SETUP_DELAY: Duration = Duration::from_millis(100);
+
+/// Maximum time a single ADC conversion may take before the measurement counts as failed
+const ADC_READ_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// VSYS voltage task, measures with the ADC set up by the caller, which binds its interrupt
+#[cfg(target_os = "none")]
 #[embassy_executor::task]
-pub async fn vsys_voltage_task(mut p_adc: Peri<'static, ADC>, mut p_pin29: Peri<'static, PIN_29>) {
+pub async fn vsys_voltage_task(mut adc: Adc<'static, Async>, p_pin29: Peri<'static, PIN_29>) {
+    // The task owns the ADC for good, nothing else measures with it, so it is kept
+    let mut channel = Channel::new_pin(p_pin29, Pull::None);
+    Timer::after(ADC_SETUP_DELAY).await;
+
     let mut voltage_median = MovingMedian::<f32, MEDIAN_WINDOW_SIZE>::new();
 
     // Track previous states to only send events on changes
     let mut prev_charging_state: Option<bool> = None;
     let mut prev_battery_percentage: Option<u8> = None;
+    // Whether the low battery warning was raised, armed again by charging or a level above the clear threshold
+    let mut low_battery_warned = false;
 
     info!("VSYS voltage task initialized successfully");
 
+    // Measure right away, the sensor task checks for USB power early on to shorten the warmup after a warm reboot
+    let mut first_measurement = true;
+
     loop {
-        // Wait for periodic measurement trigger
-        Timer::after(INTERVAL).await;
-
-        let adc_peri = p_adc.reborrow();
-        let pin_peri = p_pin29.reborrow();
-
-        '_adc: {
-            // Initialize ADC and channel for this measurement session
-            let mut adc = Adc::new(adc_peri, Irqs, Config::default());
-            let mut channel = Channel::new_pin(pin_peri, Pull::None);
-            Timer::after_millis(100).await; // small delay to ensure ADC is ready
-
-            match read_voltage(&mut adc, &mut channel).await {
-                Ok(voltage) => {
-                    // Determine charging state based on VSYS voltage
-                    let is_charging = voltage > CHARGING_VOLTAGE_THRESHOLD;
-
-                    let final_voltage = if is_charging {
-                        // When charging/external power, use direct measurement (no median filtering)
-                        voltage
+        // Wait for periodic measurement trigger, the interval is set by the power profile
+        if !first_measurement {
+            let interval = SYSTEM_STATE
+                .lock()
+                .await
+                .get_settings()
+                .power_profile
+                .params()
+                .vsys_interval;
+            Timer::after(interval).await;
+        }
+        first_measurement = false;
+        storage_mode::park_if_active("VSYS").await;
+
+        #[cfg(feature = "debug-serial")]
+        let iteration_start = Instant::now();
+
+        match read_voltage(&mut adc, &mut channel).await {
+            Ok(voltage) => {
+                // Determine charging state based on VSYS voltage
+                let is_charging = voltage > CHARGING_VOLTAGE_THRESHOLD;
+
+                let final_voltage = if is_charging {
+                    // When charging/external power, use direct measurement (no median filtering)
+                    voltage
+                } else {
+                    // When on battery power, use moving median of 5 measurements
+                    voltage_median.add_value(voltage);
+                    voltage_median.median()
+                };
+
+                let battery_percentage = voltage_to_percentage(final_voltage);
+
+                // Send events only when states change
+                let charging_state_changed = prev_charging_state != Some(is_charging);
+                let battery_level_changed = !is_charging && prev_battery_percentage != Some(battery_percentage);
+
+                // Handle charging state changes
+                if charging_state_changed {
+                    if is_charging {
+                        send_event(Event::BatteryCharging).await;
+                        info!("State change: Now charging ({}V)", final_voltage);
                     } else {
-                        // When on battery power, use moving median of 5 measurements
-                        voltage_median.add_value(voltage);
-                        voltage_median.median()
-                    };
-
-                    let battery_percentage = voltage_to_percentage(final_voltage);
-
-                    // Send events only when states change
-                    let charging_state_changed = prev_charging_state != Some(is_charging);
-                    let battery_level_changed = !is_charging && prev_battery_percentage != Some(battery_percentage);
-
-                    // Handle charging state changes
-                    if charging_state_changed {
-                        if is_charging {
-                            send_event(Event::BatteryCharging).await;
-                            info!("State change: Now charging ({}V)", final_voltage);
-                        } else {
-                            send_event(Event::BatteryLevel(battery_percentage)).await;
-                            info!(
-                                "State change: Now on battery ({}V, {}%)",
-                                final_voltage, battery_percentage
-                            );
-                        }
-                        prev_charging_state = Some(is_charging);
-                    }
-                    // Handle battery level changes (only when not charging and no charging state change)
-                    else if battery_level_changed {
                         send_event(Event::BatteryLevel(battery_percentage)).await;
-                        info!("Battery level change: {}% ({}V)", battery_percentage, final_voltage);
-                    }
-
-                    // Update previous battery percentage when on battery
-                    if !is_charging {
-                        prev_battery_percentage = Some(battery_percentage);
+                        info!(
+                            "State change: Now on battery ({}V, {}%)",
+                            final_voltage, battery_percentage
+                        );
                     }
+                    prev_charging_state = Some(is_charging);
+                }
+                // Handle battery level changes (only when not charging and no charging state change)
+                else if battery_level_changed {
+                    send_event(Event::BatteryLevel(battery_percentage)).await;
+                    info!("Battery level change: {}% ({}V)", battery_percentage, final_voltage);
+                }
 
-                    // Report task success for watchdog health monitoring
-                    report_task_success(TaskId::Vsys).await;
+                // Update previous battery percentage when on battery
+                if !is_charging {
+                    prev_battery_percentage = Some(battery_percentage);
                 }
-                Err(e) => {
-                    error!("Could not read voltage: {}", e);
-                    // Report task failure for watchdog health monitoring
-                    report_task_failure(TaskId::Vsys).await;
-                    info!("VSYS task: failed iteration, reporting failure to watchdog");
+
+                if is_charging || battery_percentage > LOW_BATTERY_WARNING_CLEAR_PERCENT {
+                    low_battery_warned = false;
+                } else if battery_percentage < LOW_BATTERY_WARNING_PERCENT && !low_battery_warned {
+                    send_event(Event::BatteryLow).await;
+                    info!("Battery low: {}% ({}V)", battery_percentage, final_voltage);
+                    low_battery_warned = true;
                 }
+
+                // Report task success for watchdog health monitoring
+                report_task_success(TaskId::Vsys).await;
+            }
+            Err(e) => {
+                error!("Could not read voltage: {}", e);
+                // Report task failure for watchdog health monitoring
+                report_task_failure(TaskId::Vsys).await;
+                info!("VSYS task: failed iteration, reporting failure to watchdog");
             }
         }
+        #[cfg(feature = "debug-serial")]
+        record_loop_time(TaskId::Vsys, iteration_start).await;
     }
 }
 
 /// Reads ADC value and converts it to voltage
+#[cfg(target_os = "none")]
 async fn read_voltage(adc: &mut Adc<'_, Async>, channel: &mut Channel<'_>) -> Result<f32, Error> {
-    match with_timeout(Duration::from_millis(200), adc.read(channel)).await {
-        Ok(Ok(adc_value)) => {
-            if adc_value == 0 {
-                error!("ADC value is zero, indicating a possible read error");
-                return Err(Error::ConversionFailed);
-            }
-            Ok(adc_value_to_voltage(adc_value))
-        }
+    match with_timeout(ADC_READ_TIMEOUT, adc.read(channel)).await {
+        Ok(Ok(adc_value)) => adc_value_to_voltage(adc_value).ok_or_else(|| {
+            error!("ADC value is zero, indicating a possible read error");
+            Error::ConversionFailed
+        }),
         Ok(Err(e)) => {
             error!("ADC read error: {}", e);
             Err(e)
@@ -133,13 +193,11 @@ async fn read_voltage(adc: &mut Adc<'_, Async>, channel: &mut Channel<'_>) -> Re
     }
 }
 
-/// Converts ADC value to voltage
-fn adc_value_to_voltage(adc_value: u16) -> f32 {
-    // Convert ADC value to voltage (assuming 3.3V reference and 12-bit resolution)
-    const ADC_REF_VOLTAGE: f32 = 3.3;
-    const VOLTAGE_DIVIDER: f32 = 3.0;
-    const ADC_MAX_VALUE: f32 = 4096.0; // 12-bit ADC
-    f32::from(adc_value) * VOLTAGE_DIVIDER * (ADC_REF_VOLTAGE / ADC_MAX_VALUE) + VSYS_VOLTAGE_OFFSET
+/// Converts ADC value to VSYS voltage, `None` for a code of 0 which VSYS never reads as, a failed conversion
+/// A mid-scale code of 2048 maps to 3.3V * 3 / 2 + 0.27V = 5.22V, USB power. Full scale is 10.17V.
+fn adc_value_to_voltage(adc_value: u16) -> Option<f32> {
+    (adc_value != 0)
+        .then(|| f32::from(adc_value) * VOLTAGE_DIVIDER * (ADC_REF_VOLTAGE / ADC_RESOLUTION) + VSYS_VOLTAGE_OFFSET)
 }
 
 /// Converts voltage to battery percentage
@@ -158,3 +216,40 @@ fn voltage_to_percentage(voltage: f32) -> u8 {
     let percentage_u8 = percentage as u8;
     percentage_u8
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts that `adc_value` converts to `expected` volts, to within a millivolt
+    fn assert_voltage(adc_value: u16, expected: f32) {
+        let voltage = adc_value_to_voltage(adc_value).unwrap();
+        assert!(
+            (voltage - expected).abs() < 0.001,
+            "{adc_value} read as {voltage}V, expected {expected}V"
+        );
+    }
+
+    #[test]
+    fn zero_code_is_a_failed_conversion() {
+        assert_eq!(adc_value_to_voltage(0), None);
+    }
+
+    #[test]
+    fn mid_scale_is_usb_power() {
+        assert_voltage(2048, 5.22);
+        assert!(adc_value_to_voltage(2048).unwrap() > CHARGING_VOLTAGE_THRESHOLD);
+    }
+
+    #[test]
+    fn full_scale() {
+        assert_voltage(4095, 10.1676);
+    }
+
+    #[test]
+    fn battery_range_maps_to_percentage() {
+        assert_eq!(voltage_to_percentage(2.9), 0);
+        assert_eq!(voltage_to_percentage(3.55), 50);
+        assert_eq!(voltage_to_percentage(4.2), 100);
+    }
+}
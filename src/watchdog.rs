@@ -1,15 +1,113 @@
 //! Watchdog task to reset the system if it stops being fed
-use defmt::{Format, info};
-use embassy_rp::{Peri, peripherals::WATCHDOG, watchdog::Watchdog};
-use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+//!
+//! Timing relationships:
+//! - A task stays unhealthy from a reported failure until its next reported success.
+//! - Each task records when it last reported success. The sensor and VSYS tasks, and the display task if
+//!   `DISPLAY_STALE_AFTER` is set, also turn unhealthy once that is longer ago than their staleness threshold, so a
+//!   task hanging without ever reporting a failure is caught as well. The thresholds are checked against the
+//!   reporting periods at compile time below.
+//! - The countdown is pushed out on every health check that finds all tasks healthy, so a reset happens
+//!   `COUNTDOWN_TIMEOUT` after the last all-healthy check, give or take one `HEALTH_CHECK_INTERVAL`.
+//! - The slowest task is the sensor task. It reports once after initialization, then waits out the warmup and
+//!   reports once per read interval of the power profile, each iteration taking up to `MAX_ITERATION_TIME`
+//!   including retries. The countdown therefore has to comfortably exceed warmup plus the longest read interval plus
+//!   one iteration, otherwise a single failed read followed by a healthy one, or a slow first cycle, could already
+//!   reset the system.
+//!   This is checked at compile time below.
+//! - The hardware watchdog is only started once the countdown expired and is never fed, its timeout just sets
+//!   how long it takes until the actual reset.
+//! - In storage mode the tasks are parked and stop reporting, health checks are suspended until a reset is requested.
+//! - When the countdown expires, a connected serial console is alerted with the stale tasks. The alert is sent while
+//!   the hardware watchdog runs down, so it does not delay the reset.
+//! - When the countdown expires, each unhealthy task is logged with the time since its last success before the reset.
+//! - With the `no-watchdog` feature an expired countdown is only logged, so the device can be held in a debugger
+//!   without being reset. Leaving storage mode still resets the system.
+//! - Before a reset the reason, with the stale tasks, is recorded in a watchdog scratch register. It is picked up by
+//!   `init_reset_reason` on the next boot, so the display can tell why the system came back up. A watchdog reset is
+//!   also recorded in flash with the first stale task, so the next boot logs it even after a loss of power.
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use defmt::{Format, error, info, warn};
+#[cfg(target_os = "none")]
+use embassy_rp::{Peri, pac, peripherals::WATCHDOG, watchdog::Watchdog};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex, signal::Signal};
 use embassy_time::{Duration, Instant, Timer};
+use heapless::String;
+
+use crate::{
+    power_profile::{MAX_READ_INTERVAL, MAX_VSYS_INTERVAL},
+    sensor::{MAX_ITERATION_TIME, READ_INTERVAL_JITTER, WARMUP_TIME},
+    settings_store, storage_mode, usb_serial,
+};
 
-/// How long our custom countdown timer runs before triggering a reset (15 minutes)
-const COUNTDOWN_TIMEOUT: Duration = Duration::from_secs(520);
+/// How long our custom countdown timer runs before triggering a reset (20 minutes)
+const COUNTDOWN_TIMEOUT: Duration = Duration::from_secs(1200);
 /// How often we check task health and update our countdown
 const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
 /// Hardware watchdog timeout (short, used only for actual reset)
 const HARDWARE_WATCHDOG_TIMEOUT: Duration = Duration::from_millis(8000);
+/// Whether the serial console is alerted with the stale tasks before an unhealthy system is reset
+/// The serial console is the only connection of the device, so the alert only reaches a host connected at the time
+const ALERT_BEFORE_RESET: bool = true;
+/// Maximum length of the alert message
+const ALERT_CAPACITY: usize = 96;
+
+/// Watchdog scratch register recording why the system was reset, it survives resets but not a loss of power
+/// (scratch register 0 marks a warmed up ENS160, 4 to 7 are used by the bootrom)
+const RESET_REASON_SCRATCH: usize = 1;
+/// Upper half of `RESET_REASON_SCRATCH` while it holds a recorded reset ("WR"), anything else is a power-on
+const RESET_REASON_MARKER: u32 = 0x5752_0000;
+/// Bit of `RESET_REASON_SCRATCH` set for a reset of an unhealthy system, the stale tasks are in the low byte
+const RESET_REASON_UNHEALTHY: u32 = 1 << 8;
+/// Bit of `RESET_REASON_SCRATCH` set for the reset leaving storage mode
+const RESET_REASON_STORAGE_EXIT: u32 = 1 << 9;
+
+/// Raw `RESET_REASON_SCRATCH` value found at boot
+static BOOT_RESET_REASON: AtomicU32 = AtomicU32::new(0);
+
+/// Longest time in seconds between two reports of the slowest task (sensor warmup, the longest read interval of any
+/// power profile stretched by the jitter and one iteration with all retries)
+const SLOWEST_TASK_PERIOD_SECS: u64 = WARMUP_TIME + MAX_READ_INTERVAL + READ_INTERVAL_JITTER + MAX_ITERATION_TIME;
+
+/// Time without a reported success after which the sensor task counts as stalled, its longest reporting period
+const SENSOR_STALE_AFTER: Duration = Duration::from_secs(SLOWEST_TASK_PERIOD_SECS);
+/// Time without a reported success after which the VSYS task counts as stalled
+const VSYS_STALE_AFTER: Duration = Duration::from_secs(60);
+/// Time without a reported success after which the display task counts as stalled, `None` to only go by reported
+/// failures. It is only sure to get a command with every reading, so this must cover the slowest sensor period
+/// E.g. `Some(Duration::from_secs(900))`
+const DISPLAY_STALE_AFTER: Option<Duration> = None;
+
+// A single late measurement must not make the VSYS task look stalled, its ADC read adds at most a fraction of a second
+const _: () = assert!(
+    VSYS_STALE_AFTER.as_ticks() >= 2 * MAX_VSYS_INTERVAL.as_ticks(),
+    "VSYS_STALE_AFTER must be at least twice the longest VSYS interval"
+);
+
+const _: () = {
+    if let Some(stale_after) = DISPLAY_STALE_AFTER {
+        assert!(
+            stale_after.as_secs() >= SLOWEST_TASK_PERIOD_SECS,
+            "DISPLAY_STALE_AFTER must cover the slowest sensor period"
+        );
+    }
+};
+
+// A healthy but slow sensor must not be able to trip the countdown. Two health check intervals of slack cover the
+// granularity at which the countdown is reset and evaluated.
+const _: () = assert!(
+    COUNTDOWN_TIMEOUT.as_secs() >= SLOWEST_TASK_PERIOD_SECS + 2 * HEALTH_CHECK_INTERVAL.as_secs(),
+    "COUNTDOWN_TIMEOUT must comfortably exceed sensor warmup plus one read interval and iteration"
+);
+
+// The RP2350 watchdog counter is 24 bits wide at 1 MHz, longer timeouts cannot be programmed
+const _: () = assert!(
+    HARDWARE_WATCHDOG_TIMEOUT.as_micros() <= 0x00FF_FFFF,
+    "HARDWARE_WATCHDOG_TIMEOUT exceeds the hardware watchdog range"
+);
 
 /// Task identifiers for health tracking
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Format)]
@@ -26,20 +124,111 @@ pub enum TaskId {
     ModeSwitch,
 }
 
+impl TaskId {
+    /// All tracked tasks, in the order of their health slots
+    const ALL: [Self; 5] = [
+        Self::Sensor,
+        Self::Display,
+        Self::Vsys,
+        Self::Orchestrator,
+        Self::ModeSwitch,
+    ];
+
+    /// Time without a reported success after which the task counts as stalled, `None` if it only turns unhealthy on
+    /// a reported failure
+    const fn stale_after(self) -> Option<Duration> {
+        match self {
+            Self::Sensor => Some(SENSOR_STALE_AFTER),
+            Self::Vsys => Some(VSYS_STALE_AFTER),
+            Self::Display => DISPLAY_STALE_AFTER,
+            Self::Orchestrator | Self::ModeSwitch => None,
+        }
+    }
+}
+
+/// Why the system was last reset
+/// A panic halts the device (panic-probe) instead of resetting it, so there is no panic reset to tell apart
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Format)]
+pub enum ResetReason {
+    /// Power-on, or a reset not initiated by the firmware (reset button, debugger, bootloader)
+    PowerOn,
+    /// The watchdog reset an unhealthy system, the stale tasks as bits by `TaskId`
+    Watchdog(u8),
+    /// The system was reset to leave storage mode
+    StorageExit,
+}
+
+impl ResetReason {
+    /// Short description of the reason, as shown on the display
+    pub const fn message(self) -> &'static str {
+        match self {
+            Self::PowerOn => "Powered on",
+            Self::Watchdog(_) => "Watchdog reset",
+            Self::StorageExit => "Left storage mode",
+        }
+    }
+
+    /// Tasks that had gone stale before a watchdog reset, none for the other reasons
+    pub fn stale_tasks(self) -> impl Iterator<Item = TaskId> {
+        let stale = match self {
+            Self::Watchdog(stale) => stale,
+            Self::PowerOn | Self::StorageExit => 0,
+        };
+        TaskId::ALL
+            .into_iter()
+            .filter(move |task_id| stale & (1 << *task_id as u8) != 0)
+    }
+
+    /// Encodes the reason for `RESET_REASON_SCRATCH`
+    fn to_scratch(self) -> u32 {
+        match self {
+            Self::PowerOn => 0,
+            Self::Watchdog(stale) => RESET_REASON_MARKER | RESET_REASON_UNHEALTHY | u32::from(stale),
+            Self::StorageExit => RESET_REASON_MARKER | RESET_REASON_STORAGE_EXIT,
+        }
+    }
+
+    /// Decodes a `RESET_REASON_SCRATCH` value, anything not recorded by us is a power-on
+    #[allow(clippy::cast_possible_truncation)]
+    const fn from_scratch(value: u32) -> Self {
+        if value & 0xFFFF_0000 != RESET_REASON_MARKER {
+            Self::PowerOn
+        } else if value & RESET_REASON_UNHEALTHY != 0 {
+            Self::Watchdog(value as u8)
+        } else if value & RESET_REASON_STORAGE_EXIT != 0 {
+            Self::StorageExit
+        } else {
+            Self::PowerOn
+        }
+    }
+}
+
 /// Task health tracking
 #[derive(Copy, Clone, Format, Debug)]
 struct TaskHealth {
-    /// Whether this task is currently healthy
-    is_healthy: bool,
+    /// When this task last reported success, `None` before its first success and after a reported failure
+    last_success: Option<Instant>,
 }
 
 impl TaskHealth {
     /// Create a new `TaskHealth` instance with default unhealthy state
     const fn new() -> Self {
-        Self { is_healthy: false }
+        Self { last_success: None }
     }
 }
 
+/// Tasks that are unhealthy at `now`, given when each task last reported success (`None` since a failure or startup)
+/// A task is healthy if it reported success since its last failure, and recently enough if it can go stale
+fn unhealthy_tasks_at(last_success: [Option<Instant>; 5], now: Instant) -> impl Iterator<Item = TaskId> {
+    TaskId::ALL.into_iter().filter(
+        move |task_id| match (last_success[*task_id as usize], task_id.stale_after()) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(last_success), Some(stale_after)) => now.saturating_duration_since(last_success) > stale_after,
+        },
+    )
+}
+
 /// System health state with custom countdown timer
 struct SystemHealth {
     /// Health status of each task
@@ -60,40 +249,41 @@ impl SystemHealth {
         }
     }
 
-    /// report a task as succeeded
-    const fn set_task_succeeded(&mut self, task_id: TaskId) {
+    /// report a task as succeeded at `now`
+    const fn set_task_succeeded(&mut self, task_id: TaskId, now: Instant) {
         let index = task_id as usize;
-        self.tasks[index].is_healthy = true;
+        self.tasks[index].last_success = Some(now);
     }
 
     /// report a task as failed
     const fn set_task_failed(&mut self, task_id: TaskId) {
         let index = task_id as usize;
-        self.tasks[index].is_healthy = false;
+        self.tasks[index].last_success = None;
     }
 
-    /// Update overall health status based on individual task health
-    fn update_overall_health(&mut self) {
+    /// Update overall health status at `now` based on individual task health
+    fn update_overall_health(&mut self, now: Instant) {
         let was_all_healthy = self.all_healthy;
 
-        // A task is considered healthy if it has reported success at least once
-        self.all_healthy = self.tasks.iter().all(|task| task.is_healthy);
+        // A task is considered healthy if it has reported success since its last failure, and not too long ago
+        let all_healthy = self.unhealthy_tasks(now).next().is_none();
+        self.all_healthy = all_healthy;
 
         if self.all_healthy && !was_all_healthy {
             info!("All tasks healthy - resetting countdown timer");
             // Reset countdown when all tasks become healthy
-            self.countdown_deadline = Some(Instant::now() + COUNTDOWN_TIMEOUT);
+            self.countdown_deadline = Some(now + COUNTDOWN_TIMEOUT);
         } else if !self.all_healthy && self.countdown_deadline.is_none() {
             info!("Some tasks unhealthy - countdown timer started");
             // Start countdown when tasks become unhealthy for the first time
-            self.countdown_deadline = Some(Instant::now() + COUNTDOWN_TIMEOUT);
+            self.countdown_deadline = Some(now + COUNTDOWN_TIMEOUT);
         }
     }
 
-    /// Reset the countdown timer (equivalent to feeding the watchdog)
-    fn reset_countdown(&mut self) {
+    /// Reset the countdown timer at `now` (equivalent to feeding the watchdog)
+    fn reset_countdown(&mut self, now: Instant) {
         if self.all_healthy {
-            self.countdown_deadline = Some(Instant::now() + COUNTDOWN_TIMEOUT);
+            self.countdown_deadline = Some(now + COUNTDOWN_TIMEOUT);
             info!(
                 "Countdown timer reset - {} seconds until reset",
                 COUNTDOWN_TIMEOUT.as_secs()
@@ -101,20 +291,58 @@ impl SystemHealth {
         }
     }
 
-    /// Check if countdown has expired and we should trigger hardware watchdog
-    fn should_trigger_reset(&self) -> bool {
-        self.countdown_deadline
-            .is_some_and(|deadline| Instant::now() >= deadline)
+    /// Tasks that have not reported success since their last failure, not at all yet, or not within their staleness
+    /// threshold
+    fn unhealthy_tasks(&self, now: Instant) -> impl Iterator<Item = TaskId> + '_ {
+        unhealthy_tasks_at(self.tasks.map(|task| task.last_success), now)
+    }
+
+    /// Logs each unhealthy task with the time since its last success, so the RTT log of the last boot names the
+    /// culprits of a reset
+    fn log_unhealthy_tasks(&self, now: Instant) {
+        for task_id in self.unhealthy_tasks(now) {
+            if let Some(last_success) = self.tasks[task_id as usize].last_success {
+                error!(
+                    "Task {} stale, last success {}s ago",
+                    task_id,
+                    now.saturating_duration_since(last_success).as_secs()
+                );
+            } else {
+                error!("Task {} unhealthy, no success since a failure or startup", task_id);
+            }
+        }
+    }
+
+    /// Check if countdown has expired at `now` and we should trigger hardware watchdog
+    fn should_trigger_reset(&self, now: Instant) -> bool {
+        self.countdown_deadline.is_some_and(|deadline| now >= deadline)
+    }
+
+    /// Checks the health at `now`, feeding the countdown while all tasks are healthy
+    /// Returns whether the countdown expired with unhealthy tasks, so the system has to be reset
+    fn check(&mut self, now: Instant) -> bool {
+        self.update_overall_health(now);
+
+        // Reset countdown if all tasks are healthy
+        if self.all_healthy {
+            self.reset_countdown(now);
+            info!("All tasks healthy");
+        }
+
+        !self.all_healthy && self.should_trigger_reset(now)
     }
 }
 
 /// Global system health tracker
 static SYSTEM_HEALTH: Mutex<CriticalSectionRawMutex, SystemHealth> = Mutex::new(SystemHealth::new());
 
+/// Signal requesting a system reset while in storage mode
+static RESET_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
 /// Report a successful task iteration
 pub async fn report_task_success(task_id: TaskId) {
     let mut health = SYSTEM_HEALTH.lock().await;
-    health.set_task_succeeded(task_id);
+    health.set_task_succeeded(task_id, Instant::now());
 }
 
 /// Report a failed task iteration
@@ -123,6 +351,67 @@ pub async fn report_task_failure(task_id: TaskId) {
     health.set_task_failed(task_id);
 }
 
+/// Request a system reset, used to leave storage mode
+pub fn request_reset() {
+    RESET_SIGNAL.signal(());
+}
+
+/// Picks up the reset reason recorded before the last reset and clears it, so the next unrecorded reset reads as a
+/// power-on. Called once early at boot
+#[cfg(target_os = "none")]
+pub fn init_reset_reason() {
+    let scratch = pac::WATCHDOG.scratch(RESET_REASON_SCRATCH);
+    BOOT_RESET_REASON.store(scratch.read(), Ordering::Relaxed);
+    scratch.write_value(0);
+    info!("Reset reason: {}", reset_reason());
+}
+
+/// Why the system was last reset
+pub fn reset_reason() -> ResetReason {
+    ResetReason::from_scratch(BOOT_RESET_REASON.load(Ordering::Relaxed))
+}
+
+/// Records the reason of the upcoming reset for the next boot
+#[cfg(target_os = "none")]
+fn record_reset_reason(reason: ResetReason) {
+    pac::WATCHDOG
+        .scratch(RESET_REASON_SCRATCH)
+        .write_value(reason.to_scratch());
+}
+
+/// Alerts a connected serial console which tasks went stale, the system resets right after
+async fn alert_unhealthy_tasks() {
+    let mut message: String<ALERT_CAPACITY> = String::new();
+    let _ = write!(message, "device unhealthy, stale tasks:");
+    {
+        let health = SYSTEM_HEALTH.lock().await;
+        for task_id in health.unhealthy_tasks(Instant::now()) {
+            let _ = write!(message, " {task_id:?}");
+        }
+    }
+    let _ = write!(message, ", resetting in {}s", HARDWARE_WATCHDOG_TIMEOUT.as_secs());
+    usb_serial::send_alert(&message);
+}
+
+/// Start the hardware watchdog and don't feed it, which resets the system
+#[cfg(target_os = "none")]
+async fn reset_system(wd: Peri<'static, WATCHDOG>) -> ! {
+    let mut watchdog = Watchdog::new(wd);
+    watchdog.pause_on_debug(false); // Don't pause during debug - we want the reset
+    watchdog.start(HARDWARE_WATCHDOG_TIMEOUT);
+
+    info!(
+        "Hardware watchdog started - system will reset in {}ms",
+        HARDWARE_WATCHDOG_TIMEOUT.as_millis()
+    );
+
+    // Wait for hardware watchdog to reset the system
+    loop {
+        Timer::after_secs(1).await;
+    }
+}
+
+#[cfg(target_os = "none")]
 #[embassy_executor::task]
 pub async fn watchdog_task(wd: Peri<'static, WATCHDOG>) {
     info!(
@@ -130,42 +419,120 @@ pub async fn watchdog_task(wd: Peri<'static, WATCHDOG>) {
         COUNTDOWN_TIMEOUT.as_secs(),
         HEALTH_CHECK_INTERVAL.as_secs()
     );
+    #[cfg(feature = "no-watchdog")]
+    warn!("WATCHDOG DISABLED - unhealthy tasks will not reset the system, never ship this build");
 
     loop {
-        // Check system health and update countdown
-        let (all_healthy, should_reset) = {
-            let mut health = SYSTEM_HEALTH.lock().await;
-            health.update_overall_health();
-
-            // Reset countdown if all tasks are healthy
-            if health.all_healthy {
-                health.reset_countdown();
-                info!("All tasks healthy");
-            }
+        // Parked tasks no longer report, only wait for the request to leave storage mode
+        if storage_mode::is_active().await {
+            RESET_SIGNAL.wait().await;
+            info!("Leaving storage mode - system will reset");
+            record_reset_reason(ResetReason::StorageExit);
+            reset_system(wd).await;
+        }
 
-            (health.all_healthy, health.should_trigger_reset())
-        };
+        // Check system health and update countdown
+        let now = Instant::now();
+        let should_reset = SYSTEM_HEALTH.lock().await.check(now);
 
-        if !all_healthy && should_reset {
+        if should_reset && cfg!(feature = "no-watchdog") {
+            warn!("WATCHDOG DISABLED - countdown expired, not resetting");
+            SYSTEM_HEALTH.lock().await.log_unhealthy_tasks(now);
+        } else if should_reset {
             info!("Countdown expired - system will reset due to unhealthy tasks");
+            SYSTEM_HEALTH.lock().await.log_unhealthy_tasks(now);
+            let (stale, first_stale) = {
+                let health = SYSTEM_HEALTH.lock().await;
+                (
+                    health
+                        .unhealthy_tasks(now)
+                        .fold(0, |stale, task_id| stale | 1 << task_id as u8),
+                    health.unhealthy_tasks(now).next(),
+                )
+            };
+            record_reset_reason(ResetReason::Watchdog(stale));
+            // The scratch register is lost with the power, the flash keeps the culprit for good
+            settings_store::record_watchdog_reset(first_stale).await;
+            if ALERT_BEFORE_RESET {
+                alert_unhealthy_tasks().await;
+            }
+            reset_system(wd).await;
+        }
 
-            // Initialize hardware watchdog and don't feed it - this will cause reset
-            let mut watchdog = Watchdog::new(wd);
-            watchdog.pause_on_debug(false); // Don't pause during debug - we want the reset
-            watchdog.start(HARDWARE_WATCHDOG_TIMEOUT);
+        // Wait before next health check
+        Timer::after(HEALTH_CHECK_INTERVAL).await;
+    }
+}
 
-            info!(
-                "Hardware watchdog started - system will reset in {}ms",
-                HARDWARE_WATCHDOG_TIMEOUT.as_millis()
-            );
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            // Wait for hardware watchdog to reset the system
-            loop {
-                Timer::after_secs(1).await;
+    /// Runs the health checks of the first `duration` after boot, every task but the sensor reports success right
+    /// before each check, the sensor at `sensor_reports`. Returns when the countdown expired, if it did
+    fn simulate(duration: Duration, sensor_reports: &[Duration]) -> Option<Duration> {
+        let boot = Instant::from_ticks(0);
+        let mut health = SystemHealth::new();
+        let mut elapsed = Duration::from_ticks(0);
+        while elapsed <= duration {
+            let now = boot + elapsed;
+            for task_id in TaskId::ALL.into_iter().filter(|task_id| *task_id != TaskId::Sensor) {
+                health.set_task_succeeded(task_id, now);
+            }
+            if let Some(&report) = sensor_reports.iter().rev().find(|report| **report <= elapsed) {
+                health.set_task_succeeded(TaskId::Sensor, boot + report);
             }
+            if health.check(now) {
+                return Some(elapsed);
+            }
+            elapsed += HEALTH_CHECK_INTERVAL;
         }
+        None
+    }
 
-        // Wait before next health check
-        Timer::after(HEALTH_CHECK_INTERVAL).await;
+    #[test]
+    fn slow_first_sensor_cycle_is_healthy() {
+        // The first reading follows the warmup, the longest read interval and the slowest iteration
+        let first = Duration::from_secs(WARMUP_TIME + MAX_READ_INTERVAL + MAX_ITERATION_TIME);
+        assert!(first < COUNTDOWN_TIMEOUT);
+        let period = Duration::from_secs(MAX_READ_INTERVAL + MAX_ITERATION_TIME);
+        let duration = Duration::from_secs(3 * 3600);
+        let cycles = u32::try_from(duration.as_secs() / period.as_secs()).unwrap();
+        let reports: Vec<_> = (0..cycles).map(|cycle| first + period * cycle).collect();
+
+        assert_eq!(simulate(duration, &reports), None);
+    }
+
+    #[test]
+    fn missing_sensor_reports_reset_the_system() {
+        assert!(simulate(Duration::from_secs(3600), &[]).is_some_and(|at| at >= COUNTDOWN_TIMEOUT));
+    }
+
+    #[test]
+    fn stalled_sensor_resets_the_system() {
+        let first = Duration::from_secs(WARMUP_TIME + MAX_READ_INTERVAL + MAX_ITERATION_TIME);
+        let reset_at = simulate(Duration::from_secs(3 * 3600), &[first]);
+
+        assert!(reset_at.is_some_and(|at| at > first + SENSOR_STALE_AFTER));
+    }
+
+    #[test]
+    fn sensor_goes_stale_after_its_slowest_period() {
+        let last_sensor_success = Instant::from_secs(100);
+        let stale_at = last_sensor_success + SENSOR_STALE_AFTER;
+        let unhealthy = |now: Instant, orchestrator: Option<Instant>| {
+            let mut last_success = [Some(now); 5];
+            last_success[TaskId::Sensor as usize] = Some(last_sensor_success);
+            last_success[TaskId::Orchestrator as usize] = orchestrator;
+            unhealthy_tasks_at(last_success, now).collect::<Vec<_>>()
+        };
+
+        assert_eq!(unhealthy(stale_at, Some(stale_at)), []);
+        assert_eq!(
+            unhealthy(stale_at + Duration::from_secs(1), Some(stale_at)),
+            [TaskId::Sensor]
+        );
+        // A task without a staleness threshold is only unhealthy after a failure
+        assert_eq!(unhealthy(stale_at, None), [TaskId::Orchestrator]);
     }
 }